@@ -1,13 +1,16 @@
 use super::{
     cli,
-    engine::Engine,
+    config::{self, Config, ConfigFile},
+    engine::{analysis::Approach, breakpoint::Condition, Engine},
     graphics, layer,
     logger::LOGGER,
     make_error,
     message::{self, Message},
-    object,
-    r#type::{Color, LayerId, SessionInfo, TimeFormat, TimeUnit},
-    shared_access, Error, Result, Shared,
+    object, report,
+    r#type::{Color, IntoStorageDuration, LayerId, ObjectName, SessionInfo, TimeFormat, TimeUnit},
+    shared_access,
+    storage::{dump::SessionDump, postgres::migration, StorageManager},
+    Error, Result, Shared,
 };
 use kiss3d::{
     camera::FirstPerson,
@@ -16,13 +19,15 @@ use kiss3d::{
     scene::SceneNode,
     window::{CanvasSetup, NumSamples, Window},
 };
-use layer::Layer;
+use layer::{Layer, ObjectOverride};
 use log::{error, info};
 use nalgebra::{Point2, Point3 /*Vector2*/};
 use object::{GenCoord, Object};
 use ptree;
 use std::{
     fmt,
+    fs::File,
+    io::{BufReader, BufWriter, Write},
     path::PathBuf,
     sync::mpsc::TryRecvError,
 };
@@ -72,7 +77,7 @@ pub struct App {
 }
 
 impl App {
-    pub fn new(log_filter: log::LevelFilter) -> Self {
+    pub fn new(log_filter: log::LevelFilter, config: &Config) -> Result<Self> {
         super::logger::Logger::init(log_filter).expect("unable to initialize logging system");
 
         let mut window = Window::new_with_setup(
@@ -94,16 +99,16 @@ impl App {
         camera.rebind_right_key(Some(Key::D));
 
         let root_scene_node = window.scene().clone();
-        let engine = Engine::init(root_scene_node).expect("unable to initialize the engine");
+        let engine = Engine::init(root_scene_node, config)?;
 
-        Self {
+        Ok(Self {
             window,
             engine,
             camera: camera,
             state: State::Paused.into(),
             new_layer: None,
             new_default_obj_index: 0,
-        }
+        })
     }
 
     pub fn run(&mut self, history: Option<PathBuf>) -> Result<()> {
@@ -132,7 +137,10 @@ impl App {
             let loop_time = loop_end - loop_begin;
 
             let frame_delta_ns = loop_time;
-            self.engine.advance_time(frame_delta_ns, advance_vtime)?;
+            if let Some(hit) = self.engine.advance_time(frame_delta_ns, advance_vtime)? {
+                println!("--- {} ---", hit);
+                self.pause_simulation()?;
+            }
         }
 
         Ok(())
@@ -153,8 +161,38 @@ impl App {
         match msg {
             Message::Layer(layer_msg) => match layer_msg {
                 LayerMsg::AddObject(msg) => self.add_object_into_layer(msg),
+                LayerMsg::ModifyObject(msg) => {
+                    let object_override = self.override_layer_object(msg.name)?;
+
+                    if msg.location.is_some() {
+                        object_override.location = msg.location;
+                    }
+
+                    if msg.velocity.is_some() {
+                        object_override.velocity = msg.velocity;
+                    }
+
+                    if msg.velocity_scale.is_some() {
+                        object_override.velocity_scale = msg.velocity_scale;
+                    }
+
+                    Ok(())
+                }
+                LayerMsg::RemoveObject(msg) => {
+                    self.override_layer_object(msg.name)?.removed = true;
+
+                    Ok(())
+                }
+                LayerMsg::SetMass(msg) => {
+                    self.override_layer_object(msg.name)?.mass = Some(msg.mass);
+
+                    Ok(())
+                }
+                LayerMsg::RmObject(msg) => self.new_layer.as_mut().unwrap().remove_object(&msg.name),
+                LayerMsg::EditObject(msg) => self.edit_layer_object(msg),
             },
-            Message::Submit(_) => self.submit_layer(),
+            Message::Undo(_) => self.new_layer.as_mut().unwrap().undo(),
+            Message::Submit(msg) => self.submit_layer(msg),
             Message::Cancel(_) => {
                 let layer = self.new_layer.take().unwrap();
 
@@ -188,7 +226,31 @@ impl App {
         match msg {
             Message::NewLayer(msg) => self.new_layer(msg),
             Message::RemoveLayer(msg) => self.engine.remove_layer(&msg.name),
+            Message::MergeLayer(msg) => self.merge_layer(msg),
+            Message::CopyLayer(msg) => self.copy_layer(msg),
             Message::RenameLayer(msg) => self.engine.rename_layer(&msg.old_name, &msg.new_name),
+            Message::Undo(_) => {
+                let operation = self.engine.undo()?;
+
+                println!("undone: {}", operation);
+
+                Ok(())
+            }
+            Message::Redo(_) => {
+                let operation = self.engine.redo()?;
+
+                println!("redone: {}", operation);
+
+                Ok(())
+            }
+            Message::PurgeJournal(msg) => self.engine.purge_journal(msg.all),
+            Message::Compact(msg) => {
+                let removed_count = self.engine.compact(msg.keyframe_step)?;
+
+                println!("removed locations: {}", removed_count);
+
+                Ok(())
+            }
             Message::GlobalHelp(_) | Message::GlobalHelpShort(_) => {
                 let max_name = Message::cli_list()
                     .iter()
@@ -305,13 +367,17 @@ impl App {
             Message::NewSession(msg) => self.engine.new_session(msg.name),
             Message::SaveSession(msg) => self.engine.save_session(msg.name),
             Message::LoadSession(msg) => {
-                self.new_default_obj_index = (self.engine.load_session(msg.name)? + 1) as usize;
+                self.new_default_obj_index = (self.engine.load_session(msg.name, msg.read_only)? + 1) as usize;
 
                 Ok(())
             },
             Message::RenameSession(msg) => self.engine.rename_session(msg.old_name, msg.new_name),
             Message::DeleteSession(msg) => self.engine.delete_session(msg.name),
+            Message::CloneSession(msg) => self.engine.clone_session(msg.name.as_ref(), &msg.new_name),
+            Message::ExportSession(msg) => self.export_session(msg),
+            Message::ImportSession(msg) => self.import_session(msg),
             // Message::RenameObject(msg) if state.is_run() => self.handle_rename_object(msg),
+            Message::ListObjects(msg) if msg.by_layer => self.list_objects_by_layer(),
             Message::ListObjects(_) => self.list_current_objects(),
             Message::Names(_) => {
                 self.engine.toggle_names();
@@ -329,35 +395,185 @@ impl App {
 
                 Ok(())
             }
+            Message::Status(_) => self.print_status(),
+            Message::Break(msg) => self.handle_break_msg(msg),
+            Message::Mark(msg) => self.handle_mark_msg(state, msg),
+            Message::State(msg) => self.print_object_state(msg),
+            Message::ClosestApproach(msg) => self.print_closest_approach(msg),
+            Message::LayerDiff(msg) => self.print_layer_diff(msg),
+            Message::HideGhost(_) => {
+                self.engine.hide_ghost();
+
+                Ok(())
+            }
             unexpected => return Err(Error::UnexpectedMessage(unexpected)),
         }
     }
 
     fn new_layer(&mut self, new_layer_msg: message::NewLayer) -> Result<()> {
+        self.engine.check_writable()?;
+
         let layer_name = new_layer_msg.name;
 
         if self.engine.get_layer_id(&layer_name).is_ok() {
             return Err(make_error!(Error::Layer::LayerAlreadyExists(layer_name)));
         }
 
-        println!("--- creating new layer \"{}\" ---", layer_name);
+        let start_time = new_layer_msg.at.unwrap_or(self.engine.virtual_time());
+        self.engine.check_layer_start_time(start_time)?;
 
-        self.new_layer = Some(Layer::new(layer_name, self.engine.context().session_id()));
+        println!(
+            "--- creating new layer \"{}\" at {} ---",
+            layer_name,
+            TimeFormat::VirtualTimeShort(start_time)
+        );
+
+        self.new_layer = Some(Layer::new(
+            layer_name,
+            self.engine.context().session_id(),
+            start_time,
+        ));
         Ok(())
     }
 
-    fn submit_layer(&mut self) -> Result<()> {
+    fn submit_layer(&mut self, msg: message::Submit) -> Result<()> {
+        let layer = self.new_layer.as_ref().unwrap();
+
+        if msg.force {
+            if let Err(err) = self.engine.validate_layer(layer) {
+                println!("--- forced submit ---\n{}", err);
+            }
+        } else {
+            self.engine.validate_layer(layer)?;
+        }
+
         let layer = self.new_layer.take().unwrap();
 
         println!("--- submit layer \"{}\" ---", layer.name());
         self.engine.add_layer(layer)
-        // TODO ADD CHECKS
     }
 
-    fn list_layers(&mut self, _: message::ListLayers) -> Result<()> {
-        let layers_tree = self.engine.get_session_layers()?;
+    fn merge_layer(&mut self, msg: message::MergeLayer) -> Result<()> {
+        let info = self.engine.merge_layer(&msg.name, msg.dry_run)?;
+
+        let print_names = |title: &str, names: &[String]| {
+            if !names.is_empty() {
+                println!("\t{}: {}", title, names.join(", "));
+            }
+        };
+
+        println!();
+        if msg.dry_run {
+            println!(
+                "--- dry run: merge layer \"{}\" into \"{}\" ---",
+                msg.name, info.parent_name
+            );
+        } else {
+            println!(
+                "--- layer \"{}\" is merged into \"{}\" ---",
+                msg.name, info.parent_name
+            );
+        }
+
+        println!("\tbranch time: {}", TimeFormat::VirtualTimeLong(info.start_time));
+        println!("\treplaced parent's locations: {}", info.replaced_locations);
+        println!("\tmoved locations: {}", info.moved_locations);
+        print_names("moved objects", &info.moved_objects);
+        print_names("overridden objects", &info.overridden_objects);
+        print_names("reparented layers", &info.reparented_layers);
+        print_names("conflicting layers (the merge is refused)", &info.conflicting_layers);
+
+        Ok(())
+    }
+
+    fn copy_layer(&mut self, msg: message::CopyLayer) -> Result<()> {
+        let info = self.engine.copy_layer(
+            &msg.name,
+            msg.to_session.as_ref(),
+            msg.parent.as_ref(),
+            msg.recursive,
+            msg.drop_computed,
+        )?;
+
+        println!();
+        for (src_name, new_name) in info.layers.iter() {
+            println!("\tlayer \"{}\" -> \"{}\"", src_name, new_name);
+        }
+
+        for (src_name, new_name) in info.renamed_objects.iter() {
+            println!("\tobject \"{}\" is renamed to \"{}\"", src_name, new_name);
+        }
+
+        println!("\tcopied objects: {}", info.copied_objects);
+        println!("\tcopied locations: {}", info.copied_locations);
+
+        if !info.skipped_objects.is_empty() {
+            println!(
+                "\tskipped tracks of inherited objects: {}",
+                info.skipped_objects.join(", ")
+            );
+        }
+
+        Ok(())
+    }
+
+    fn list_layers(&mut self, msg: message::ListLayers) -> Result<()> {
+        let (session_name, layers) = self.engine.get_session_layers()?;
+
+        let mut out: Box<dyn Write> = match msg.out {
+            Some(ref path) => Box::new(File::create(path)?),
+            None => Box::new(std::io::stdout()),
+        };
+
+        match msg.format.as_str() {
+            "dot" => write!(out, "{}", report::layers_dot(&session_name, &layers))?,
+            "json" => writeln!(out, "{:#}", report::layers_json(&session_name, &layers))?,
+            _ => {
+                let layers_tree = report::layers_tree(&session_name, &layers);
+
+                ptree::write_tree(&layers_tree, &mut out)?
+            }
+        }
+
+        if let Some(path) = msg.out {
+            println!("layers are written into \"{}\"", path.display());
+        }
+
+        Ok(())
+    }
+
+    fn export_session(&mut self, msg: message::ExportSession) -> Result<()> {
+        let dump = self
+            .engine
+            .export_session(msg.name.as_ref(), msg.with_computed)?;
+
+        dump.write(BufWriter::new(File::create(&msg.out)?))?;
 
-        ptree::print_tree(&layers_tree).map_err(|err| Error::Io(err))
+        println!(
+            "session \"{}\" is exported into \"{}\" (layers: {}, objects: {}, locations: {})",
+            dump.session_name.as_deref().unwrap_or("/unnamed/"),
+            msg.out.display(),
+            dump.layers.len(),
+            dump.objects.len(),
+            dump.locations.len()
+        );
+
+        Ok(())
+    }
+
+    fn import_session(&mut self, msg: message::ImportSession) -> Result<()> {
+        let dump = SessionDump::read(BufReader::new(File::open(&msg.file)?))?;
+        let session_name = self.engine.import_session(&dump, msg.name)?;
+
+        println!(
+            "session \"{}\" is imported (layers: {}, objects: {}, locations: {})",
+            session_name,
+            dump.layers.len(),
+            dump.objects.len(),
+            dump.locations.len()
+        );
+
+        Ok(())
     }
 
     fn list_new_layer_objects(&self) -> Result<()> {
@@ -383,6 +599,63 @@ impl App {
         Ok(())
     }
 
+    fn list_objects_by_layer(&mut self) -> Result<()> {
+        let (layers, overrides) = self.engine.objects_layers()?;
+        let time = self.engine.virtual_time();
+        let context = self.engine.context();
+
+        let print_row = |name: &str, radius: &str, mass: &str, step: &str, overridden_in: &str| {
+            println!(
+                "\t{:<20} {:>10} {:>10} {:>10}  {}",
+                name, radius, mass, step, overridden_in
+            );
+        };
+
+        for (layer_id, layer_name) in layers {
+            let mut actors = context
+                .actors()
+                .iter()
+                .filter(|(_, actor)| actor.object().layer_id() == layer_id)
+                .collect::<Vec<_>>();
+            actors.sort_by(|(_, lhs), (_, rhs)| lhs.object().name().cmp(rhs.object().name()));
+
+            println!();
+            println!(" --- layer \"{}\" objects ---", layer_name);
+
+            if actors.is_empty() {
+                println!("\t/no new objects/");
+                continue;
+            }
+
+            print_row("name", "radius", "mass", "step", "overridden in");
+            for (object_id, actor) in actors {
+                let object = actor.object();
+
+                let mut overridden_in = overrides
+                    .get(object_id)
+                    .map(|layers| layers.join(", "))
+                    .unwrap_or_default();
+
+                if let Some(removed_time) = actor.removed_time() {
+                    overridden_in.push_str(&format!(
+                        " (removed at {})",
+                        TimeFormat::VirtualTimeShort(removed_time)
+                    ));
+                }
+
+                print_row(
+                    object.name(),
+                    &object.radius().to_string(),
+                    &actor.mass_at(time).to_string(),
+                    &TimeFormat::VirtualTimeShort(object.compute_step()).to_string(),
+                    &overridden_in,
+                );
+            }
+        }
+
+        Ok(())
+    }
+
     fn handle_tracks_msg(&mut self, msg: message::Tracks) -> Result<()> {
         match msg.step {
             Some(step) => self.engine.show_tracks(step),
@@ -392,6 +665,313 @@ impl App {
         Ok(())
     }
 
+    fn print_object_state(&mut self, msg: message::State) -> Result<()> {
+        let time = msg.time.unwrap_or(self.engine.virtual_time());
+        let coord = self.engine.object_state(&msg.name, time)?;
+
+        let location = coord.location();
+        let velocity = coord.velocity();
+
+        if msg.json {
+            let state = serde_json::json!({
+                "name": msg.name,
+                "time_ms": coord.time().into_storage_duration(),
+                "time": TimeFormat::VirtualTimeShort(coord.time()).to_string(),
+                "location": [location[0], location[1], location[2]],
+                "velocity": [velocity[0], velocity[1], velocity[2]],
+                "speed": velocity.norm(),
+            });
+
+            println!("{}", state);
+        } else {
+            println!();
+            println!("\"{}\": {{", msg.name);
+            println!("\ttime = {}", TimeFormat::VirtualTimeLong(coord.time()));
+            println!(
+                "\tlocation = {{{}, {}, {}}}",
+                location[0], location[1], location[2]
+            );
+            println!(
+                "\tvelocity = {{{}, {}, {}}}",
+                velocity[0], velocity[1], velocity[2]
+            );
+            println!("\tspeed = {}", velocity.norm());
+            println!("}}");
+        }
+
+        Ok(())
+    }
+
+    fn print_closest_approach(&mut self, msg: message::ClosestApproach) -> Result<()> {
+        let from = msg.from;
+        let print_approach = |lhs: &ObjectName, rhs: &ObjectName, approach: &Approach| {
+            println!(
+                "\t\"{}\" - \"{}\": {} at {} (centers distance {})",
+                lhs,
+                rhs,
+                approach.separation,
+                TimeFormat::VirtualTimeLong(approach.time),
+                approach.distance
+            );
+
+            if let Some(contact_time) = approach.contact_time {
+                println!(
+                    "\t\ttime to contact: {} (at {})",
+                    TimeFormat::VirtualTimeShort(contact_time - from),
+                    TimeFormat::VirtualTimeLong(contact_time)
+                );
+            }
+        };
+
+        println!();
+        if msg.all {
+            let threshold = msg.threshold.unwrap();
+            let approaches = self.engine.close_approaches(threshold, msg.from, msg.to)?;
+
+            println!("--- objects closer than {} ---", threshold);
+            for (lhs, rhs, approach) in approaches.iter() {
+                print_approach(lhs, rhs, approach);
+            }
+        } else {
+            let lhs = msg.lhs.unwrap();
+            let rhs = msg.rhs.unwrap();
+
+            match self.engine.closest_approach(&lhs, &rhs, msg.from, msg.to)? {
+                Some(approach) => {
+                    println!("--- closest approach ---");
+                    print_approach(&lhs, &rhs, &approach);
+
+                    let lhs_location = approach.lhs.location();
+                    let rhs_location = approach.rhs.location();
+                    println!(
+                        "\t\"{}\" location = {{{}, {}, {}}}",
+                        lhs, lhs_location[0], lhs_location[1], lhs_location[2]
+                    );
+                    println!(
+                        "\t\"{}\" location = {{{}, {}, {}}}",
+                        rhs, rhs_location[0], rhs_location[1], rhs_location[2]
+                    );
+                }
+                None => println!("--- no computed tracks of the objects in the time range ---"),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn print_layer_diff(&mut self, msg: message::LayerDiff) -> Result<()> {
+        let diff = self.engine.layer_diff(
+            &msg.lhs,
+            &msg.rhs,
+            msg.from,
+            msg.to,
+            msg.step,
+            msg.eps,
+            msg.ghost,
+        )?;
+
+        let print_times = |title: &str, times: &[chrono::Duration]| {
+            if times.is_empty() {
+                return;
+            }
+
+            let times = times
+                .iter()
+                .map(|time| TimeFormat::VirtualTimeShort(*time).to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            println!("\t\t{}: {}", title, times);
+        };
+
+        println!();
+        println!("--- layer \"{}\" vs layer \"{}\" ---", msg.lhs, msg.rhs);
+        for object in diff.objects.iter() {
+            if object.is_same() {
+                println!("\t\"{}\": same", object.name);
+                continue;
+            }
+
+            println!("\t\"{}\":", object.name);
+
+            if let Some(time) = object.separation_time {
+                println!("\t\tseparates at {}", TimeFormat::VirtualTimeLong(time));
+            }
+
+            let (location_diff, location_diff_time) = object.max_location_diff;
+            println!(
+                "\t\tmax location difference = {} at {}",
+                location_diff,
+                TimeFormat::VirtualTimeShort(location_diff_time)
+            );
+
+            let (velocity_diff, velocity_diff_time) = object.max_velocity_diff;
+            println!(
+                "\t\tmax velocity difference = {} at {}",
+                velocity_diff,
+                TimeFormat::VirtualTimeShort(velocity_diff_time)
+            );
+
+            print_times(
+                &format!("collisions only in \"{}\"", msg.lhs),
+                &object.lhs_only_collisions,
+            );
+            print_times(
+                &format!("collisions only in \"{}\"", msg.rhs),
+                &object.rhs_only_collisions,
+            );
+        }
+
+        for name in diff.lhs_only_objects.iter() {
+            println!("\t\"{}\": only in \"{}\"", name, msg.lhs);
+        }
+
+        for name in diff.rhs_only_objects.iter() {
+            println!("\t\"{}\": only in \"{}\"", name, msg.rhs);
+        }
+
+        Ok(())
+    }
+
+    fn handle_break_msg(&mut self, msg: message::Break) -> Result<()> {
+        use message::BreakCommand;
+
+        match msg.command {
+            BreakCommand::Add {
+                collision,
+                name,
+                other,
+                closer_than,
+                region_min,
+                region_max,
+                faster_than,
+            } => {
+                let condition = if collision {
+                    Condition::Collision { object: name }
+                } else if let Some(distance) = closer_than {
+                    Condition::Proximity {
+                        lhs: name.unwrap(),
+                        rhs: other.unwrap(),
+                        distance,
+                    }
+                } else if let (Some(min), Some(max)) = (region_min, region_max) {
+                    Condition::LeaveRegion {
+                        object: name.unwrap(),
+                        min,
+                        max,
+                    }
+                } else if let Some(speed) = faster_than {
+                    Condition::Speed {
+                        object: name,
+                        speed,
+                    }
+                } else {
+                    return Err(Error::Breakpoint(
+                        "expected one of `--collision`, `--closer-than`, `--region-min` or `--faster-than`".into(),
+                    ));
+                };
+
+                for object_name in condition.object_names() {
+                    if !self.engine.is_object_exists(object_name)? {
+                        return Err(make_error![Error::Layer::ObjectNotFound(
+                            object_name.clone()
+                        )]);
+                    }
+                }
+
+                let id = self.engine.breakpoints_mut().add(condition);
+
+                println!("breakpoint #{} is added", id);
+            }
+            BreakCommand::List => {
+                println!("\n\t-- breakpoints --");
+                for breakpoint in self.engine.breakpoints().iter() {
+                    println!("\t{}", breakpoint);
+                }
+            }
+            BreakCommand::Rm { id: Some(id), .. } => self.engine.breakpoints_mut().remove(id)?,
+            BreakCommand::Rm { id: None, .. } => self.engine.breakpoints_mut().clear(),
+        }
+
+        Ok(())
+    }
+
+    fn handle_mark_msg(&mut self, state: State, msg: message::Mark) -> Result<()> {
+        use message::MarkCommand;
+
+        match msg.command {
+            MarkCommand::Add { name, time } => {
+                let time = time.unwrap_or(self.engine.virtual_time());
+
+                if time < chrono::Duration::zero() {
+                    return Err(Error::Bookmark(
+                        "bookmark's time that lower than zero is forbidden".into(),
+                    ));
+                }
+
+                self.engine.add_bookmark(&name, time)?;
+
+                println!(
+                    "bookmark \"{}\" is added at {}",
+                    name,
+                    TimeFormat::VirtualTimeLong(time)
+                );
+            }
+            MarkCommand::Goto { name } if state.is_run() => {
+                let time = self.engine.bookmark(&name)?.time;
+
+                self.engine.set_virtual_time(time, true);
+            }
+            MarkCommand::Goto { .. } => {
+                return Err(Error::VirtualTime(
+                    "setting virtual time after the simulation has complete is forbidden".into(),
+                ))
+            }
+            MarkCommand::List => {
+                println!("\n\t-- bookmarks --");
+                for bookmark in self.engine.bookmarks() {
+                    println!(
+                        "\t{} {} [layer \"{}\"]",
+                        TimeFormat::VirtualTimeShort(bookmark.time),
+                        bookmark.name,
+                        bookmark.layer_name
+                    );
+                }
+            }
+            MarkCommand::Rm { name } => self.engine.remove_bookmark(&name)?,
+        }
+
+        Ok(())
+    }
+
+    fn goto_bookmark(&mut self, forward: bool) -> Result<()> {
+        let bookmark = if forward {
+            self.engine.next_bookmark()
+        } else {
+            self.engine.prev_bookmark()
+        };
+
+        if let Some(time) = bookmark.map(|bookmark| bookmark.time) {
+            self.engine.set_virtual_time(time, true);
+        }
+
+        Ok(())
+    }
+
+    fn override_layer_object(&mut self, object_name: ObjectName) -> Result<&mut ObjectOverride> {
+        if self.engine.context().actor_id(&object_name).is_none() {
+            return Err(make_error![Error::Layer::ObjectNotFound(object_name)]);
+        }
+
+        log::info! {
+            target: LOG_TARGET,
+            "[layer] override object \"{}\"",
+            object_name
+        }
+
+        Ok(self.new_layer.as_mut().unwrap().override_object(object_name))
+    }
+
     fn add_object_into_layer(&mut self, msg: message::layer::AddObject) -> Result<()> {
         let object_name = msg.name.unwrap_or_else(|| {
             let default_name = format!("object-{}", self.new_default_obj_index);
@@ -407,7 +987,6 @@ impl App {
             object_name
         }
 
-        // TODO check collisions
         if self.engine.is_object_exists(&object_name)? {
             Err(make_error!(Error::Layer::ObjectAlreadyExists(object_name)))
         } else {
@@ -422,12 +1001,48 @@ impl App {
                 msg.step,
             );
 
-            let coord = GenCoord::new(self.engine.virtual_time(), msg.location, msg.velocity);
+            let coord = GenCoord::new(layer.start_time(), msg.location, msg.velocity);
 
             layer.add_object(object, coord)
         }
     }
 
+    fn edit_layer_object(&mut self, msg: message::layer::EditObject) -> Result<()> {
+        log::info! {
+            target: LOG_TARGET,
+            "[layer] edit object \"{}\"",
+            msg.name
+        }
+
+        let layer = self.new_layer.as_mut().unwrap();
+
+        layer.edit_object(&msg.name, |object, coord| {
+            if let Some(location) = msg.location {
+                coord.set_location(location);
+            }
+
+            if let Some(velocity) = msg.velocity {
+                coord.set_velocity(velocity);
+            }
+
+            if let Some(color) = msg.color {
+                object.set_color(color);
+            }
+
+            if let Some(radius) = msg.radius {
+                object.set_radius(radius);
+            }
+
+            if let Some(mass) = msg.mass {
+                object.set_mass(mass);
+            }
+
+            if let Some(step) = msg.step {
+                object.set_compute_step(step);
+            }
+        })
+    }
+
     fn check_window_opened(&mut self) {
         let state = *shared_access![self.state];
         if self.window.should_close() && matches![state, State::Simulating | State::Paused] {
@@ -456,6 +1071,32 @@ impl App {
         Ok(())
     }
 
+    fn print_status(&mut self) -> Result<()> {
+        let state = *shared_access![self.state];
+
+        println!();
+        println!("\tstate: {}", state);
+        println!(
+            "\tvirtual time: {}",
+            TimeFormat::VirtualTimeLong(self.engine.virtual_time())
+        );
+
+        match self.engine.context_progress() {
+            Some(progress) => println!("\tcontext update: {}", progress),
+            None => {
+                let time_range = self.engine.context().time_range();
+
+                println!(
+                    "\tcontext is up to date: [{}; {}]",
+                    TimeFormat::VirtualTimeShort(time_range.start()),
+                    TimeFormat::VirtualTimeShort(time_range.end())
+                );
+            }
+        }
+
+        Ok(())
+    }
+
     fn print_current_session_name(&mut self) -> Result<()> {
         let session_name = self.engine.get_session_name()?;
 
@@ -606,6 +1247,16 @@ impl App {
                 self.engine.set_virtual_time(vtime, true);
                 Ok(())
             }
+            Key::PageDown
+                if matches![action, Action::Press] && shared_access![self.state].is_run() =>
+            {
+                self.goto_bookmark(true)
+            }
+            Key::PageUp
+                if matches![action, Action::Press] && shared_access![self.state].is_run() =>
+            {
+                self.goto_bookmark(false)
+            }
             Key::C if modifiers.contains(Modifiers::Control) && matches![action, Action::Press] => {
                 self.close();
                 Ok(())
@@ -640,9 +1291,15 @@ impl App {
 
     fn draw_state_text(&mut self) {
         let state = *shared_access![self.state];
+        let read_only_label = if self.engine.is_read_only() {
+            " [READ-ONLY]"
+        } else {
+            ""
+        };
+
         self.engine.scene_mut().draw_text(
             &mut self.window,
-            format!("{}", state).as_ref(),
+            format!("{}{}", state, read_only_label).as_ref(),
             Point2::origin(),
             Color::new(1.0, 1.0, 1.0),
         );
@@ -684,6 +1341,9 @@ fn epoch_offset_ns() -> i128 {
 
 #[derive(StructOpt)]
 pub struct Options {
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+
     /// File with command history
     #[structopt(long)]
     pub history_file: Option<PathBuf>,
@@ -691,4 +1351,161 @@ pub struct Options {
     /// Log level filter
     #[structopt(short, long, default_value = "warn")]
     pub log_filter: log::LevelFilter,
+
+    /// Config file (JSON). `apriori.json` in the current directory is used if it exists
+    #[structopt(long)]
+    pub config: Option<PathBuf>,
+
+    /// Storage backend. The `memory` one keeps the sessions only until the app exits
+    #[structopt(long, possible_values = &["postgres", "sqlite", "memory"])]
+    pub storage: Option<String>,
+
+    /// SQLite database file (used with `--storage sqlite`) [default: apriori.sqlite]
+    #[structopt(long)]
+    pub sqlite_file: Option<PathBuf>,
+
+    /// PostgreSQL connection string or URL [default: host=localhost user=postgres]
+    #[structopt(long, env = "APRIORI_DB_URL", hide_env_values = true)]
+    pub db_url: Option<String>,
+
+    /// Postgres schema name. Different schemas isolate the deployments sharing one database
+    /// [default: apriori]
+    #[structopt(long, env = "APRIORI_SCHEMA")]
+    pub schema_name: Option<String>,
+
+    /// Max number of the database connections [default: 10]
+    #[structopt(long)]
+    pub pool_size: Option<u32>,
+
+    /// Database connection timeout in seconds [default: 30]
+    #[structopt(long)]
+    pub connection_timeout: Option<i64>,
+
+    /// How often the current session access time is updated, in seconds [default: 30]
+    #[structopt(long)]
+    pub access_update_time: Option<i64>,
+
+    /// The session which access time isn't updated for that long (in seconds)
+    /// is unlocked [default: the access update time + 10]
+    #[structopt(long)]
+    pub session_max_hang_time: Option<i64>,
+}
+
+#[derive(StructOpt)]
+pub enum Command {
+    /// Manage the database schema migrations (Postgres storage only)
+    Migrate(MigrateCommand),
+}
+
+#[derive(StructOpt)]
+pub enum MigrateCommand {
+    /// Show the applied and the pending migrations
+    Status,
+
+    /// Apply all the pending migrations
+    Up,
+}
+
+impl MigrateCommand {
+    pub fn run(&self, config: &Config) -> Result<()> {
+        match self {
+            Self::Status => {
+                let status = StorageManager::migration_status(config)?;
+
+                println!("\n\t-- schema migrations --");
+                for migration in status.applied.iter() {
+                    println!(
+                        "{:>4} {} [applied {}]",
+                        migration.version, migration.name, migration.applied_at
+                    );
+                }
+
+                for migration in status.pending.iter() {
+                    println!("{:>4} {} [PENDING]", migration.version, migration.name);
+                }
+
+                println!(
+                    "\n\tschema version: {} (latest: {})",
+                    status.current_version(),
+                    migration::latest_version()
+                );
+
+                if status.is_newer() {
+                    println!("\tthe database is newer than the app, update the app");
+                }
+            }
+            Self::Up => {
+                let applied = StorageManager::migrate_up(config)?;
+
+                if applied.is_empty() {
+                    println!("the schema is up to date");
+                }
+
+                for migration in applied {
+                    println!("applied: {:>4} {}", migration.version, migration.name);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Options {
+    /// Merges the options with the config file ones.
+    /// The options (and their environment variables) take precedence.
+    pub fn config(&self) -> Result<Config> {
+        let file = ConfigFile::load(self.config.as_deref())?;
+
+        let storage = self
+            .storage
+            .clone()
+            .or(file.storage)
+            .unwrap_or_else(|| "postgres".into());
+
+        let sqlite_file = self
+            .sqlite_file
+            .clone()
+            .or(file.sqlite_file)
+            .unwrap_or_else(|| config::DEFAULT_SQLITE_FILE.into());
+
+        let access_update_time = self
+            .access_update_time
+            .or(file.access_update_time)
+            .unwrap_or(config::DEFAULT_ACCESS_UPDATE_TIME_SECS);
+
+        let session_max_hang_time = self
+            .session_max_hang_time
+            .or(file.session_max_hang_time)
+            .unwrap_or(access_update_time + config::SESSION_HANG_MARGIN_SECS);
+
+        let config = Config {
+            storage_kind: config::parse_storage_kind(&storage, sqlite_file)?,
+            db_url: self
+                .db_url
+                .clone()
+                .or(file.db_url)
+                .unwrap_or_else(|| config::DEFAULT_DB_URL.into()),
+            schema_name: self
+                .schema_name
+                .clone()
+                .or(file.schema_name)
+                .unwrap_or_else(|| config::DEFAULT_SCHEMA_NAME.into()),
+            pool_size: self
+                .pool_size
+                .or(file.pool_size)
+                .unwrap_or(config::DEFAULT_POOL_SIZE),
+            connection_timeout: chrono::Duration::seconds(
+                self.connection_timeout
+                    .or(file.connection_timeout)
+                    .unwrap_or(config::DEFAULT_CONNECTION_TIMEOUT_SECS),
+            ),
+            access_update_time: chrono::Duration::seconds(access_update_time),
+            session_max_hang_time: chrono::Duration::seconds(session_max_hang_time),
+        };
+
+        config.validate()?;
+
+        Ok(config)
+    }
 }
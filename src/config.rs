@@ -0,0 +1,144 @@
+use {
+    crate::{make_error, storage::StorageKind, Result},
+    serde::Deserialize,
+    std::path::{Path, PathBuf},
+};
+
+pub const DEFAULT_SCHEMA_NAME: &'static str = crate::app::APP_NAME;
+pub const DEFAULT_CONFIG_FILE: &'static str = "apriori.json";
+pub const DEFAULT_DB_URL: &'static str = "host=localhost user=postgres";
+pub const DEFAULT_SQLITE_FILE: &'static str = "apriori.sqlite";
+pub const MAX_SCHEMA_NAME_LEN: usize = 63;
+pub const DEFAULT_POOL_SIZE: u32 = 10;
+pub const DEFAULT_CONNECTION_TIMEOUT_SECS: i64 = 30;
+pub const DEFAULT_ACCESS_UPDATE_TIME_SECS: i64 = 30;
+
+/// The session is unlocked if its access time isn't updated for that long
+/// after the last expected update.
+pub const SESSION_HANG_MARGIN_SECS: i64 = 10;
+
+/// Storage and session settings.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub storage_kind: StorageKind,
+    pub db_url: String,
+
+    /// The Postgres schema holding all the tables and functions.
+    /// Separate schemas let several deployments share one database.
+    pub schema_name: String,
+
+    pub pool_size: u32,
+    pub connection_timeout: chrono::Duration,
+
+    /// How often the access time of the current session is updated.
+    pub access_update_time: chrono::Duration,
+
+    /// The session which access time isn't updated for that long is considered hanged.
+    pub session_max_hang_time: chrono::Duration,
+}
+
+/// The config file entries. All of them are optional,
+/// the command line options and the environment variables take precedence.
+///
+/// The file is a JSON object, the times are in seconds:
+/// ```json
+/// {
+///     "storage": "postgres",
+///     "db_url": "host=localhost user=postgres",
+///     "schema_name": "apriori",
+///     "pool_size": 10,
+///     "connection_timeout": 30,
+///     "access_update_time": 30,
+///     "session_max_hang_time": 40
+/// }
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    pub storage: Option<String>,
+    pub sqlite_file: Option<PathBuf>,
+    pub db_url: Option<String>,
+    pub schema_name: Option<String>,
+    pub pool_size: Option<u32>,
+    pub connection_timeout: Option<i64>,
+    pub access_update_time: Option<i64>,
+    pub session_max_hang_time: Option<i64>,
+}
+
+impl ConfigFile {
+    /// Reads the config file. The missing default config file is treated as an empty one.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => Path::new(DEFAULT_CONFIG_FILE),
+            None => return Ok(Self::default()),
+        };
+
+        let file = std::fs::File::open(path).map_err(|err| {
+            make_error![Error::Config(format!(
+                "unable to open `{}`: {}",
+                path.display(),
+                err
+            ))]
+        })?;
+
+        serde_json::from_reader(std::io::BufReader::new(file)).map_err(|err| {
+            make_error![Error::Config(format!(
+                "unable to parse `{}`: {}",
+                path.display(),
+                err
+            ))]
+        })
+    }
+}
+
+impl Config {
+    pub fn validate(&self) -> Result<()> {
+        // The schema name is substituted into the queries as is.
+        let is_valid_schema_name = self.schema_name.len() <= MAX_SCHEMA_NAME_LEN
+            && self
+                .schema_name
+                .chars()
+                .next()
+                .map_or(false, |c| c.is_ascii_lowercase() || c == '_')
+            && self
+                .schema_name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+
+        if !is_valid_schema_name {
+            return Err(make_error![Error::Config(format!(
+                "invalid schema name `{}`, expected lowercase letters, digits and `_` (at most {} characters)",
+                self.schema_name, MAX_SCHEMA_NAME_LEN
+            ))]);
+        }
+
+        if self.pool_size == 0 {
+            return Err(make_error![Error::Config(
+                "the pool size must be positive".into()
+            )]);
+        }
+
+        if self.session_max_hang_time <= self.access_update_time {
+            return Err(make_error![Error::Config(format!(
+                "the session max hang time ({}s) must be greater than the access update time ({}s)",
+                self.session_max_hang_time.num_seconds(),
+                self.access_update_time.num_seconds()
+            ))]);
+        }
+
+        Ok(())
+    }
+}
+
+pub fn parse_storage_kind(storage: &str, sqlite_file: PathBuf) -> Result<StorageKind> {
+    match storage {
+        "postgres" => Ok(StorageKind::Postgres),
+        "sqlite" => Ok(StorageKind::Sqlite(sqlite_file)),
+        "memory" => Ok(StorageKind::Memory),
+        _ => Err(make_error![Error::Config(format!(
+            "unknown storage `{}`, expected `postgres`, `sqlite` or `memory`",
+            storage
+        ))]),
+    }
+}
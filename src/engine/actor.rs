@@ -1,5 +1,8 @@
 use {
-    crate::object::{GenCoord, Object},
+    crate::{
+        object::{GenCoord, Object},
+        r#type::Mass,
+    },
     std::sync::RwLock
 };
 
@@ -8,6 +11,8 @@ pub struct Actor {
     object: Object,
     last_gen_coord: RwLock<Option<GenCoord>>,
     last_computed_time: chrono::Duration,
+    removed_time: Option<chrono::Duration>,
+    mass_overrides: Vec<(chrono::Duration, Mass)>,
 }
 
 impl Actor {
@@ -16,6 +21,8 @@ impl Actor {
             object,
             last_gen_coord: RwLock::new(None),
             last_computed_time: chrono::Duration::zero(),
+            removed_time: None,
+            mass_overrides: vec![],
         }
     }
 
@@ -38,17 +45,50 @@ impl Actor {
     pub fn set_last_computed_time(&mut self, time: chrono::Duration) {
         self.last_computed_time = time;
     }
+
+    /// Returns the time when the object is removed from the layer.
+    pub fn removed_time(&self) -> Option<chrono::Duration> {
+        self.removed_time
+    }
+
+    pub fn set_removed_time(&mut self, time: Option<chrono::Duration>) {
+        self.removed_time = time;
+    }
+
+    pub fn is_removed_at(&self, time: chrono::Duration) -> bool {
+        self.removed_time.map_or(false, |removed_time| removed_time <= time)
+    }
+
+    /// Adds the mass overridden by a layer from its start time.
+    pub fn add_mass_override(&mut self, start_time: chrono::Duration, mass: Mass) {
+        let idx = self.mass_overrides.partition_point(|(time, _)| *time <= start_time);
+        self.mass_overrides.insert(idx, (start_time, mass));
+    }
+
+    /// Returns the object's mass at the time: the latest override started by then
+    /// or the object's own mass.
+    pub fn mass_at(&self, time: chrono::Duration) -> Mass {
+        self.mass_overrides
+            .iter()
+            .rev()
+            .find(|(start_time, _)| *start_time <= time)
+            .map_or(self.object.mass(), |(_, mass)| *mass)
+    }
 }
 
 impl Clone for Actor {
     fn clone(&self) -> Self {
         let object = self.object.clone();
         let last_computed_time = self.last_computed_time;
+        let removed_time = self.removed_time;
+        let mass_overrides = self.mass_overrides.clone();
 
         Actor {
             object,
             last_gen_coord: RwLock::new(None),
             last_computed_time,
+            removed_time,
+            mass_overrides,
         }
     }
 }
@@ -0,0 +1,456 @@
+use {
+    crate::{
+        engine::{
+            context::{Context, TrackPartInfo},
+            math,
+        },
+        object::GenCoord,
+        r#type::{AsAbsoluteTime, Coord, Distance, ObjectId, ObjectName, RelativeTime},
+    },
+    lr_tree::mbr,
+    std::{collections::HashSet, ops::Range},
+};
+
+const TIME_EPS: RelativeTime = 0.00001;
+
+/// The objects' surfaces closer than this are considered touching.
+const CONTACT_EPS: Distance = 0.00001;
+
+/// Collisions of two layers closer than 1ms are considered the same.
+const COLLISION_TIME_EPS: RelativeTime = 0.001;
+
+#[derive(Debug, Clone)]
+pub struct Approach {
+    pub time: chrono::Duration,
+
+    /// Distance between the objects' centers.
+    pub distance: Distance,
+
+    /// Distance between the objects' surfaces.
+    pub separation: Distance,
+
+    pub lhs: GenCoord,
+    pub rhs: GenCoord,
+
+    /// The first time the objects' surfaces touch inside the range if any.
+    pub contact_time: Option<chrono::Duration>,
+}
+
+/// Object's track parts inside a time range ordered by time.
+pub struct ObjectTrack {
+    parts: Vec<(RelativeTime, RelativeTime, TrackPartInfo)>,
+}
+
+impl ObjectTrack {
+    pub fn load(context: &Context, object_id: ObjectId, range: &Range<RelativeTime>) -> Self {
+        let mut parts = vec![];
+
+        context.tracks_tree().search_access(
+            &mbr![t = [range.start; range.end]],
+            |obj_space, id| {
+                let track_part_info = obj_space.get_data_payload(id);
+
+                if track_part_info.object_id == object_id {
+                    let time_bounds = obj_space.get_data_mbr(id).bounds(0);
+
+                    parts.push((time_bounds.min, time_bounds.max, track_part_info.clone()));
+                }
+            },
+        );
+
+        parts.sort_by(|(lhs_t, _, _), (rhs_t, _, _)| lhs_t.total_cmp(rhs_t));
+
+        Self { parts }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    pub fn boundaries<'s>(&'s self) -> impl Iterator<Item = RelativeTime> + 's {
+        self.parts
+            .iter()
+            .flat_map(|(start_t, end_t, _)| vec![*start_t, *end_t])
+    }
+
+    /// Returns the track part covering the time.
+    /// The later part is used on the boundary of two parts.
+    fn part(&self, t: RelativeTime) -> Option<&(RelativeTime, RelativeTime, TrackPartInfo)> {
+        let idx = self.parts.partition_point(|(start_t, _, _)| *start_t <= t);
+
+        self.parts[..idx]
+            .iter()
+            .rev()
+            .find(|(_, end_t, _)| t <= *end_t)
+    }
+
+    pub fn gen_coord(&self, t: RelativeTime) -> Option<GenCoord> {
+        let (start_t, end_t, info) = self.part(t)?;
+
+        let location = math::hermite_interpolation(
+            &info.start_location,
+            &info.start_velocity,
+            *start_t,
+            &info.end_location,
+            &info.end_velocity,
+            *end_t,
+            t,
+        );
+
+        let velocity = math::hermite_derivative(
+            &info.start_location,
+            &info.start_velocity,
+            *start_t,
+            &info.end_location,
+            &info.end_velocity,
+            *end_t,
+            t,
+        );
+
+        Some(GenCoord::new(t.as_absolute_time(), location, velocity))
+    }
+
+    /// Returns the collision times inside the range.
+    pub fn collisions(&self, range: &Range<RelativeTime>) -> Vec<RelativeTime> {
+        self.parts
+            .iter()
+            .filter(|(_, end_t, info)| {
+                info.final_velocity.is_some() && range.start <= *end_t && *end_t <= range.end
+            })
+            .map(|(_, end_t, _)| *end_t)
+            .collect()
+    }
+}
+
+/// Finds the minimal distance between two objects inside the range.
+pub fn closest_approach(
+    context: &Context,
+    lhs_id: ObjectId,
+    rhs_id: ObjectId,
+    range: &Range<RelativeTime>,
+) -> Option<Approach> {
+    let lhs_track = ObjectTrack::load(context, lhs_id, range);
+    let rhs_track = ObjectTrack::load(context, rhs_id, range);
+
+    if lhs_track.is_empty() || rhs_track.is_empty() {
+        return None;
+    }
+
+    let radius_sum =
+        context.actor(&lhs_id).object().radius() + context.actor(&rhs_id).object().radius();
+
+    let distance = |t: RelativeTime| -> Distance {
+        match (lhs_track.gen_coord(t), rhs_track.gen_coord(t)) {
+            (Some(lhs), Some(rhs)) => (rhs.location() - lhs.location()).norm(),
+            _ => Distance::INFINITY,
+        }
+    };
+
+    // The distance's derivative, the distance is minimal when it becomes positive.
+    let receding_speed = |t: RelativeTime| -> Coord {
+        match (lhs_track.gen_coord(t), rhs_track.gen_coord(t)) {
+            (Some(lhs), Some(rhs)) => {
+                let direction = rhs.location() - lhs.location();
+                let velocity = rhs.velocity() - lhs.velocity();
+                let norm = direction.norm();
+
+                // The centers coincide, the distance can only grow from here.
+                if norm == 0.0 {
+                    velocity.norm()
+                } else {
+                    direction.dot(&velocity) / norm
+                }
+            }
+            _ => Coord::NEG_INFINITY,
+        }
+    };
+
+    let is_touching = |t: RelativeTime| distance(t) - radius_sum <= CONTACT_EPS;
+    let touching = |t: RelativeTime| if is_touching(t) { 1.0 } else { -1.0 };
+
+    let mut boundaries = lhs_track
+        .boundaries()
+        .chain(rhs_track.boundaries())
+        .filter(|t| range.start <= *t && *t <= range.end)
+        .chain(vec![range.start, range.end])
+        .collect::<Vec<_>>();
+
+    boundaries.sort_by(|lhs, rhs| lhs.total_cmp(rhs));
+    boundaries.dedup_by(|lhs, rhs| (*lhs - *rhs).abs() <= TIME_EPS);
+
+    let mut min: Option<(RelativeTime, Distance)> = None;
+    let mut consider = |t: RelativeTime| {
+        let d = distance(t);
+
+        if !d.is_finite() {
+            return;
+        }
+
+        match min {
+            Some((_, min_d)) if min_d <= d => {}
+            _ => min = Some((t, d)),
+        }
+    };
+
+    let mut contact_time = None;
+
+    for (&start, &end) in boundaries.iter().zip(boundaries.iter().skip(1)) {
+        consider(start);
+        consider(end);
+
+        let closest_t = if end - start > TIME_EPS
+            && receding_speed(start) <= 0.0
+            && receding_speed(end) > 0.0
+        {
+            let t = math::sign_change(start..end, TIME_EPS, &receding_speed);
+            consider(t);
+
+            t
+        } else {
+            end
+        };
+
+        if contact_time.is_none() {
+            contact_time = if is_touching(start) {
+                Some(start)
+            } else {
+                [closest_t, end]
+                    .iter()
+                    .copied()
+                    .find(|t| is_touching(*t))
+                    .map(|t| math::sign_change(start..t, TIME_EPS, &touching))
+            };
+        }
+    }
+
+    let (t, d) = min?;
+
+    Some(Approach {
+        time: t.as_absolute_time(),
+        distance: d,
+        separation: d - radius_sum,
+        lhs: lhs_track.gen_coord(t)?,
+        rhs: rhs_track.gen_coord(t)?,
+        contact_time: contact_time.map(|t| t.as_absolute_time()),
+    })
+}
+
+/// Finds all pairs of objects which are closer than the threshold inside the range.
+///
+/// The tracks tree is used as a broad phase:
+/// only the pairs with intersecting (inflated by the threshold and radii) track parts are checked.
+pub fn close_approaches(
+    context: &Context,
+    threshold: Distance,
+    range: &Range<RelativeTime>,
+) -> Vec<(ObjectId, ObjectId, Approach)> {
+    let range_mbr = mbr![t = [range.start; range.end]];
+
+    let mut track_parts = vec![];
+    context
+        .tracks_tree()
+        .search_access(&range_mbr, |obj_space, id| {
+            let object_id = obj_space.get_data_payload(id).object_id;
+            let mbr = obj_space.get_data_mbr(id);
+
+            let bounds = (0..4)
+                .map(|axis| {
+                    let bounds = mbr.bounds(axis);
+                    (bounds.min, bounds.max)
+                })
+                .collect::<Vec<(Coord, Coord)>>();
+
+            track_parts.push((object_id, bounds));
+        });
+
+    // Track parts MBRs are built from the objects' centers.
+    let max_radius = context
+        .actors()
+        .values()
+        .map(|actor| actor.object().radius())
+        .fold(0.0, Distance::max);
+
+    let inflation = threshold + 2.0 * max_radius;
+
+    let mut candidates = HashSet::new();
+    for (object_id, bounds) in track_parts {
+        let inflated = mbr! {
+            t = [bounds[0].0; bounds[0].1],
+            x = [bounds[1].0 - inflation; bounds[1].1 + inflation],
+            y = [bounds[2].0 - inflation; bounds[2].1 + inflation],
+            z = [bounds[3].0 - inflation; bounds[3].1 + inflation]
+        };
+
+        context
+            .tracks_tree()
+            .search_access(&inflated, |obj_space, id| {
+                let partner_id = obj_space.get_data_payload(id).object_id;
+
+                if partner_id != object_id {
+                    candidates.insert((object_id.min(partner_id), object_id.max(partner_id)));
+                }
+            });
+    }
+
+    let mut approaches = candidates
+        .into_iter()
+        .filter_map(|(lhs_id, rhs_id)| {
+            closest_approach(context, lhs_id, rhs_id, range)
+                .filter(|approach| approach.separation < threshold)
+                .map(|approach| (lhs_id, rhs_id, approach))
+        })
+        .collect::<Vec<_>>();
+
+    approaches.sort_by(|(_, _, lhs), (_, _, rhs)| lhs.separation.total_cmp(&rhs.separation));
+
+    approaches
+}
+
+#[derive(Debug, Clone)]
+pub struct ObjectDiff {
+    pub name: ObjectName,
+
+    /// The largest location difference and its time.
+    pub max_location_diff: (Distance, chrono::Duration),
+
+    /// The largest velocity difference and its time.
+    pub max_velocity_diff: (Coord, chrono::Duration),
+
+    /// The first time when the location difference exceeds the epsilon.
+    pub separation_time: Option<chrono::Duration>,
+
+    pub lhs_only_collisions: Vec<chrono::Duration>,
+    pub rhs_only_collisions: Vec<chrono::Duration>,
+}
+
+impl ObjectDiff {
+    pub fn is_same(&self) -> bool {
+        self.separation_time.is_none()
+            && self.lhs_only_collisions.is_empty()
+            && self.rhs_only_collisions.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LayerDiff {
+    pub objects: Vec<ObjectDiff>,
+    pub lhs_only_objects: Vec<ObjectName>,
+    pub rhs_only_objects: Vec<ObjectName>,
+}
+
+/// Compares the tracks of the same objects in two contexts.
+///
+/// The tracks are sampled with the step, the separation time is refined between the samples.
+pub fn layer_diff(
+    lhs: &Context,
+    rhs: &Context,
+    range: &Range<RelativeTime>,
+    step: RelativeTime,
+    eps: Distance,
+) -> LayerDiff {
+    debug_assert!(step > 0.0);
+
+    let mut diff = LayerDiff::default();
+
+    for (object_id, actor) in lhs.actors().iter() {
+        if !rhs.actors().contains_key(object_id) {
+            diff.lhs_only_objects.push(actor.object().name().clone());
+        }
+    }
+
+    for (object_id, actor) in rhs.actors().iter() {
+        if !lhs.actors().contains_key(object_id) {
+            diff.rhs_only_objects.push(actor.object().name().clone());
+        }
+    }
+
+    for (object_id, actor) in lhs.actors().iter() {
+        if !rhs.actors().contains_key(object_id) {
+            continue;
+        }
+
+        let lhs_track = ObjectTrack::load(lhs, *object_id, range);
+        let rhs_track = ObjectTrack::load(rhs, *object_id, range);
+
+        let location_diff = |t: RelativeTime| -> Option<Distance> {
+            let lhs_coord = lhs_track.gen_coord(t)?;
+            let rhs_coord = rhs_track.gen_coord(t)?;
+
+            Some((rhs_coord.location() - lhs_coord.location()).norm())
+        };
+
+        let velocity_diff = |t: RelativeTime| -> Option<Coord> {
+            let lhs_coord = lhs_track.gen_coord(t)?;
+            let rhs_coord = rhs_track.gen_coord(t)?;
+
+            Some((rhs_coord.velocity() - lhs_coord.velocity()).norm())
+        };
+
+        let mut max_location_diff = (0.0, range.start);
+        let mut max_velocity_diff = (0.0, range.start);
+        let mut separation_t = None;
+        let mut prev_t = None;
+
+        let mut t = range.start;
+        loop {
+            if let Some(location_diff) = location_diff(t) {
+                if location_diff > max_location_diff.0 {
+                    max_location_diff = (location_diff, t);
+                }
+
+                if separation_t.is_none() && location_diff > eps {
+                    separation_t = Some(match prev_t {
+                        Some(prev_t) => math::sign_change(prev_t..t, TIME_EPS, &|t| {
+                            location_diff(t).map_or(0.0, |diff| diff - eps)
+                        }),
+                        None => t,
+                    });
+                }
+
+                prev_t = Some(t);
+            }
+
+            if let Some(velocity_diff) = velocity_diff(t) {
+                if velocity_diff > max_velocity_diff.0 {
+                    max_velocity_diff = (velocity_diff, t);
+                }
+            }
+
+            if t >= range.end {
+                break;
+            }
+
+            t = (t + step).min(range.end);
+        }
+
+        let lhs_collisions = lhs_track.collisions(range);
+        let rhs_collisions = rhs_track.collisions(range);
+
+        let only_in = |collisions: &[RelativeTime], other: &[RelativeTime]| {
+            collisions
+                .iter()
+                .filter(|t| {
+                    other
+                        .iter()
+                        .all(|other_t| (*other_t - **t).abs() > COLLISION_TIME_EPS)
+                })
+                .map(|t| t.as_absolute_time())
+                .collect::<Vec<_>>()
+        };
+
+        diff.objects.push(ObjectDiff {
+            name: actor.object().name().clone(),
+            max_location_diff: (max_location_diff.0, max_location_diff.1.as_absolute_time()),
+            max_velocity_diff: (max_velocity_diff.0, max_velocity_diff.1.as_absolute_time()),
+            separation_time: separation_t.map(|t| t.as_absolute_time()),
+            lhs_only_collisions: only_in(&lhs_collisions, &rhs_collisions),
+            rhs_only_collisions: only_in(&rhs_collisions, &lhs_collisions),
+        });
+    }
+
+    diff.objects.sort_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
+    diff.lhs_only_objects.sort();
+    diff.rhs_only_objects.sort();
+
+    diff
+}
@@ -0,0 +1,335 @@
+use {
+    crate::{
+        engine::{context::Context, math},
+        r#type::{
+            AsAbsoluteTime, AsRelativeTime, BreakpointId, Coord, Distance, ObjectId, ObjectName,
+            RelativeTime, TimeFormat, Vector,
+        },
+        Error, Result,
+    },
+    lr_tree::mbr,
+    std::{fmt, ops::Range},
+};
+
+/// Virtual time resolution is 1ms.
+const TIME_EPS: RelativeTime = 0.001;
+
+/// Number of samples per checked range.
+/// Guards against conditions that start and end inside one frame.
+const CHECK_SAMPLES: usize = 8;
+
+#[derive(Debug, Clone)]
+pub enum Condition {
+    Collision {
+        object: Option<ObjectName>,
+    },
+    Proximity {
+        lhs: ObjectName,
+        rhs: ObjectName,
+        distance: Distance,
+    },
+    LeaveRegion {
+        object: ObjectName,
+        min: Vector,
+        max: Vector,
+    },
+    Speed {
+        object: Option<ObjectName>,
+        speed: Coord,
+    },
+}
+
+impl Condition {
+    /// Returns the names of the objects the condition refers to.
+    pub fn object_names(&self) -> Vec<&ObjectName> {
+        match self {
+            Condition::Collision { object } | Condition::Speed { object, .. } => {
+                object.iter().collect()
+            }
+            Condition::Proximity { lhs, rhs, .. } => vec![lhs, rhs],
+            Condition::LeaveRegion { object, .. } => vec![object],
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Breakpoint {
+    id: BreakpointId,
+    condition: Condition,
+}
+
+impl Breakpoint {
+    pub fn id(&self) -> BreakpointId {
+        self.id
+    }
+
+    pub fn condition(&self) -> &Condition {
+        &self.condition
+    }
+
+    /// Returns the earliest time in the range when the condition is triggered.
+    fn check(&self, context: &Context, range: &Range<RelativeTime>) -> Option<RelativeTime> {
+        match &self.condition {
+            Condition::Collision { object } => {
+                let object_id = match object {
+                    Some(name) => Some(context.actor_id(name)?),
+                    None => None,
+                };
+
+                first_collision(context, object_id, range)
+            }
+            Condition::Proximity { lhs, rhs, distance } => {
+                let lhs_id = context.actor_id(lhs)?;
+                let rhs_id = context.actor_id(rhs)?;
+
+                let radius_sum = context.actor(&lhs_id).object().radius()
+                    + context.actor(&rhs_id).object().radius();
+
+                first_transition(range, |t| {
+                    let lhs_location = context.object_gen_coord(lhs_id, t)?.location().clone();
+                    let rhs_location = context.object_gen_coord(rhs_id, t)?.location().clone();
+
+                    let separation = (rhs_location - lhs_location).norm() - radius_sum;
+
+                    Some(distance - separation)
+                })
+            }
+            Condition::LeaveRegion { object, min, max } => {
+                let object_id = context.actor_id(object)?;
+
+                first_transition(range, |t| {
+                    let coord = context.object_gen_coord(object_id, t)?;
+                    let location = coord.location();
+
+                    let outside = (0..3)
+                        .map(|axis| (min[axis] - location[axis]).max(location[axis] - max[axis]))
+                        .fold(Coord::NEG_INFINITY, Coord::max);
+
+                    Some(outside)
+                })
+            }
+            Condition::Speed { object, speed } => {
+                let objects_ids = match object {
+                    Some(name) => vec![context.actor_id(name)?],
+                    None => context.actors().keys().cloned().collect(),
+                };
+
+                objects_ids
+                    .into_iter()
+                    .filter_map(|object_id| {
+                        first_transition(range, |t| {
+                            let coord = context.object_gen_coord(object_id, t)?;
+
+                            Some(coord.velocity().norm() - speed)
+                        })
+                    })
+                    .fold(None, |min_t: Option<RelativeTime>, t| match min_t {
+                        Some(min_t) if min_t <= t => Some(min_t),
+                        _ => Some(t),
+                    })
+            }
+        }
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{} {}", self.id, self.condition)
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::Collision { object: Some(name) } => {
+                write!(f, "collision involving \"{}\"", name)
+            }
+            Condition::Collision { object: None } => write!(f, "any collision"),
+            Condition::Proximity { lhs, rhs, distance } => {
+                write!(f, "\"{}\" and \"{}\" are closer than {}", lhs, rhs, distance)
+            }
+            Condition::LeaveRegion { object, min, max } => write!(
+                f,
+                "\"{}\" leaves the region {{{}, {}, {}}} - {{{}, {}, {}}}",
+                object, min[0], min[1], min[2], max[0], max[1], max[2]
+            ),
+            Condition::Speed {
+                object: Some(name),
+                speed,
+            } => write!(f, "speed of \"{}\" is above {}", name, speed),
+            Condition::Speed {
+                object: None,
+                speed,
+            } => write!(f, "speed of any object is above {}", speed),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BreakpointHit {
+    pub breakpoint: Breakpoint,
+    pub time: chrono::Duration,
+}
+
+impl fmt::Display for BreakpointHit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "breakpoint {} is hit at {}",
+            self.breakpoint,
+            TimeFormat::VirtualTimeLong(self.time)
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Breakpoints {
+    next_id: BreakpointId,
+    list: Vec<Breakpoint>,
+}
+
+impl Breakpoints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, condition: Condition) -> BreakpointId {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.list.push(Breakpoint { id, condition });
+
+        id
+    }
+
+    pub fn remove(&mut self, id: BreakpointId) -> Result<()> {
+        match self.list.iter().position(|breakpoint| breakpoint.id == id) {
+            Some(idx) => {
+                self.list.remove(idx);
+                Ok(())
+            }
+            None => Err(Error::Breakpoint(format!("breakpoint #{} is not found", id))),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Breakpoint> {
+        self.list.iter()
+    }
+
+    /// Returns the earliest breakpoint hit in the range (from; to].
+    pub fn check(
+        &self,
+        context: &Context,
+        from: chrono::Duration,
+        to: chrono::Duration,
+    ) -> Option<BreakpointHit> {
+        // The previous hit time is truncated to milliseconds,
+        // so the event that caused it must not be reported again.
+        let range = Range {
+            start: from.as_relative_time() + TIME_EPS,
+            end: to.as_relative_time(),
+        };
+
+        if self.list.is_empty() || range.start >= range.end {
+            return None;
+        }
+
+        self.list
+            .iter()
+            .filter_map(|breakpoint| {
+                breakpoint
+                    .check(context, &range)
+                    .map(|t| (t, breakpoint))
+            })
+            .min_by(|(lhs_t, _), (rhs_t, _)| lhs_t.partial_cmp(rhs_t).unwrap())
+            .map(|(t, breakpoint)| BreakpointHit {
+                breakpoint: breakpoint.clone(),
+                time: t.as_absolute_time(),
+            })
+    }
+}
+
+fn first_collision(
+    context: &Context,
+    object_id: Option<ObjectId>,
+    range: &Range<RelativeTime>,
+) -> Option<RelativeTime> {
+    let mut min_t: Option<RelativeTime> = None;
+
+    context
+        .tracks_tree()
+        .search_access(&mbr![t = [range.start; range.end]], |obj_space, id| {
+            let track_part_info = obj_space.get_data_payload(id);
+
+            if track_part_info.final_velocity.is_none() {
+                return;
+            }
+
+            if let Some(object_id) = object_id {
+                if track_part_info.object_id != object_id {
+                    return;
+                }
+            }
+
+            let collision_t = obj_space.get_data_mbr(id).bounds(0).max;
+
+            if collision_t < range.start || collision_t > range.end {
+                return;
+            }
+
+            match min_t {
+                Some(t) if t <= collision_t => {}
+                _ => min_t = Some(collision_t),
+            }
+        });
+
+    min_t
+}
+
+/// Returns the earliest time in the range when `f` becomes positive.
+///
+/// `f` returns `None` when the condition can't be evaluated (e.g. the object is not computed).
+/// Conditions that already hold at the start of the range are not reported.
+fn first_transition(
+    range: &Range<RelativeTime>,
+    f: impl Fn(RelativeTime) -> Option<f32>,
+) -> Option<RelativeTime> {
+    let step = (range.end - range.start) / CHECK_SAMPLES as RelativeTime;
+
+    let mut prev_t = range.start;
+    let mut prev_value = f(prev_t);
+
+    for sample in 1..=CHECK_SAMPLES {
+        let t = if sample == CHECK_SAMPLES {
+            range.end
+        } else {
+            range.start + step * sample as RelativeTime
+        };
+
+        let value = f(t);
+
+        match (prev_value, value) {
+            (Some(prev), Some(current)) if prev <= 0.0 && current > 0.0 => {
+                let transition_t = math::sign_change(prev_t..t, TIME_EPS, &|t| {
+                    f(t).unwrap_or(Coord::NEG_INFINITY)
+                });
+
+                return Some(transition_t);
+            }
+            _ => {}
+        }
+
+        prev_t = t;
+        prev_value = value;
+    }
+
+    None
+}
@@ -5,7 +5,7 @@ use {
         object::{GenCoord, Object},
         r#type::{
             AsRelativeTime, Coord, Distance, IntoRustDuration, IntoStorageDuration, LocationId, ObjectId, RawTime,
-            Vector, LayerId,
+            Vector, LayerId, Mass,
         },
     },
     lr_tree::*,
@@ -19,6 +19,7 @@ use {
 
 const OBJECT_FIELDS_LEN: usize = 6;
 const LOCATION_INFO_FIELDS_LEN: usize = 12;
+const MASS_OVERRIDE_FIELDS_LEN: usize = 3;
 
 pub struct LocationInfo {
     pub layer_id: LayerId,
@@ -122,7 +123,8 @@ impl<'de> Deserialize<'de> for LocationInfo {
     }
 }
 
-pub struct ObjectInfo(pub ObjectId, pub Object);
+/// Object's info with the time of its removal in the layer if any.
+pub struct ObjectInfo(pub ObjectId, pub Object, pub Option<chrono::Duration>);
 
 impl<'de> Deserialize<'de> for ObjectInfo {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -152,9 +154,12 @@ impl<'de> Deserialize<'de> for ObjectInfo {
                 let compute_step: RawTime = seq.next_element()?.expect("expected compute step");
                 let compute_step = compute_step.into_rust_duration();
 
+                let removed_time: Option<RawTime> = seq.next_element()?.flatten();
+                let removed_time = removed_time.map(|time| time.into_rust_duration());
+
                 let object = Object::new(layer_id, name, radius, color, mass, compute_step);
 
-                Ok(ObjectInfo(object_id, object))
+                Ok(ObjectInfo(object_id, object, removed_time))
             }
         }
 
@@ -162,6 +167,39 @@ impl<'de> Deserialize<'de> for ObjectInfo {
     }
 }
 
+/// Object's mass overridden by a layer from the layer's start time.
+pub struct MassOverrideInfo(pub ObjectId, pub chrono::Duration, pub Mass);
+
+impl<'de> Deserialize<'de> for MassOverrideInfo {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct MassOverrideInfoVisitor;
+
+        impl<'de> Visitor<'de> for MassOverrideInfoVisitor {
+            type Value = MassOverrideInfo;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "an object's mass override")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let object_id = seq.next_element()?.expect("expected object ID");
+
+                let start_time: RawTime = seq.next_element()?.expect("expected start time");
+                let start_time = start_time.into_rust_duration();
+
+                let mass = seq.next_element()?.expect("expected mass");
+
+                Ok(MassOverrideInfo(object_id, start_time, mass))
+            }
+        }
+
+        deserializer.deserialize_tuple(MASS_OVERRIDE_FIELDS_LEN, MassOverrideInfoVisitor)
+    }
+}
+
 pub fn make_last_gen_coord(location_info: &LocationInfo) -> GenCoord {
     let time = location_info.t;
     let location = Vector::new(location_info.x, location_info.y, location_info.z);
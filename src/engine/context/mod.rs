@@ -3,31 +3,35 @@ use {
         engine::{actor::Actor, math, phys::*},
         r#type::{
             TimeFormat,
-            Coord, IntoStorageDuration, LayerId, ObjectId, ObjectName,
+            Coord, LayerId, ObjectId, ObjectName,
             RelativeTime, AsRelativeTime, AsAbsoluteTime, SessionId, LocationId, Vector,
         },
         object::GenCoord,
         storage::StorageManager,
         Error, Result,
     },
-    itertools::Itertools,
-    log::{trace, info},
+    log::{trace, info, warn},
     lr_tree::*,
     std::{
         collections::{HashMap, HashSet},
-        sync::{mpsc, Arc, RwLock},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            mpsc, Arc, RwLock,
+        },
     },
     rayon::prelude::*,
 };
 
 pub mod db_util;
+pub mod progress;
 pub mod time_range;
 
+pub use progress::*;
 pub use time_range::*;
 
 use db_util::*;
 
-use crate::query;
+use crate::transaction;
 
 pub type ActorsMap = HashMap<ObjectId, Actor>;
 pub type ActorsNamesMap = HashMap<ObjectName, ObjectId>;
@@ -69,6 +73,9 @@ pub struct ContextChangeParams {
     pub session_id: SessionId,
     pub layer_id: LayerId,
     pub time_range: TimeRange,
+
+    /// The computed tracks aren't stored.
+    pub is_read_only: bool,
 }
 
 #[derive(Debug)]
@@ -80,6 +87,9 @@ pub struct Context {
     tracks_tree: TracksTree,
     time_range: TimeRange,
     new_objects: Vec<ObjectId>,
+
+    /// The ranges of the active layer's tracks removed by the storage compaction.
+    compacted_ranges: HashMap<ObjectId, Vec<TimeRange>>,
 }
 
 impl Context {
@@ -100,6 +110,7 @@ impl Context {
             tracks_tree: TracksTree::with_obj_space(Self::new_tracks_space()),
             time_range,
             new_objects: vec![],
+            compacted_ranges: HashMap::new(),
         }
     }
 
@@ -123,6 +134,10 @@ impl Context {
         &self.actors
     }
 
+    pub fn actor_id(&self, name: &ObjectName) -> Option<ObjectId> {
+        self.actors_names.get(name).cloned()
+    }
+
     pub fn tracks_tree(&self) -> &TracksTree {
         &self.tracks_tree
     }
@@ -149,6 +164,57 @@ impl Context {
         location
     }
 
+    pub fn velocity(
+        mbr: &MBR<Coord>,
+        track_part_info: &TrackPartInfo,
+        t: RelativeTime,
+    ) -> Vector {
+        math::hermite_derivative(
+            &track_part_info.start_location,
+            &track_part_info.start_velocity,
+            mbr.bounds(0).min,
+            &track_part_info.end_location,
+            &track_part_info.end_velocity,
+            mbr.bounds(0).max,
+            t,
+        )
+    }
+
+    /// Returns the object's location and velocity at the time `t`
+    /// if the time is covered by the object's tracks.
+    ///
+    /// When `t` is the boundary of two track parts the later one is used,
+    /// so the velocity after a collision is returned.
+    pub fn object_gen_coord(&self, object_id: ObjectId, t: RelativeTime) -> Option<GenCoord> {
+        let mut found: Option<(RelativeTime, Vector, Vector)> = None;
+
+        self.tracks_tree.search_access(&mbr![t = [t; t]], |obj_space, id| {
+            let track_part_info = obj_space.get_data_payload(id);
+
+            if track_part_info.object_id != object_id {
+                return;
+            }
+
+            let mbr = obj_space.get_data_mbr(id);
+            let start_t = mbr.bounds(0).min;
+
+            let is_later = match found {
+                Some((found_t, _, _)) => start_t > found_t,
+                None => true,
+            };
+
+            if is_later {
+                found = Some((
+                    start_t,
+                    Self::location(mbr, track_part_info, t),
+                    Self::velocity(mbr, track_part_info, t),
+                ));
+            }
+        });
+
+        found.map(|(_, location, velocity)| GenCoord::new(t.as_absolute_time(), location, velocity))
+    }
+
     /// Returns all ids of objects that were changed.
     pub fn cancel_tracks_except(&self, from: RelativeTime, except: HashSet<ObjectId>) -> HashSet<ObjectId> {
         let mut changed = HashSet::new();
@@ -345,6 +411,7 @@ impl Context {
                 tracks_tree,
                 time_range: time_range.clone(),
                 new_objects: vec![],
+                compacted_ranges: HashMap::new(),
             };
 
             (new_context, UpdateKind::Initial(time_range))
@@ -355,72 +422,70 @@ impl Context {
         mut self,
         storage_mgr: StorageManager,
         update_kind: UpdateKind,
+        is_read_only: bool,
         interrupter: mpsc::Receiver<()>,
+        progress_sender: mpsc::Sender<ContextProgress>,
     ) -> Result<Self> {
-        self.load_content_from_db(storage_mgr.clone(), update_kind)?;
+        let recompute_compacted = true;
+        self.load_content_from_db(storage_mgr.clone(), update_kind, recompute_compacted)?;
 
-        self.compute_tracks(storage_mgr, interrupter)?;
+        self.compute_tracks(storage_mgr, is_read_only, interrupter, progress_sender)?;
 
         Ok(self)
     }
 
-    fn load_content_from_db(&mut self, storage_mgr: StorageManager, update_kind: UpdateKind) -> Result<()> {
-        let mut connection = storage_mgr.pool.get()?;
-        let reader = connection.copy_out(query![
-            "COPY (
-                    SELECT * FROM {schema_name}.current_objects_delta(
-                        {layer_id},
-                        ARRAY[{known_objects_ids}]::bigint[]
-                    )
-                ) TO stdout WITH (FORMAT CSV)",
-            layer_id = self.layer_id,
-            known_objects_ids = self.actors.iter().map(|(id, _)| id).join(",")
-        ])?;
-
-        let reader = csv::ReaderBuilder::new()
-            .has_headers(false)
-            .from_reader(reader);
-
-        self.load_objects_from_db(reader)?;
-
-        let reader = connection.copy_out(query![
-            "COPY
-                    (
-                        SELECT
-                            out_location_id,
-                            out_object_fk_id,
-                            out_t,
-                            out_x,
-                            out_y,
-                            out_z,
-                            out_vx,
-                            out_vy,
-                            out_vz,
-
-                            out_vcx,
-                            out_vcy,
-                            out_vcz
-
-                            -- NULLIF(array_to_string(out_collision_partners, ','), '')
-                        FROM
-                            {schema_name}.range_locations({layer_id}, {start_time}, {stop_time}, {step_coeff})
-                    )
-                TO stdout WITH (FORMAT CSV)",
-            layer_id = self.layer_id,
-            start_time = update_kind.time_range().start().into_storage_duration(),
-            stop_time = update_kind.time_range().end().into_storage_duration(),
-            step_coeff = update_kind.as_step_coeff()
-        ])?;
+    /// Loads the stored tracks of the time range without computing the missing ones.
+    pub fn load(
+        storage_mgr: StorageManager,
+        session_id: SessionId,
+        layer_id: LayerId,
+        time_range: TimeRange,
+    ) -> Result<Self> {
+        let mut context = Self::with_time_range(session_id, layer_id, time_range.clone());
 
-        let mut reader = csv::ReaderBuilder::new()
-            .has_headers(false)
-            .from_reader(reader);
+        let recompute_compacted = false;
+        context.load_content_from_db(storage_mgr, UpdateKind::Initial(time_range), recompute_compacted)?;
 
-        // let mut collision_partners_map = HashMap::new();
+        Ok(context)
+    }
 
-        for result in reader.deserialize() {
-            let location_info: LocationInfo = result.map_err(|err| Error::SerializeCSV(err))?;
+    fn load_content_from_db(
+        &mut self,
+        storage_mgr: StorageManager,
+        update_kind: UpdateKind,
+        recompute_compacted: bool,
+    ) -> Result<()> {
+        let known_objects_ids = self.actors.keys().cloned().collect::<Vec<_>>();
+        let objects;
+        let mass_overrides;
+        let locations;
+        let layer_start_time;
+
+        transaction! {
+            storage_mgr => t(RepeatableRead) {
+                layer_start_time = t.layer().get_start_time(self.layer_id)?;
+                objects = t.object().get_objects_delta(self.layer_id, &known_objects_ids)?;
+                mass_overrides = t.object().get_mass_overrides(self.layer_id)?;
+                locations = t.location().get_range_locations(
+                    self.layer_id,
+                    update_kind.time_range(),
+                    update_kind.as_step_coeff()
+                )?;
+            }
+        }
+
+        self.load_objects(objects);
+        self.load_mass_overrides(mass_overrides, &known_objects_ids);
 
+        let locations = if recompute_compacted {
+            self.drop_compacted_ranges(locations, layer_start_time)
+        } else {
+            locations
+        };
+
+        // let mut collision_partners_map = HashMap::new();
+
+        for location_info in locations {
             let object_id = location_info.object_id;
             let actor = self.actors.get_mut(&object_id).unwrap();
             match actor.last_gen_coord() {
@@ -464,20 +529,122 @@ impl Context {
         Ok(())
     }
 
-    fn load_objects_from_db(
+    /// Drops the locations after the first range removed by the storage compaction,
+    /// they are recomputed from the kept ones instead.
+    ///
+    /// The computed locations of an object are at most its compute step apart,
+    /// so a larger distance is a removed range. All the objects are cut at the same time
+    /// to recompute their collisions deterministically.
+    ///
+    /// Only the active layer's ranges are recomputed: the tracks before the layer's start time
+    /// belong to the ancestor layers, and the ancestors' history can't be written from here.
+    /// They stay compacted until viewed from their own layer.
+    ///
+    /// The recomputed ranges are stored again, so a viewed range is no longer compacted.
+    fn drop_compacted_ranges(
         &mut self,
-        mut reader: csv::Reader<postgres::CopyOutReader>,
-    ) -> Result<()> {
-        for result in reader.deserialize() {
-            let object_info: ObjectInfo = result.map_err(Error::SerializeCSV)?;
+        locations: Vec<LocationInfo>,
+        layer_start_time: chrono::Duration,
+    ) -> Vec<LocationInfo> {
+        let mut last_times = HashMap::new();
+        let mut cut_time: Option<chrono::Duration> = None;
+
+        for location_info in locations.iter() {
+            let object_id = location_info.object_id;
+
+            let last_time = match last_times.insert(object_id, location_info.t) {
+                Some(last_time) => last_time,
+                None => continue,
+            };
 
-            let ObjectInfo(object_id, object) = object_info;
+            let compute_step = self.actor(&object_id).object().compute_step();
+
+            if location_info.t - last_time <= compute_step {
+                continue;
+            }
+
+            if last_time < layer_start_time {
+                warn! {
+                    target: LOG_TARGET,
+                    "the compacted track of the object #{} in [{}; {}] belongs to an ancestor layer, it isn't recomputed",
+                    object_id,
+                    TimeFormat::VirtualTimeShort(last_time),
+                    TimeFormat::VirtualTimeShort(location_info.t)
+                }
+
+                continue;
+            }
+
+            self.compacted_ranges
+                .entry(object_id)
+                .or_default()
+                .push(TimeRange::with_bounds(last_time, location_info.t));
+
+            cut_time = Some(cut_time.map_or(last_time, |time| time.min(last_time)));
+        }
+
+        let cut_time = match cut_time {
+            Some(time) => time,
+            None => return locations,
+        };
+
+        trace! {
+            target: LOG_TARGET,
+            "the stored tracks are compacted, recompute from {}",
+            TimeFormat::VirtualTimeShort(cut_time)
+        }
+
+        // The dropped locations are still stored, only the compacted ranges are written back.
+        for (object_id, last_time) in last_times {
+            if let Some(actor) = self.actors.get_mut(&object_id) {
+                actor.set_last_computed_time(last_time);
+            }
+        }
+
+        // The first location of an object is kept anyway, the object starts from it.
+        let mut known_objects = HashSet::new();
+        locations
+            .into_iter()
+            .filter(|location_info| {
+                known_objects.insert(location_info.object_id) || location_info.t <= cut_time
+            })
+            .collect()
+    }
+
+    /// Checks if the time is inside a compacted range of the object's track.
+    fn is_compacted(&self, object_id: ObjectId, t: chrono::Duration) -> bool {
+        self.compacted_ranges
+            .get(&object_id)
+            .map_or(false, |ranges| {
+                ranges.iter().any(|range| range.start() < t && t < range.end())
+            })
+    }
+
+    fn load_objects(&mut self, objects: Vec<ObjectInfo>) {
+        for ObjectInfo(object_id, object, removed_time) in objects {
             self.new_objects.push(object_id);
             self.actors_names.insert(object.name().clone(), object_id);
-            self.actors.insert(object_id, Actor::new(object));
+
+            let mut actor = Actor::new(object);
+            actor.set_removed_time(removed_time);
+
+            self.actors.insert(object_id, actor);
         }
+    }
 
-        Ok(())
+    fn load_mass_overrides(
+        &mut self,
+        mass_overrides: Vec<MassOverrideInfo>,
+        known_objects_ids: &[ObjectId],
+    ) {
+        let known_objects_ids = known_objects_ids.iter().collect::<HashSet<_>>();
+
+        for MassOverrideInfo(object_id, start_time, mass) in mass_overrides {
+            // The known actors already have their overrides.
+            if !known_objects_ids.contains(&object_id) {
+                self.actors.get_mut(&object_id).unwrap().add_mass_override(start_time, mass);
+            }
+        }
     }
 
     // fn fix_collision_partners(
@@ -499,13 +666,23 @@ impl Context {
     //     }
     // }
 
-    fn compute_tracks(&mut self, storage_mgr: StorageManager, interrupter: mpsc::Receiver<()>) -> Result<()> {
+    fn compute_tracks(
+        &mut self,
+        storage_mgr: StorageManager,
+        is_read_only: bool,
+        interrupter: mpsc::Receiver<()>,
+        progress_sender: mpsc::Sender<ContextProgress>,
+    ) -> Result<()> {
         let mut is_interrupted = false;
+        let mut progress = ContextProgress::new(self.time_range.clone());
+        self.send_progress(&progress_sender, &progress);
+
         let mut checker = collision::CollisionChecker::new(self);
 
         let mut uncomputed = self.actors().keys().cloned().collect::<HashSet<_>>();
         while !uncomputed.is_empty() && !is_interrupted {
             let arc_checker = Arc::new(RwLock::new(checker));
+            let track_parts_created = AtomicUsize::new(0);
 
             uncomputed = uncomputed.into_par_iter()
                 .filter_map(|object_id| {
@@ -517,10 +694,17 @@ impl Context {
                         None => return None,
                     };
 
-                    let next_coord = next_gen_coord(
-                        &last_coord,
-                        actor.object().compute_step()
-                    );
+                    let mut step = actor.object().compute_step();
+
+                    if let Some(removed_time) = actor.removed_time() {
+                        if last_coord.time() >= removed_time {
+                            return None;
+                        }
+
+                        step = step.min(removed_time - last_coord.time());
+                    }
+
+                    let next_coord = next_gen_coord(&last_coord, step);
 
                     let track_part_info = TrackPartInfo::new(
                         object_id,
@@ -549,6 +733,7 @@ impl Context {
                     );
 
                     actor.set_last_gen_coord(next_coord);
+                    track_parts_created.fetch_add(1, Ordering::Relaxed);
 
                     self.tracks_tree().insert_transaction(
                         track_part_info,
@@ -583,7 +768,12 @@ impl Context {
                 .filter(|(_, group)| group.edge_count() != 0)
                 .min_by(|(lhs_t, _), (rhs_t, _)| lhs_t.partial_cmp(&rhs_t).unwrap());
 
+            progress.add_track_parts_created(track_parts_created.into_inner());
+
             if let Some((t, group)) = collision_info {
+                progress.add_collisions_resolved(group.edge_count());
+                progress.add_track_parts_created(group.node_count());
+
                 let changed_ids = compute_collisions(self, t, group);
 
                 uncomputed.extend(changed_ids);
@@ -591,6 +781,15 @@ impl Context {
 
             checker.clear();
 
+            let reached_time = uncomputed.iter()
+                .filter_map(|id| self.actor(id).last_gen_coord())
+                .map(|coord| coord.time())
+                .min()
+                .unwrap_or(self.time_range.end());
+
+            progress.set_reached_time(reached_time);
+            self.send_progress(&progress_sender, &progress);
+
             if let Ok(_) = interrupter.try_recv() {
                 info! {
                     target: LOG_TARGET,
@@ -605,7 +804,9 @@ impl Context {
         self.tracks_tree = LRTree::with_obj_space(obj_space);
         self.rebuild_rtree();
 
-        self.update_db(storage_mgr)?;
+        if !is_read_only {
+            self.update_db(storage_mgr)?;
+        }
 
         for (_, actor) in self.actors.iter_mut() {
             if let Some(last_coord) = actor.last_gen_coord() {
@@ -629,28 +830,17 @@ impl Context {
         }
     }
 
+    fn send_progress(&self, sender: &mpsc::Sender<ContextProgress>, progress: &ContextProgress) {
+        if let Err(err) = sender.send(progress.clone()) {
+            trace! {
+                target: LOG_TARGET,
+                "unable to send the context progress: {}", err
+            }
+        }
+    }
+
     fn update_db(&self, storage_mgr: StorageManager) -> Result<()> {
-        let mut connection = storage_mgr.pool.get()?;
-        let writer = connection.copy_in(
-            query!["COPY {schema_name}.location(
-                object_fk_id,
-                layer_fk_id,
-                t,
-                x,
-                y,
-                z,
-                vx,
-                vy,
-                vz,
-                vcx,
-                vcy,
-                vcz
-            ) FROM stdin WITH (FORCE_NULL(vcx, vcy, vcz), FORMAT CSV)"]
-        ).unwrap();
-
-        let mut writer = csv::WriterBuilder::new()
-            .quote_style(csv::QuoteStyle::NonNumeric)
-            .from_writer(writer);
+        let mut locations = vec![];
 
         let mbr = mbr![
             t = [self.time_range.start().as_relative_time(); self.time_range.end().as_relative_time()]
@@ -673,12 +863,15 @@ impl Context {
                     None => [None, None, None],
                 };
 
-                if actor.last_computed_time().as_relative_time() < mbr.bounds(0).max {
+                let t = mbr.bounds(0).max.as_absolute_time();
+                let is_new = actor.last_computed_time().as_relative_time() < mbr.bounds(0).max;
+
+                if is_new || self.is_compacted(track_part.object_id, t) {
                     let location_info = LocationInfo {
                         object_id: track_part.object_id,
                         location_id: LocationId::default(),
                         layer_id: self.layer_id,
-                        t: mbr.bounds(0).max.as_absolute_time(),
+                        t,
                         x: track_part.end_location[0],
                         y: track_part.end_location[1],
                         z: track_part.end_location[2],
@@ -690,13 +883,16 @@ impl Context {
                         vcz: vc[2],
                     };
 
-                    writer.serialize(location_info).expect("update DB");
+                    locations.push(location_info);
                 }
             }
         );
 
-        let writer = writer.into_inner().expect("unwrap CSV writer");
-        writer.finish().expect("DB writer finish");
+        transaction! {
+            storage_mgr => t {
+                t.location().add_locations(&locations)?;
+            }
+        }
 
         Ok(())
     }
@@ -0,0 +1,120 @@
+use {
+    crate::{
+        engine::context::TimeRange,
+        r#type::{AsRelativeTime, TimeFormat},
+    },
+    std::{fmt, time::Instant},
+};
+
+const PROGRESS_BAR_FILLED: char = '#';
+const PROGRESS_BAR_EMPTY: char = '.';
+
+#[derive(Debug, Clone)]
+pub struct ContextProgress {
+    time_range: TimeRange,
+    reached_time: chrono::Duration,
+    collisions_resolved: usize,
+    track_parts_created: usize,
+    started_at: Instant,
+}
+
+impl ContextProgress {
+    pub fn new(time_range: TimeRange) -> Self {
+        let reached_time = time_range.start();
+
+        Self {
+            time_range,
+            reached_time,
+            collisions_resolved: 0,
+            track_parts_created: 0,
+            started_at: Instant::now(),
+        }
+    }
+
+    pub fn time_range(&self) -> &TimeRange {
+        &self.time_range
+    }
+
+    pub fn reached_time(&self) -> chrono::Duration {
+        self.reached_time
+    }
+
+    pub fn set_reached_time(&mut self, time: chrono::Duration) {
+        self.reached_time = time;
+    }
+
+    pub fn collisions_resolved(&self) -> usize {
+        self.collisions_resolved
+    }
+
+    pub fn add_collisions_resolved(&mut self, count: usize) {
+        self.collisions_resolved += count;
+    }
+
+    pub fn track_parts_created(&self) -> usize {
+        self.track_parts_created
+    }
+
+    pub fn add_track_parts_created(&mut self, count: usize) {
+        self.track_parts_created += count;
+    }
+
+    /// Returns the computed part of the time range in [0; 1].
+    pub fn ratio(&self) -> f32 {
+        let ratio = self.time_range.ratio(self.reached_time);
+
+        ratio.max(0.0).min(1.0)
+    }
+
+    pub fn elapsed(&self) -> chrono::Duration {
+        chrono::Duration::from_std(self.started_at.elapsed())
+            .unwrap_or_else(|_| chrono::Duration::zero())
+    }
+
+    /// Estimates the remaining real time using the current computation speed.
+    pub fn eta(&self) -> Option<chrono::Duration> {
+        let ratio = self.ratio();
+
+        if ratio <= 0.0 {
+            return None;
+        }
+
+        let elapsed = self.elapsed().as_relative_time();
+        let remaining_ms = (elapsed * (1.0 - ratio) / ratio * 1000.0) as i64;
+
+        Some(chrono::Duration::milliseconds(remaining_ms))
+    }
+
+    pub fn progress_bar(&self, width: usize) -> String {
+        let filled = (self.ratio() * width as f32).round() as usize;
+        let filled = filled.min(width);
+
+        let mut bar = String::with_capacity(width + 2);
+        bar.push('[');
+        bar.extend(std::iter::repeat(PROGRESS_BAR_FILLED).take(filled));
+        bar.extend(std::iter::repeat(PROGRESS_BAR_EMPTY).take(width - filled));
+        bar.push(']');
+
+        bar
+    }
+}
+
+impl fmt::Display for ContextProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "simulated {} of [{}; {}] ({:.0}%), collisions resolved: {}, track parts created: {}, ETA: ",
+            TimeFormat::VirtualTimeShort(self.reached_time),
+            TimeFormat::VirtualTimeShort(self.time_range.start()),
+            TimeFormat::VirtualTimeShort(self.time_range.end()),
+            self.ratio() * 100.0,
+            self.collisions_resolved,
+            self.track_parts_created,
+        )?;
+
+        match self.eta() {
+            Some(eta) => write!(f, "{}", TimeFormat::RealTime(eta)),
+            None => write!(f, "unknown"),
+        }
+    }
+}
@@ -33,6 +33,32 @@ pub fn hermite_interpolation(
     p0 + m0 + p1 + m1
 }
 
+pub fn hermite_derivative(
+    location_0: &Vector,
+    velocity_0: &Vector,
+    time_0: RelativeTime,
+    location_1: &Vector,
+    velocity_1: &Vector,
+    time_1: RelativeTime,
+    interest_time: RelativeTime,
+) -> Vector {
+    let step = time_1 - time_0;
+    let t = (interest_time - time_0) / step;
+
+    let t2 = t * t;
+
+    let p1_coeff = (-6.0 * t2 + 6.0 * t) / step;
+    let p0_coeff = -p1_coeff;
+
+    let p0 = location_0.scale(p0_coeff);
+    let m0 = velocity_0.scale(3.0 * t2 - 4.0 * t + 1.0);
+
+    let p1 = location_1.scale(p1_coeff);
+    let m1 = velocity_1.scale(3.0 * t2 - 2.0 * t);
+
+    p0 + m0 + p1 + m1
+}
+
 pub fn golden_section_search(
     valid_range: Range<RelativeTime>,
     t_eps: f32,
@@ -134,3 +160,32 @@ pub fn find_root(
 
     bisection(&valid_range, t_eps, f_eps, &f)
 }
+
+/// Narrows down the moment when `f` becomes positive.
+///
+/// `f` is expected to be non-positive at the start of the range and positive at its end.
+/// Returns the earliest found time when `f` is positive.
+pub fn sign_change(
+    valid_range: Range<RelativeTime>,
+    t_eps: f32,
+    f: &impl Fn(RelativeTime) -> f32,
+) -> RelativeTime {
+    let Range {
+        mut start,
+        mut end
+    } = valid_range;
+
+    debug_assert!(start < end);
+
+    while end - start > t_eps {
+        let mid = (start + end) / 2.0;
+
+        if f(mid) > 0.0 {
+            end = mid;
+        } else {
+            start = mid;
+        }
+    }
+
+    end
+}
@@ -1,40 +1,45 @@
 use {
     crate::{
-        layer::Layer,
+        config::Config,
+        layer::{Layer, LayerIssue},
+        make_error,
+        object::GenCoord,
         r#type::{
-            LayerId, LayerName, ObjectName, RawTime, SessionId, SessionInfo, SessionName,
-            TimeFormat, Color,
+            AsRelativeTime, BookmarkInfo, BookmarkName, JournalOperation, LayerCopyInfo, LayerId,
+            LayerInfo, LayerMergeInfo, LayerName, Mass, ObjectId, ObjectName, RawTime, SessionId,
+            SessionInfo, SessionName, TimeFormat, Color, Distance, Vector,
         },
-        storage::{self, StorageManager, StorageTransaction},
-        transaction, Result,
+        storage::{
+            dump::SessionDump, IsolationLevel, LayerApi, SessionApi, StorageEvent, StorageManager,
+        },
+        transaction, Error, Result,
     },
     kiss3d::{scene::SceneNode, window::Window, camera::Camera},
-    lazy_static::lazy_static,
     log::{error, trace, warn},
-    ptree::{item::StringItem, TreeBuilder},
-    std::sync::{mpsc, Arc},
+    std::{
+        collections::{HashMap, HashSet},
+        sync::{mpsc, Arc},
+    },
     nalgebra::Point2,
 };
 
 pub mod actor;
+pub mod analysis;
+pub mod breakpoint;
 pub mod context;
 pub mod phys;
 pub mod math;
 pub mod scene;
 
-use context::{Context, TimeRange, ContextChangeParams};
+use analysis::{Approach, LayerDiff};
+use breakpoint::{BreakpointHit, Breakpoints};
+use context::{Context, TimeRange, ContextChangeParams, ContextProgress};
 use scene::Scene;
 
-const CONNECTION_STRING: &'static str = "host=localhost user=postgres";
 const LOG_TARGET: &'static str = "engine";
 
 const CONTEXT_CHANGE_RATIO: f32 = 0.6;
-
-lazy_static! {
-    static ref ACCESS_UPDATE_TIME: chrono::Duration = chrono::Duration::seconds(30);
-    pub static ref SESSION_MAX_HANG_TIME: chrono::Duration =
-        chrono::Duration::seconds(ACCESS_UPDATE_TIME.num_seconds() + 10);
-}
+const PROGRESS_BAR_WIDTH: usize = 20;
 
 pub struct DebugInfoSettings {
     pub tracks: Option<chrono::Duration>,
@@ -45,12 +50,17 @@ pub struct DebugInfoSettings {
 
 pub struct Engine {
     storage_mgr: StorageManager,
+    storage_events: Option<mpsc::Receiver<StorageEvent>>,
     context: Arc<Context>,
     context_recv: mpsc::Receiver<Context>,
     context_upd_intrp: mpsc::Sender<()>,
+    context_progress_recv: mpsc::Receiver<ContextProgress>,
+    context_progress: Option<ContextProgress>,
     scene: Scene,
     real_time: chrono::Duration,
     last_session_update_time: chrono::Duration,
+    access_update_time: chrono::Duration,
+    is_read_only: bool,
     virtual_time: chrono::Duration,
     virtual_step: chrono::Duration,
     last_frame_delta: chrono::Duration,
@@ -59,22 +69,31 @@ pub struct Engine {
     context_change_params: Option<ContextChangeParams>,
     is_context_change_spawned: bool,
     debug_info_settings: DebugInfoSettings,
+    breakpoints: Breakpoints,
+    bookmarks: Vec<BookmarkInfo>,
 }
 
 impl Engine {
-    pub fn init(root_scene_node: SceneNode) -> Result<Self> {
-        let storage_mgr = StorageManager::setup(CONNECTION_STRING, *SESSION_MAX_HANG_TIME)?;
+    pub fn init(root_scene_node: SceneNode, config: &Config) -> Result<Self> {
+        let storage_mgr = StorageManager::setup(config)?;
+
         let (_, context_recv) = mpsc::channel();
         let (context_upd_intrp, _) = mpsc::channel();
+        let (_, context_progress_recv) = mpsc::channel();
 
         let mut engine = Self {
             storage_mgr,
+            storage_events: None,
             context: Arc::new(Context::new(SessionId::default(), LayerId::default())),
             context_recv,
             context_upd_intrp,
+            context_progress_recv,
+            context_progress: None,
             scene: Scene::new(root_scene_node),
             real_time: chrono::Duration::zero(),
             last_session_update_time: chrono::Duration::zero(),
+            access_update_time: config.access_update_time,
+            is_read_only: false,
             virtual_time: chrono::Duration::zero(),
             virtual_step: chrono::Duration::seconds(1),
             last_frame_delta: chrono::Duration::zero(),
@@ -88,6 +107,8 @@ impl Engine {
                 show_rtree: false,
                 sim_stats: true,
             },
+            breakpoints: Breakpoints::new(),
+            bookmarks: vec![],
         };
 
         let session_name = None;
@@ -101,7 +122,12 @@ impl Engine {
         Ok(engine)
     }
 
-    pub fn advance_time(&mut self, frame_delta_ns: i128, advance_virtual_time: bool) -> Result<()> {
+    /// Returns the breakpoint hit if the virtual time has reached one.
+    pub fn advance_time(
+        &mut self,
+        frame_delta_ns: i128,
+        advance_virtual_time: bool,
+    ) -> Result<Option<BreakpointHit>> {
         match self.context_recv.try_recv() {
             Ok(new_context) => self.set_new_context(new_context)?,
             Err(mpsc::TryRecvError::Disconnected) => {
@@ -110,14 +136,32 @@ impl Engine {
             _ => {}
         }
 
+        while let Ok(progress) = self.context_progress_recv.try_recv() {
+            self.context_progress = Some(progress);
+        }
+
         let one_second_ns = chrono::Duration::seconds(1).num_nanoseconds().unwrap() as i128;
         let ns_per_ms = 1_000_000;
 
         let vt_step_ns = self.virtual_step.num_milliseconds() as i128 * ns_per_ms;
         let real_step = (frame_delta_ns as i128 * vt_step_ns / one_second_ns) as RawTime;
 
+        let mut breakpoint_hit = None;
+
         if advance_virtual_time {
+            let prev_virtual_time = self.virtual_time;
             self.virtual_time = self.virtual_time + chrono::Duration::nanoseconds(real_step);
+
+            breakpoint_hit = self.breakpoints.check(
+                &self.context,
+                prev_virtual_time,
+                self.virtual_time
+            );
+
+            if let Some(hit) = breakpoint_hit.as_ref() {
+                self.virtual_time = hit.time;
+            }
+
             self.scene.set_time(&self.context, self.virtual_time);
 
             if !self.is_context_change_spawned
@@ -145,7 +189,15 @@ impl Engine {
             }
         });
 
-        Ok(())
+        self.handle_storage_events().unwrap_or_else(|err| {
+            error! {
+                target: LOG_TARGET,
+                "unable to refresh the session: {}",
+                err
+            }
+        });
+
+        Ok(breakpoint_hit)
     }
 
     pub fn draw_debug_info<C: Camera>(&mut self, window: &mut Window, camera: &mut C) {
@@ -165,6 +217,15 @@ impl Engine {
         &self.context
     }
 
+    /// Returns the progress of the running context update if any.
+    pub fn context_progress(&self) -> Option<&ContextProgress> {
+        if self.is_context_change_spawned {
+            self.context_progress.as_ref()
+        } else {
+            None
+        }
+    }
+
     pub fn virtual_time(&self) -> chrono::Duration {
         self.virtual_time
     }
@@ -193,6 +254,74 @@ impl Engine {
         );
     }
 
+    pub fn bookmarks(&self) -> &[BookmarkInfo] {
+        &self.bookmarks
+    }
+
+    pub fn bookmark(&self, name: &BookmarkName) -> Result<&BookmarkInfo> {
+        self.bookmarks
+            .iter()
+            .find(|bookmark| &bookmark.name == name)
+            .ok_or_else(|| Error::Bookmark(format!("bookmark \"{}\" is not found", name)))
+    }
+
+    /// Returns the nearest bookmark after the current virtual time.
+    pub fn next_bookmark(&self) -> Option<&BookmarkInfo> {
+        self.bookmarks
+            .iter()
+            .find(|bookmark| bookmark.time > self.virtual_time)
+    }
+
+    /// Returns the nearest bookmark before the current virtual time.
+    pub fn prev_bookmark(&self) -> Option<&BookmarkInfo> {
+        self.bookmarks
+            .iter()
+            .rev()
+            .find(|bookmark| bookmark.time < self.virtual_time)
+    }
+
+    pub fn add_bookmark(&mut self, name: &BookmarkName, time: chrono::Duration) -> Result<()> {
+        self.check_writable()?;
+
+        transaction! {
+            self.storage_mgr => t {
+                t.bookmark().add(self.context.session_id(), self.context.layer_id(), name, time)?;
+            }
+        }
+
+        self.reload_bookmarks()
+    }
+
+    pub fn remove_bookmark(&mut self, name: &BookmarkName) -> Result<()> {
+        self.check_writable()?;
+
+        transaction! {
+            self.storage_mgr => t {
+                t.bookmark().remove(self.context.session_id(), name)?;
+            }
+        }
+
+        self.reload_bookmarks()
+    }
+
+    fn reload_bookmarks(&mut self) -> Result<()> {
+        transaction! {
+            self.storage_mgr => t {
+                self.bookmarks = t.bookmark().get_list(self.context.session_id())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn breakpoints(&self) -> &Breakpoints {
+        &self.breakpoints
+    }
+
+    pub fn breakpoints_mut(&mut self) -> &mut Breakpoints {
+        &mut self.breakpoints
+    }
+
     pub fn virtual_step(&self) -> chrono::Duration {
         self.virtual_step
     }
@@ -262,10 +391,46 @@ impl Engine {
         )
         .unwrap();
 
+        if !self.bookmarks.is_empty() {
+            writeln!(&mut stats_text, "bookmarks:").unwrap();
+
+            let next_bookmark = self.next_bookmark().map(|bookmark| &bookmark.name);
+
+            for bookmark in self.bookmarks.iter() {
+                let marker = if Some(&bookmark.name) == next_bookmark {
+                    ">"
+                } else {
+                    " "
+                };
+
+                writeln!(
+                    &mut stats_text,
+                    "{} {} {}",
+                    marker,
+                    TimeFormat::VirtualTimeShort(bookmark.time),
+                    bookmark.name
+                )
+                .unwrap();
+            }
+        }
+
         self.scene.draw_text(window, &stats_text, pos, Color::new(1.0, 0.0, 1.0));
 
         if self.is_context_change_spawned {
-            let upd_text = format!("*UPDATING CONTEXT*");
+            let mut upd_text = format!("*UPDATING CONTEXT*");
+
+            if let Some(progress) = self.context_progress.as_ref() {
+                write!(
+                    &mut upd_text,
+                    "\n{} {:.0}%",
+                    progress.progress_bar(PROGRESS_BAR_WIDTH),
+                    progress.ratio() * 100.0
+                ).unwrap();
+
+                if let Some(eta) = progress.eta() {
+                    write!(&mut upd_text, " ETA {}", TimeFormat::RealTime(eta)).unwrap();
+                }
+            }
 
             let pos = Point2::new(pos[0], pos[1] + 450.0);
 
@@ -278,9 +443,13 @@ impl Engine {
     }
 
     pub fn add_layer(&mut self, layer: Layer) -> Result<()> {
+        self.check_writable()?;
+
         let session_id = self.context.session_id();
         let active_layer_id = self.context.layer_id();
-        let new_layer_start_time = self.virtual_time;
+        let new_layer_start_time = layer.start_time();
+
+        let overrides = self.resolve_overrides(&layer, new_layer_start_time)?;
 
         let new_layer_id;
 
@@ -297,6 +466,14 @@ impl Engine {
                     let object_id = t.object().add(session_id, new_layer_id, object)?;
                     t.location().add(object_id, new_layer_id, coord)?;
                 }
+
+                for (object_id, coord, mass, removed_time) in overrides {
+                    t.object().set_override(new_layer_id, object_id, None, mass, removed_time)?;
+
+                    if let Some(coord) = coord {
+                        t.location().add(object_id, new_layer_id, coord)?;
+                    }
+                }
             }
         }
 
@@ -305,6 +482,219 @@ impl Engine {
         Ok(())
     }
 
+    /// Checks that a new layer can start at the time:
+    /// the time must not precede the main layer and it must be already computed.
+    /// A layer starting before the active layer is attached to the ancestor containing the time.
+    pub fn check_layer_start_time(&mut self, start_time: chrono::Duration) -> Result<()> {
+        let session_id = self.context.session_id();
+        let active_layer_id = self.context.layer_id();
+
+        let main_start_time;
+        let min_valid_start_time;
+
+        transaction! {
+            self.storage_mgr => t {
+                let main_layer_id = t.layer().get_main_layer(session_id)?;
+                main_start_time = t.layer().get_start_time(main_layer_id)?;
+                min_valid_start_time = t.location()
+                    .get_min_valid_start_time(active_layer_id, start_time)?;
+            }
+        }
+
+        if start_time < main_start_time {
+            return Err(Error::VirtualTime(format!(
+                "new layer can't start at {} before the main layer's start {}",
+                TimeFormat::VirtualTimeShort(start_time),
+                TimeFormat::VirtualTimeShort(main_start_time)
+            )));
+        }
+
+        if start_time > min_valid_start_time {
+            return Err(Error::VirtualTime(format!(
+                "new layer can't start at {}, the active layer is computed up to {}",
+                TimeFormat::VirtualTimeShort(start_time),
+                TimeFormat::VirtualTimeShort(min_valid_start_time)
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Checks the layer's objects parameters and overlaps at the layer's start time.
+    pub fn validate_layer(&mut self, layer: &Layer) -> Result<()> {
+        let start_time = layer.start_time();
+
+        let mut issues = layer.check_parameters();
+
+        // (name, radius, location, is changed by the layer)
+        let mut spheres = layer
+            .iter_objects()
+            .map(|(object, coord)| (object.name().clone(), object.radius(), *coord.location(), true))
+            .collect::<Vec<_>>();
+
+        let attached_actors = self.attached_actors(start_time)?;
+
+        let mut actors = self
+            .context
+            .actors()
+            .iter()
+            .filter(|(object_id, _)| attached_actors.contains(object_id))
+            .map(|(_, actor)| (actor.object().name().clone(), actor.object().radius()))
+            .collect::<Vec<_>>();
+        actors.sort_by(|(lhs, _), (rhs, _)| lhs.cmp(rhs));
+
+        for (object_name, radius) in actors {
+            let object_override = layer.overrides().get(&object_name);
+
+            if object_override.map_or(false, |o| o.removed) {
+                continue;
+            }
+
+            let coord = match self.object_state(&object_name, start_time) {
+                Ok(coord) => coord,
+                Err(Error::ObjectStateUnavailable(..)) => continue,
+                Err(err) => return Err(err),
+            };
+
+            let location = match object_override {
+                Some(object_override) => *object_override.apply(&coord).location(),
+                None => *coord.location(),
+            };
+
+            spheres.push((object_name, radius, location, object_override.is_some()));
+        }
+
+        for (i, (lhs_name, lhs_radius, lhs_location, is_lhs_changed)) in spheres.iter().enumerate() {
+            for (rhs_name, rhs_radius, rhs_location, is_rhs_changed) in spheres.iter().skip(i + 1) {
+                if !is_lhs_changed && !is_rhs_changed {
+                    continue;
+                }
+
+                let depth = lhs_radius + rhs_radius - (rhs_location - lhs_location).norm();
+
+                if depth > 0.0 {
+                    issues.push(LayerIssue::Overlap(lhs_name.clone(), rhs_name.clone(), depth));
+                }
+            }
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(make_error![Error::Layer::Invalid(issues)])
+        }
+    }
+
+    /// Returns the ids of the actors visible from the layer a new layer starting at the time
+    /// is attached to. A layer starting before the active layer is attached to its ancestor,
+    /// so the objects of the layers in between aren't inherited.
+    fn attached_actors(&mut self, start_time: chrono::Duration) -> Result<HashSet<ObjectId>> {
+        let active_layer_id = self.context.layer_id();
+        let ancestors;
+
+        transaction! {
+            self.storage_mgr => t {
+                let parent_id = t.layer().get_current_layer_id(active_layer_id, start_time)?;
+                ancestors = t.layer().layer_ancestors(parent_id)?;
+            }
+        }
+
+        let attached_actors = self
+            .context
+            .actors()
+            .iter()
+            .filter(|(_, actor)| ancestors.contains(&actor.object().layer_id()))
+            .map(|(object_id, _)| *object_id)
+            .collect();
+
+        Ok(attached_actors)
+    }
+
+    /// Resolves the layer's overrides of the inherited objects at the layer's start time.
+    ///
+    /// The states of the overridden objects are restarted in the new layer,
+    /// the other inherited objects keep their tracks.
+    fn resolve_overrides(
+        &mut self,
+        layer: &Layer,
+        start_time: chrono::Duration,
+    ) -> Result<Vec<(ObjectId, Option<GenCoord>, Option<Mass>, Option<chrono::Duration>)>> {
+        let attached_actors = self.attached_actors(start_time)?;
+        let mut resolved = vec![];
+
+        for (object_name, object_override) in layer.overrides() {
+            let object_id = self
+                .context
+                .actor_id(object_name)
+                .filter(|object_id| attached_actors.contains(object_id))
+                .ok_or_else(|| make_error![Error::Layer::ObjectNotFound(object_name.clone())])?;
+
+            if object_override.removed {
+                resolved.push((object_id, None, object_override.mass, Some(start_time)));
+                continue;
+            }
+
+            let coord = self.object_state(object_name, start_time)?;
+
+            resolved.push((
+                object_id,
+                Some(object_override.apply(&coord)),
+                object_override.mass,
+                None,
+            ));
+        }
+
+        resolved.sort_by_key(|(object_id, ..)| *object_id);
+
+        Ok(resolved)
+    }
+
+    /// Returns the active layer's ancestors (starting from the main layer)
+    /// and the names of the layers where the objects are overridden,
+    /// i.e. the descendants of the objects' layers having an override.
+    pub fn objects_layers(
+        &mut self,
+    ) -> Result<(Vec<(LayerId, LayerName)>, HashMap<ObjectId, Vec<LayerName>>)> {
+        let active_layer_id = self.context.layer_id();
+
+        let mut layers = vec![];
+        let mut overrides = HashMap::new();
+        transaction! {
+            self.storage_mgr => t {
+                let layer = t.layer();
+
+                let mut ancestors = layer.layer_ancestors(active_layer_id)?;
+                ancestors.sort();
+
+                for layer_id in ancestors {
+                    layers.push((layer_id, layer.get_name(layer_id)?));
+                }
+
+                let mut layers_names = layers.iter().cloned().collect::<HashMap<_, _>>();
+                let overridden = t.object().get_overridden(active_layer_id)?;
+
+                // The overriding layers might be outside of the active layer's branch.
+                for (layer_id, object_id) in overridden {
+                    let layer_name = match layers_names.get(&layer_id) {
+                        Some(name) => name.clone(),
+                        None => {
+                            let name = t.layer().get_name(layer_id)?;
+                            layers_names.insert(layer_id, name.clone());
+                            name
+                        }
+                    };
+
+                    overrides
+                        .entry(object_id)
+                        .or_insert_with(Vec::new)
+                        .push(layer_name);
+                }
+            }
+        }
+
+        Ok((layers, overrides))
+    }
+
     pub fn is_object_exists(&mut self, object_name: &ObjectName) -> Result<bool> {
         let result;
         transaction! {
@@ -316,6 +706,158 @@ impl Engine {
         result
     }
 
+    /// Returns the object's location and velocity at the given time.
+    ///
+    /// The current context is used if it covers the time,
+    /// otherwise the state is interpolated from the stored locations.
+    pub fn object_state(
+        &mut self,
+        object_name: &ObjectName,
+        time: chrono::Duration,
+    ) -> Result<GenCoord> {
+        let object_id = self
+            .context
+            .actor_id(object_name)
+            .ok_or_else(|| make_error![Error::Layer::ObjectNotFound(object_name.clone())])?;
+
+        if let Some(coord) = self.context.object_gen_coord(object_id, time.as_relative_time()) {
+            return Ok(GenCoord::new(
+                time,
+                coord.location().clone(),
+                coord.velocity().clone(),
+            ));
+        }
+
+        let locations;
+        transaction! {
+            self.storage_mgr => t {
+                locations = t.location().get_object_locations_around(
+                    self.context.layer_id(),
+                    object_id,
+                    time
+                )?;
+            }
+        }
+
+        let compute_step = self.context.actor(&object_id).object().compute_step();
+
+        interpolate_state(&locations, time, compute_step)
+            .ok_or_else(|| Error::ObjectStateUnavailable(object_name.clone(), time))
+    }
+
+    pub fn closest_approach(
+        &self,
+        lhs_name: &ObjectName,
+        rhs_name: &ObjectName,
+        from: chrono::Duration,
+        to: chrono::Duration,
+    ) -> Result<Option<Approach>> {
+        let context = self.load_analysis_context(self.context.layer_id(), from, to)?;
+
+        let lhs_id = context
+            .actor_id(lhs_name)
+            .ok_or_else(|| make_error![Error::Layer::ObjectNotFound(lhs_name.clone())])?;
+
+        let rhs_id = context
+            .actor_id(rhs_name)
+            .ok_or_else(|| make_error![Error::Layer::ObjectNotFound(rhs_name.clone())])?;
+
+        let range = from.as_relative_time()..to.as_relative_time();
+
+        Ok(analysis::closest_approach(&context, lhs_id, rhs_id, &range))
+    }
+
+    pub fn close_approaches(
+        &self,
+        threshold: Distance,
+        from: chrono::Duration,
+        to: chrono::Duration,
+    ) -> Result<Vec<(ObjectName, ObjectName, Approach)>> {
+        let context = self.load_analysis_context(self.context.layer_id(), from, to)?;
+
+        let range = from.as_relative_time()..to.as_relative_time();
+
+        let approaches = analysis::close_approaches(&context, threshold, &range)
+            .into_iter()
+            .map(|(lhs_id, rhs_id, approach)| {
+                (
+                    context.actor(&lhs_id).object().name().clone(),
+                    context.actor(&rhs_id).object().name().clone(),
+                    approach,
+                )
+            })
+            .collect();
+
+        Ok(approaches)
+    }
+
+    /// Compares the tracks of two layers of the current session.
+    /// The second layer is shown as ghost objects if `ghost` is set.
+    pub fn layer_diff(
+        &mut self,
+        lhs_layer_name: &LayerName,
+        rhs_layer_name: &LayerName,
+        from: chrono::Duration,
+        to: chrono::Duration,
+        step: chrono::Duration,
+        eps: Distance,
+        ghost: bool,
+    ) -> Result<LayerDiff> {
+        if step <= chrono::Duration::zero() {
+            return Err(Error::VirtualTime("comparison step must be greater than zero".into()));
+        }
+
+        let lhs_layer_id = self.get_layer_id(lhs_layer_name)?;
+        let rhs_layer_id = self.get_layer_id(rhs_layer_name)?;
+
+        let lhs_context = self.load_analysis_context(lhs_layer_id, from, to)?;
+        let rhs_context = self.load_analysis_context(rhs_layer_id, from, to)?;
+
+        let range = from.as_relative_time()..to.as_relative_time();
+
+        let diff = analysis::layer_diff(
+            &lhs_context,
+            &rhs_context,
+            &range,
+            step.as_relative_time(),
+            eps,
+        );
+
+        if ghost {
+            self.scene.set_ghost(rhs_context, self.virtual_time);
+        }
+
+        Ok(diff)
+    }
+
+    pub fn hide_ghost(&mut self) {
+        self.scene.clear_ghost();
+    }
+
+    /// Loads the stored tracks of the layer.
+    /// The analysis doesn't compute missing tracks, so the active context stays intact.
+    fn load_analysis_context(
+        &self,
+        layer_id: LayerId,
+        from: chrono::Duration,
+        to: chrono::Duration,
+    ) -> Result<Context> {
+        if from >= to {
+            return Err(Error::VirtualTime(format!(
+                "invalid time range [{}; {}]",
+                TimeFormat::VirtualTimeShort(from),
+                TimeFormat::VirtualTimeShort(to)
+            )));
+        }
+
+        Context::load(
+            self.storage_mgr.clone(),
+            self.context.session_id(),
+            layer_id,
+            TimeRange::with_bounds(from, to),
+        )
+    }
+
     pub fn get_layer_id(&mut self, layer_name: &LayerName) -> Result<LayerId> {
         let result;
 
@@ -329,12 +871,15 @@ impl Engine {
     }
 
     pub fn remove_layer(&mut self, layer_name: &LayerName) -> Result<()> {
+        self.check_writable()?;
+
+        let session_id = self.context.session_id();
+
         transaction! {
             self.storage_mgr => t {
-                match self.get_layer_id(layer_name) {
+                match t.layer().get_layer_id(session_id, layer_name) {
                     Ok(layer_id) => {
-                        let mut layer = t.layer();
-                        let active_ancestors = layer.layer_ancestors(self.context.layer_id())?;
+                        let active_ancestors = t.layer().layer_ancestors(self.context.layer_id())?;
 
                         if active_ancestors.contains(&layer_id) {
                             error! {
@@ -342,7 +887,7 @@ impl Engine {
                                 "unable to remove active layer or it's ancestors"
                             }
                         } else {
-                            layer.remove_layer(layer_id)?;
+                            t.journal().remove_layer(session_id, layer_id)?;
                         }
                     },
                     Err(err) => warn!("unable to remove a layer: {}", err)
@@ -353,18 +898,128 @@ impl Engine {
         Ok(())
     }
 
+    /// Moves the layer's tracks, objects and children into its parent layer.
+    /// The parent's tracks the layer doesn't continue are cut at its start and recomputed.
+    /// Nothing is changed if `dry_run` is set.
+    ///
+    /// The merge isn't journaled and the journaled operations can't be undone across it,
+    /// so the session's journal is purged.
+    pub fn merge_layer(&mut self, layer_name: &LayerName, dry_run: bool) -> Result<LayerMergeInfo> {
+        if !dry_run {
+            self.check_writable()?;
+        }
+
+        let session_id = self.context.session_id();
+        let active_layer_id = self.context.layer_id();
+
+        let merge_info;
+        let layer_id;
+        let active_ancestors;
+
+        transaction! {
+            self.storage_mgr => t(RepeatableRead) {
+                let layer = t.layer();
+
+                layer_id = layer.get_layer_id(session_id, layer_name)?;
+                active_ancestors = layer.layer_ancestors(active_layer_id)?;
+                merge_info = layer.get_merge_info(layer_id)?;
+
+                if !dry_run {
+                    if !merge_info.conflicting_layers.is_empty() {
+                        return Err(make_error![Error::Layer::MergeConflict(
+                            layer_name.clone(),
+                            merge_info.conflicting_layers.clone()
+                        )]);
+                    }
+
+                    layer.merge_layer(layer_id)?;
+                    t.journal().purge(Some(session_id))?;
+                }
+            }
+        }
+
+        if !dry_run && active_ancestors.contains(&layer_id) {
+            let new_active_layer_id = if active_layer_id == layer_id {
+                self.get_layer_id(&merge_info.parent_name)?
+            } else {
+                active_layer_id
+            };
+
+            self.invalidate_context(new_active_layer_id);
+        }
+
+        Ok(merge_info)
+    }
+
+    /// Copies the layer (and its descendants if `recursive` is set) into the session.
+    /// The copy becomes a child of the `parent` layer (the main layer by default).
+    pub fn copy_layer(
+        &mut self,
+        layer_name: &LayerName,
+        to_session: Option<&SessionName>,
+        parent: Option<&LayerName>,
+        recursive: bool,
+        drop_computed: bool,
+    ) -> Result<LayerCopyInfo> {
+        if to_session.is_none() {
+            self.check_writable()?;
+        }
+
+        let session_id = self.context.session_id();
+
+        let copy_info;
+        transaction! {
+            self.storage_mgr => t(RepeatableRead) {
+                let dst_session_id = match to_session {
+                    Some(name) => t.session().get_unlocked_id(name, session_id)?,
+                    None => session_id,
+                };
+
+                let layer = t.layer();
+
+                let src_layer_id = layer.get_layer_id(session_id, layer_name)?;
+                let dst_parent_id = match parent {
+                    Some(name) => layer.get_layer_id(dst_session_id, name)?,
+                    None => layer.get_main_layer(dst_session_id)?,
+                };
+
+                let start_time = layer.get_start_time(src_layer_id)?;
+                let parent_start_time = layer.get_start_time(dst_parent_id)?;
+
+                if start_time < parent_start_time {
+                    return Err(Error::VirtualTime(format!(
+                        "the layer starts at {} before the parent layer's start {}",
+                        TimeFormat::VirtualTimeShort(start_time),
+                        TimeFormat::VirtualTimeShort(parent_start_time)
+                    )));
+                }
+
+                copy_info = layer.copy_layer(
+                    src_layer_id,
+                    dst_session_id,
+                    dst_parent_id,
+                    recursive,
+                    !drop_computed
+                )?;
+            }
+        }
+
+        Ok(copy_info)
+    }
+
     pub fn rename_layer(
         &mut self,
         old_layer_name: &LayerName,
         new_layer_name: &LayerName,
     ) -> Result<()> {
+        self.check_writable()?;
+
         transaction! {
             self.storage_mgr => t {
-                let mut layer = t.layer();
-
-                let id = layer.get_layer_id(self.context.session_id(), old_layer_name)?;
+                let session_id = self.context.session_id();
+                let id = t.layer().get_layer_id(session_id, old_layer_name)?;
 
-                layer.rename_layer(id, new_layer_name)?;
+                t.journal().rename_layer(session_id, id, new_layer_name)?;
             }
         }
 
@@ -388,9 +1043,9 @@ impl Engine {
         let result;
         transaction! {
             self.storage_mgr => t {
-                let mut layer = t.layer();
+                let layer = t.layer();
 
-                let id = self.current_layer_id(&mut layer)?;
+                let id = self.current_layer_id(layer)?;
 
                 result = layer.get_name(id);
             }
@@ -399,82 +1054,33 @@ impl Engine {
         result
     }
 
-    fn current_layer_id(&mut self, layer_api: &mut storage::Layer) -> Result<LayerId> {
+    fn current_layer_id(&mut self, layer_api: &mut dyn LayerApi) -> Result<LayerId> {
         layer_api.get_current_layer_id(self.context.layer_id(), self.virtual_time)
     }
 
-    pub fn get_session_layers(&mut self) -> Result<StringItem> {
-        let result;
+    pub fn get_session_layers(&mut self) -> Result<(SessionName, Vec<LayerInfo>)> {
+        let session_id = self.context.session_id();
+        let active_layer_id = self.context.layer_id();
+
+        let session_name;
+        let mut layers;
         transaction! {
             self.storage_mgr => t {
-                let session_id = self.context.session_id();
-                let session_name = t.session().get_name(session_id)?;
-
-                let tree_title = format!("layers of the session \"{}\"", session_name);
-                let mut builder = TreeBuilder::new(tree_title);
+                session_name = t.session().get_name(session_id)?;
 
-                let mut layer = t.layer();
-                let current_layer_id = self.current_layer_id(&mut layer)?;
-                let parent_layer_id = layer.get_main_layer(session_id)?;
+                let layer = t.layer();
+                let current_layer_id = self.current_layer_id(layer)?;
 
-                self.get_session_layers_helper(
-                    &mut layer,
-                    &mut builder,
-                    current_layer_id,
-                    parent_layer_id
-                )?;
+                layers = layer.get_layers_info(session_id)?;
 
-                result = builder.build();
+                for info in layers.iter_mut() {
+                    info.is_active = info.id == active_layer_id;
+                    info.is_current = info.id == current_layer_id;
+                }
             }
         }
 
-        Ok(result)
-    }
-
-    fn get_session_layers_helper(
-        &mut self,
-        layer_api: &mut storage::Layer,
-        builder: &mut TreeBuilder,
-        current_layer_id: LayerId,
-        parent_layer_id: LayerId,
-    ) -> Result<()> {
-        let start_time = layer_api.get_start_time(parent_layer_id)?;
-
-        let active_layer_id = self.context().layer_id();
-        let layer_name = layer_api.get_name(parent_layer_id)?;
-        let layer_status =
-            if parent_layer_id == active_layer_id && parent_layer_id == current_layer_id {
-                "[active/current] "
-            } else if parent_layer_id == active_layer_id {
-                "[active] "
-            } else if parent_layer_id == current_layer_id {
-                "[current] "
-            } else {
-                ""
-            };
-
-        let layer_info = format!(
-            "{}{}: {}",
-            layer_status,
-            layer_name,
-            TimeFormat::VirtualTimeShort(start_time)
-        );
-
-        let children = layer_api.get_layer_children(self.context.session_id(), parent_layer_id)?;
-
-        // if children.is_empty() {
-        //     builder.add_empty_child(layer_info);
-        // } else {
-        builder.begin_child(layer_info);
-
-        for &child_id in children.iter() {
-            self.get_session_layers_helper(layer_api, builder, current_layer_id, child_id)?;
-        }
-
-        builder.end_child();
-        // }
-
-        Ok(())
+        Ok((session_name, layers))
     }
 
     pub fn select_layer(&mut self, layer_name: &LayerName) -> Result<()> {
@@ -525,10 +1131,12 @@ impl Engine {
     }
 
     pub fn new_session(&mut self, session_name: Option<SessionName>) -> Result<()> {
-        self.new_session_helper(session_name, Some(self.context.session_id()))
+        self.new_session_helper(session_name, self.locked_session_id())
     }
 
     pub fn save_session(&mut self, session_name: SessionName) -> Result<()> {
+        self.check_writable()?;
+
         transaction! {
             self.storage_mgr => t {
                 t.session().save(self.context.session_id(), &session_name)?;
@@ -538,20 +1146,26 @@ impl Engine {
         Ok(())
     }
 
-    /// Returns max object's default name number
-    pub fn load_session(&mut self, session_name: SessionName) -> Result<i64> {
+    /// Returns max object's default name number.
+    /// The session attached read-only stays locked by its owner and is never modified.
+    pub fn load_session(&mut self, session_name: SessionName, read_only: bool) -> Result<i64> {
         let num;
 
         transaction! {
             self.storage_mgr => t {
-                let mut session = t.session();
-                let (new_session_id, new_layer_id) = session.load(&session_name)?;
+                let session = t.session();
+                let (new_session_id, new_layer_id) = if read_only {
+                    session.attach(&session_name)?
+                } else {
+                    session.load(&session_name)?
+                };
 
                 self.set_new_session(
-                    &mut session,
+                    session,
                     new_session_id,
                     new_layer_id,
-                    Some(self.context.session_id())
+                    self.locked_session_id(),
+                    read_only
                 )?;
 
                 num = t.object().get_max_object_default_name_num(new_session_id)?;
@@ -566,6 +1180,8 @@ impl Engine {
         old_session_name: SessionName,
         new_session_name: SessionName,
     ) -> Result<()> {
+        self.check_writable()?;
+
         transaction! {
             self.storage_mgr => t {
                 t.session().rename(&old_session_name, &new_session_name)?;
@@ -576,9 +1192,214 @@ impl Engine {
     }
 
     pub fn delete_session(&mut self, session_name: SessionName) -> Result<()> {
+        self.check_writable()?;
+
         transaction! {
             self.storage_mgr => t {
-                t.session().delete(&session_name)?;
+                t.journal().delete_session(self.context.session_id(), &session_name)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Copies the session (the current one by default) into a new saved session
+    /// with all its layers, objects, overrides, bookmarks and computed locations.
+    /// The copy is made inside the storage without loading the session.
+    pub fn clone_session(
+        &mut self,
+        session_name: Option<&SessionName>,
+        new_session_name: &SessionName,
+    ) -> Result<()> {
+        let session_id = self.context.session_id();
+
+        transaction! {
+            self.storage_mgr => t(RepeatableRead) {
+                let cloned_session_id = match session_name {
+                    Some(name) => t.session().get_unlocked_id(name, session_id)?,
+                    None => session_id,
+                };
+
+                let (new_session_id, new_main_layer_id) = t.session().new(Some(new_session_name.clone()))?;
+                t.session().unlock(new_session_id)?;
+
+                let main_layer_id = t.layer().get_main_layer(cloned_session_id)?;
+                let main_layer_name = t.layer().get_name(main_layer_id)?;
+
+                // The source layers' names can't clash with the new main layer then.
+                if main_layer_name != t.layer().get_name(new_main_layer_id)? {
+                    t.layer().rename_layer(new_main_layer_id, &main_layer_name)?;
+                }
+
+                // The main layer is copied as a child of the new main layer
+                // and merged into it afterwards.
+                let with_descendants = true;
+                let with_computed = true;
+                let copy_info = t.layer().copy_layer(
+                    main_layer_id,
+                    new_session_id,
+                    new_main_layer_id,
+                    with_descendants,
+                    with_computed
+                )?;
+
+                let bookmarks = t.bookmark().get_list(cloned_session_id)?;
+
+                let mut copied_main_layer_id = None;
+                for (src_name, new_name) in copy_info.layers.iter() {
+                    let src_layer_id = t.layer().get_layer_id(cloned_session_id, src_name)?;
+                    let new_layer_id = t.layer().get_layer_id(new_session_id, new_name)?;
+
+                    if src_layer_id == main_layer_id {
+                        copied_main_layer_id = Some(new_layer_id);
+                    }
+
+                    for bookmark in bookmarks.iter().filter(|bookmark| bookmark.layer_name == *src_name) {
+                        t.bookmark().add(new_session_id, new_layer_id, &bookmark.name, bookmark.time)?;
+                    }
+                }
+
+                if let Some(copied_main_layer_id) = copied_main_layer_id {
+                    t.layer().merge_layer(copied_main_layer_id)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Collects the session (the current one by default) with all its layers.
+    pub fn export_session(
+        &mut self,
+        session_name: Option<&SessionName>,
+        with_computed: bool,
+    ) -> Result<SessionDump> {
+        let session_id = self.context.session_id();
+
+        let dump;
+        transaction! {
+            self.storage_mgr => t(RepeatableRead) {
+                let exported_session_id = match session_name {
+                    Some(name) => t.session().get_unlocked_id(name, session_id)?,
+                    None => session_id,
+                };
+
+                dump = t.session().dump(exported_session_id, with_computed)?;
+            }
+        }
+
+        Ok(dump)
+    }
+
+    /// Creates a new saved session from the dump. Returns the new session's name.
+    pub fn import_session(
+        &mut self,
+        dump: &SessionDump,
+        session_name: Option<SessionName>,
+    ) -> Result<SessionName> {
+        let session_name = session_name
+            .or_else(|| dump.session_name.clone())
+            .ok_or_else(|| {
+                make_error![Error::SessionFile(
+                    "the exported session is unnamed\nHINT: set the new name with `-n`".into()
+                )]
+            })?;
+
+        transaction! {
+            self.storage_mgr => t {
+                dump.import(&mut *t, &session_name)?;
+            }
+        }
+
+        Ok(session_name)
+    }
+
+    /// Rolls back the last layer/session operation made in the current session.
+    pub fn undo(&mut self) -> Result<JournalOperation> {
+        self.check_writable()?;
+
+        let session_id = self.context.session_id();
+
+        let operation;
+        transaction! {
+            self.storage_mgr => t {
+                operation = t.journal()
+                    .undo(session_id)?
+                    .ok_or_else(|| Error::Journal("nothing to undo".into()))?;
+            }
+        }
+
+        Ok(operation)
+    }
+
+    /// Repeats the last undone layer/session operation.
+    pub fn redo(&mut self) -> Result<JournalOperation> {
+        self.check_writable()?;
+
+        let session_id = self.context.session_id();
+
+        let operation;
+        transaction! {
+            self.storage_mgr => t {
+                let active_ancestors = t.layer().layer_ancestors(self.context.layer_id())?;
+
+                operation = t.journal()
+                    .redo(session_id)?
+                    .ok_or_else(|| Error::Journal("nothing to redo".into()))?;
+
+                // The transaction isn't committed, so the layer stays intact.
+                if let JournalOperation::RemoveLayer(layer_id, ref name) = operation {
+                    if active_ancestors.contains(&layer_id) {
+                        return Err(Error::Journal(format!(
+                            "unable to remove active layer or it's ancestors (\"{}\")",
+                            name
+                        )));
+                    }
+                }
+            }
+        }
+
+        Ok(operation)
+    }
+
+    /// Removes the stored locations of the current session except the initial ones,
+    /// the collision points and a keyframe per `keyframe_step`.
+    /// The removed ranges are recomputed from the keyframes when needed.
+    ///
+    /// Returns the number of the removed locations.
+    pub fn compact(&mut self, keyframe_step: chrono::Duration) -> Result<i64> {
+        self.check_writable()?;
+
+        if keyframe_step <= chrono::Duration::zero() {
+            return Err(Error::VirtualTime("the keyframe step must be positive".into()));
+        }
+
+        let session_id = self.context.session_id();
+
+        let removed_count;
+        transaction! {
+            self.storage_mgr => t {
+                removed_count = t.location().compact(session_id, keyframe_step)?;
+            }
+        }
+
+        Ok(removed_count)
+    }
+
+    /// Forgets the journal of the current session (or of all sessions)
+    /// and deletes the kept rows.
+    pub fn purge_journal(&mut self, all_sessions: bool) -> Result<()> {
+        let session_id = if all_sessions {
+            None
+        } else {
+            self.check_writable()?;
+
+            Some(self.context.session_id())
+        };
+
+        transaction! {
+            self.storage_mgr => t {
+                t.journal().purge(session_id)?;
             }
         }
 
@@ -592,11 +1413,11 @@ impl Engine {
     ) -> Result<()> {
         transaction! {
             self.storage_mgr => t {
-                let mut session = t.session();
+                let session = t.session();
 
                 let (new_session_id, new_layer_id) = session.new(session_name)?;
 
-                self.set_new_session(&mut session, new_session_id, new_layer_id, old_session_id)?;
+                self.set_new_session(session, new_session_id, new_layer_id, old_session_id, false)?;
             }
         }
 
@@ -605,21 +1426,69 @@ impl Engine {
 
     fn set_new_session(
         &mut self,
-        session: &mut storage::Session,
+        session: &mut dyn SessionApi,
         new_session_id: SessionId,
         new_layer_id: LayerId,
         old_session_id: Option<SessionId>,
+        is_read_only: bool,
     ) -> Result<()> {
         if let Some(old_session_id) = old_session_id {
             session.unlock(old_session_id)?;
         }
 
+        self.is_read_only = is_read_only;
+
+        // Only a session attached read-only is changed by another application.
+        if !is_read_only {
+            self.storage_events = None;
+        } else if self.storage_events.is_none() {
+            self.storage_events = self.storage_mgr.subscribe().unwrap_or_else(|err| {
+                warn! {
+                    target: LOG_TARGET,
+                    "the changes made by the session owner won't be seen: {}",
+                    err
+                }
+
+                None
+            });
+        }
+
         // self.spawn_context_change(new_session_id, new_layer_id, TimeRange::default())
         self.schedule_context_change(new_session_id, new_layer_id, TimeRange::default());
 
         Ok(())
     }
 
+    pub fn is_read_only(&self) -> bool {
+        self.is_read_only
+    }
+
+    pub fn check_writable(&self) -> Result<()> {
+        if self.is_read_only {
+            Err(Error::ReadOnlySession)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The current session if it is locked by this application.
+    fn locked_session_id(&self) -> Option<SessionId> {
+        if self.is_read_only {
+            None
+        } else {
+            Some(self.context.session_id())
+        }
+    }
+
+    /// Reloads the context from scratch, e.g. after the layers structure has been changed.
+    fn invalidate_context(&mut self, layer_id: LayerId) {
+        let session_id = self.context.session_id();
+        let time_range = self.context.time_range().clone();
+
+        self.context = Arc::new(Context::new(session_id, LayerId::default()));
+        self.schedule_context_change(session_id, layer_id, time_range);
+    }
+
     fn schedule_context_change(
         &mut self,
         session_id: SessionId,
@@ -637,6 +1506,7 @@ impl Engine {
             session_id,
             layer_id,
             time_range,
+            is_read_only: self.is_read_only,
         };
 
         self.context_change_params = Some(change_params);
@@ -664,23 +1534,33 @@ impl Engine {
 
         self.is_context_change_spawned = true;
 
-        let min_valid_start_time;
+        let start_time;
         transaction! {
             self.storage_mgr => t {
-                min_valid_start_time = t.location()
+                let location = t.location();
+                let min_valid_start_time = location
                     .get_min_valid_start_time(change_params.layer_id, change_params.time_range.start())?;
+
+                // The compacted ranges are recomputed from the kept keyframes.
+                start_time = location.get_keyframe_time(
+                    change_params.layer_id,
+                    min_valid_start_time.min(change_params.time_range.start())
+                )?;
             }
         }
 
-        if min_valid_start_time < change_params.time_range.start() {
-            change_params.time_range = TimeRange::with_default_len(min_valid_start_time);
+        if start_time < change_params.time_range.start() {
+            change_params.time_range = TimeRange::with_default_len(start_time);
         }
 
         let (ctx_sender, ctx_recv) = mpsc::channel();
         let (ctx_upd_intrp_sender, ctx_upd_intrp_recv) = mpsc::channel();
+        let (ctx_progress_sender, ctx_progress_recv) = mpsc::channel();
 
         self.context_recv = ctx_recv;
         self.context_upd_intrp = ctx_upd_intrp_sender;
+        self.context_progress_recv = ctx_progress_recv;
+        self.context_progress = None;
 
         let storage_mgr = self.storage_mgr.clone();
         let context = Arc::clone(&self.context);
@@ -695,7 +1575,9 @@ impl Engine {
             if let Ok(new_context) = new_context.update_content(
                 storage_mgr,
                 update_kind,
-                ctx_upd_intrp_recv
+                change_params.is_read_only,
+                ctx_upd_intrp_recv,
+                ctx_progress_sender
             ) {
                 match ctx_sender.send(new_context) {
                     Ok(_) => trace! {
@@ -714,9 +1596,10 @@ impl Engine {
     }
 
     fn set_new_context(&mut self, mut context: Context) -> Result<()> {
-        if self.context.session_id() != context.session_id()
-            || self.context().layer_id() != context.layer_id()
-        {
+        let is_layer_changed = self.context.session_id() != context.session_id()
+            || self.context().layer_id() != context.layer_id();
+
+        if is_layer_changed {
             self.scene.clear();
         }
 
@@ -724,6 +1607,11 @@ impl Engine {
         self.scene.set_time(&context, self.virtual_time);
 
         self.context = Arc::new(context);
+        self.context_progress = None;
+
+        if is_layer_changed {
+            self.reload_bookmarks()?;
+        }
 
         trace! {
             target: LOG_TARGET,
@@ -743,10 +1631,118 @@ impl Engine {
         Ok(())
     }
 
+    /// Refreshes the session attached read-only with the changes made by its owner.
+    ///
+    /// A locked session is changed by this application only, so its events are skipped.
+    fn handle_storage_events(&mut self) -> Result<()> {
+        let events = match self.storage_events.as_ref() {
+            Some(events) => events.try_iter().collect::<Vec<_>>(),
+            None => return Ok(()),
+        };
+
+        if !self.is_read_only || events.is_empty() {
+            return Ok(());
+        }
+
+        let session_id = self.context.session_id();
+
+        let mut is_layers_changed = false;
+        let mut is_bookmarks_changed = false;
+        let mut computed_layers = HashSet::new();
+
+        for event in events {
+            match event {
+                StorageEvent::LayersChanged(id) if id == session_id => is_layers_changed = true,
+                StorageEvent::LocationsAdded(id, layer_id) if id == session_id => {
+                    computed_layers.insert(layer_id);
+                }
+                StorageEvent::BookmarksChanged(id) if id == session_id => {
+                    is_bookmarks_changed = true
+                }
+                _ => {}
+            }
+        }
+
+        if is_layers_changed {
+            trace! {
+                target: LOG_TARGET,
+                "the layers are changed by the session owner"
+            }
+
+            // The active layer might be removed or merged by the owner.
+            let active_layer_id = self.target_layer_id();
+            let layer_id;
+            transaction! {
+                self.storage_mgr => t {
+                    let is_active_layer_exists = t.layer()
+                        .get_layers_info(session_id)?
+                        .iter()
+                        .any(|layer| layer.id == active_layer_id);
+
+                    layer_id = if is_active_layer_exists {
+                        active_layer_id
+                    } else {
+                        t.layer().get_main_layer(session_id)?
+                    };
+                }
+            }
+
+            // The bookmarks are reloaded with the new context.
+            self.invalidate_context(layer_id);
+        } else if !computed_layers.is_empty() && !self.is_context_change_spawned {
+            // A running context update is skipped, it computes the missing locations anyway.
+            let layer_id = self.target_layer_id();
+            let active_ancestors;
+            let layers_info;
+            transaction! {
+                self.storage_mgr => t {
+                    active_ancestors = t.layer().layer_ancestors(layer_id)?;
+                    layers_info = t.layer().get_layers_info(session_id)?;
+                }
+            }
+
+            let stored_end_time = layers_info
+                .iter()
+                .filter(|layer| active_ancestors.contains(&layer.id) && computed_layers.contains(&layer.id))
+                .filter_map(|layer| layer.last_computed_time)
+                .max();
+
+            // The context has computed its time range itself,
+            // so only the tracks stored beyond it are loaded.
+            let time_range = self.context.time_range().clone();
+            if let Some(stored_end_time) = stored_end_time.filter(|time| *time > time_range.end()) {
+                trace! {
+                    target: LOG_TARGET,
+                    "new locations are computed by the session owner up to {}",
+                    TimeFormat::VirtualTimeShort(stored_end_time)
+                }
+
+                self.schedule_context_change(
+                    session_id,
+                    layer_id,
+                    TimeRange::with_bounds(time_range.start(), stored_end_time)
+                );
+            }
+        }
+
+        if is_bookmarks_changed && !is_layers_changed {
+            self.reload_bookmarks()?;
+        }
+
+        Ok(())
+    }
+
+    /// The layer of the scheduled context if any, the context may be already invalidated.
+    fn target_layer_id(&self) -> LayerId {
+        self.context_change_params
+            .as_ref()
+            .map_or(self.context.layer_id(), |params| params.layer_id)
+    }
+
     fn update_session_access_time(&mut self) -> Result<()> {
-        if self.real_time.num_milliseconds()
+        if !self.is_read_only && self.real_time.num_milliseconds()
             >= (self.last_session_update_time.num_milliseconds()
-                + ACCESS_UPDATE_TIME.num_milliseconds())
+                + self.access_update_time.num_milliseconds())
         {
             trace! {
                 target: LOG_TARGET,
@@ -766,20 +1762,76 @@ impl Engine {
     }
 }
 
+/// Interpolates the state between the stored locations (ordered by time).
+///
+/// The locations farther than `max_step` apart surround a compacted range,
+/// the state inside it is unknown.
+fn interpolate_state(
+    locations: &[(GenCoord, Option<Vector>)],
+    time: chrono::Duration,
+    max_step: chrono::Duration,
+) -> Option<GenCoord> {
+    let lhs = locations.iter().rev().find(|(coord, _)| coord.time() <= time)?;
+    let (lhs_coord, lhs_collision_velocity) = lhs;
+
+    let lhs_velocity = lhs_collision_velocity
+        .as_ref()
+        .unwrap_or(lhs_coord.velocity());
+
+    if lhs_coord.time() == time {
+        return Some(GenCoord::new(
+            time,
+            lhs_coord.location().clone(),
+            lhs_velocity.clone(),
+        ));
+    }
+
+    let (rhs_coord, _) = locations.iter().find(|(coord, _)| coord.time() > time)?;
+
+    if rhs_coord.time() - lhs_coord.time() > max_step {
+        return None;
+    }
+
+    let lhs_t = lhs_coord.time().as_relative_time();
+    let rhs_t = rhs_coord.time().as_relative_time();
+    let t = time.as_relative_time();
+
+    let location = math::hermite_interpolation(
+        lhs_coord.location(),
+        lhs_velocity,
+        lhs_t,
+        rhs_coord.location(),
+        rhs_coord.velocity(),
+        rhs_t,
+        t,
+    );
+
+    let velocity = math::hermite_derivative(
+        lhs_coord.location(),
+        lhs_velocity,
+        lhs_t,
+        rhs_coord.location(),
+        rhs_coord.velocity(),
+        rhs_t,
+        t,
+    );
+
+    Some(GenCoord::new(time, location, velocity))
+}
+
 impl Drop for Engine {
     fn drop(&mut self) {
-        let mut pooled_connection = self
+        if self.is_read_only {
+            return;
+        }
+
+        let mut transaction = self
             .storage_mgr
-            .pool
-            .get()
-            .expect("the pooled connection is expected to be established");
-        let mut transaction = pooled_connection
-            .transaction()
+            .transaction(IsolationLevel::ReadCommitted)
             .expect("the transaction is expected to be started");
 
-        let mut session = storage::Session::new_api(&mut transaction);
-
-        session
+        transaction
+            .session()
             .unlock(self.context.session_id())
             .expect("the session is expected to be unlocked");
 
@@ -2,7 +2,7 @@ use {
     crate::{
         engine::{math, context::{Context, TrackPartInfo, db_util::make_track_part_mbr, TimeRange}},
         object::{GenCoord},
-        r#type::{ObjectId, Vector, Mass, AsRelativeTime, RelativeTime}
+        r#type::{ObjectId, Vector, Mass, AsRelativeTime, AsAbsoluteTime, RelativeTime}
     },
     std::{
         ops::Range,
@@ -153,7 +153,7 @@ pub fn compute_collisions(context: &Context, t: RelativeTime, graph: CollisionGr
 
         let (partners_mass, partners_impulses, collision_dir) = graph.neighbors(lhs)
             .map(|rhs| {
-                let mass = context.actor(&rhs.object_id).object().mass();
+                let mass = context.actor(&rhs.object_id).mass_at(t.as_absolute_time());
 
                 let rhs_path = collision_vectors.object_path(context, &rhs, t);
                 let collision_dir = (lhs_path.end.location() - rhs_path.end.location()).normalize();
@@ -170,7 +170,7 @@ pub fn compute_collisions(context: &Context, t: RelativeTime, graph: CollisionGr
                 }
             );
 
-        let mass = context.actor(&lhs.object_id).object().mass();
+        let mass = context.actor(&lhs.object_id).mass_at(t.as_absolute_time());
         let src_velocity = lhs_path.end.velocity().clone();
 
         let collision_dir = collision_dir.normalize();
@@ -20,6 +20,14 @@ pub struct Scene {
     root: SceneNode,
     objects_map: HashMap<ObjectId, SceneNode>,
     rtree: Option<SceneNode>,
+    ghost: Option<Ghost>,
+}
+
+/// Objects of another layer drawn as wireframe spheres.
+struct Ghost {
+    context: Context,
+    group: SceneNode,
+    objects_map: HashMap<ObjectId, SceneNode>,
 }
 
 impl Scene {
@@ -28,6 +36,59 @@ impl Scene {
             root,
             objects_map: HashMap::new(),
             rtree: None,
+            ghost: None,
+        }
+    }
+
+    pub fn set_ghost(&mut self, context: Context, vtime: chrono::Duration) {
+        self.clear_ghost();
+
+        let mut group = self.root.add_group();
+        let mut objects_map = HashMap::new();
+
+        for (id, actor) in context.actors().iter() {
+            let radius = actor.object().radius();
+            let color = actor.object().color();
+
+            let mut sphere = group.add_sphere(radius);
+            sphere.set_color(color[0], color[1], color[2]);
+            sphere.set_lines_width(1.0);
+            sphere.set_surface_rendering_activation(false);
+            sphere.set_visible(false);
+
+            objects_map.insert(*id, sphere);
+        }
+
+        self.ghost = Some(Ghost {
+            context,
+            group,
+            objects_map,
+        });
+
+        self.set_ghost_time(vtime);
+    }
+
+    pub fn clear_ghost(&mut self) {
+        if let Some(mut ghost) = self.ghost.take() {
+            ghost.group.unlink();
+        }
+    }
+
+    fn set_ghost_time(&mut self, vtime: chrono::Duration) {
+        let ghost = match self.ghost.as_mut() {
+            Some(ghost) => ghost,
+            None => return,
+        };
+
+        let t = vtime.as_relative_time();
+        for (id, node) in ghost.objects_map.iter_mut() {
+            match ghost.context.object_gen_coord(*id, t) {
+                Some(coord) => {
+                    node.set_local_translation(make_translation(coord.location().clone()));
+                    node.set_visible(true);
+                }
+                None => node.set_visible(false),
+            }
         }
     }
 
@@ -74,6 +135,8 @@ impl Scene {
     }
 
     pub fn set_time(&mut self, context: &Context, vtime: chrono::Duration) {
+        self.set_ghost_time(vtime);
+
         let mut visited_objects = HashSet::new();
 
         let t = vtime.as_relative_time();
@@ -101,6 +164,7 @@ impl Scene {
         for unvisited_id in diff {
             let actor = context.actor(unvisited_id);
             match actor.last_gen_coord() {
+                _ if actor.is_removed_at(vtime) => self.hide_object(unvisited_id),
                 None => self.handle_future_object(unvisited_id, actor.object().name()),
                 Some(last_coord) if last_coord.time() > vtime => {
                     self.handle_future_object(unvisited_id, actor.object().name());
@@ -1,5 +1,6 @@
 use super::{
     engine::context::TimeRange,
+    layer::LayerIssue,
     message,
     r#type::{LayerName, ObjectName, TimeFormat},
 };
@@ -32,6 +33,7 @@ pub enum Error {
     Layer(Layer),
     ContextUpdateInterrupted,
     ObjectsNotComputed(TimeRange),
+    ObjectStateUnavailable(ObjectName, chrono::Duration),
     MissingMessage,
     UnknownMessage(String),
     UnexpectedMessage(super::message::Message),
@@ -40,7 +42,13 @@ pub enum Error {
     Parse(Parse),
     CliRead(rustyline::error::ReadlineError),
     VirtualTime(Description),
+    Breakpoint(Description),
+    Bookmark(Description),
+    Journal(Description),
     Storage(Storage),
+    Config(Description),
+    SessionFile(Description),
+    ReadOnlySession,
     SerializeCSV(csv::Error),
     WriterCSV(String),
 }
@@ -52,6 +60,10 @@ pub enum Layer {
     ObjectNotFound(ObjectName),
     ObjectAlreadyAdded(ObjectName),
     ObjectAlreadyExists(ObjectName),
+    NothingToUndo,
+    Invalid(Vec<LayerIssue>),
+    NoParentLayer(LayerName),
+    MergeConflict(LayerName, Vec<LayerName>),
 }
 
 #[derive(Debug)]
@@ -72,6 +84,12 @@ pub enum Storage {
     Layer(postgres::Error),
     Object(postgres::Error),
     Location(postgres::Error),
+    Bookmark(postgres::Error),
+    Journal(postgres::Error),
+    Sqlite(rusqlite::Error),
+    Connection(Description),
+    SchemaVersion(Description),
+    Backend(Description),
 }
 
 impl From<std::io::Error> for Error {
@@ -144,6 +162,12 @@ impl fmt::Display for Error {
                 TimeFormat::VirtualTimeShort(time_range.start()),
                 TimeFormat::VirtualTimeShort(time_range.end())
             ),
+            Error::ObjectStateUnavailable(name, time) => write!(
+                f,
+                "the state of the object \"{}\" at {} is unavailable (is it computed?)",
+                name,
+                TimeFormat::VirtualTimeShort(*time)
+            ),
             Error::MissingMessage => write!(f, "[missing message]"),
             Error::UnknownMessage(msg) => write!(f, "[unknown message] {}", msg),
             Error::UnexpectedMessage(msg) => {
@@ -154,7 +178,17 @@ impl fmt::Display for Error {
             Error::Parse(err) => write!(f, "[parse] {}", err),
             Error::CliRead(err) => write!(f, "[cli] {}", err),
             Error::VirtualTime(desc) => write!(f, "[virtual time] {}", desc),
+            Error::Breakpoint(desc) => write!(f, "[breakpoint] {}", desc),
+            Error::Bookmark(desc) => write!(f, "[bookmark] {}", desc),
+            Error::Journal(desc) => write!(f, "[journal] {}", desc),
             Error::Storage(err) => write!(f, "[storage] {}", err),
+            Error::Config(desc) => write!(f, "[config] {}", desc),
+            Error::SessionFile(desc) => write!(f, "[session file] {}", desc),
+            Error::ReadOnlySession => write!(
+                f,
+                "[session] the session is attached read-only\n\
+                HINT: use `clone-session` to make a writable copy"
+            ),
             Error::SerializeCSV(err) => write!(f, "[serialization csv] {}", err),
             Error::WriterCSV(err) => write!(f, "[write csv] {}", err),
         }
@@ -205,6 +239,23 @@ impl fmt::Display for Layer {
             Self::ObjectAlreadyExists(name) => {
                 write!(f, "pbject \"{}\" alredy exists in the session", name)
             }
+            Self::NothingToUndo => write!(f, "nothing to undo in the layer"),
+            Self::NoParentLayer(name) => write!(f, "layer \"{}\" has no parent layer", name),
+            Self::MergeConflict(name, layers) => write!(
+                f,
+                "layer \"{}\" can't be merged: the layers {} branch later and inherit the replaced tracks\nHINT: remove them first",
+                name,
+                layers.join(", ")
+            ),
+            Self::Invalid(issues) => {
+                write!(f, "the layer is invalid:")?;
+
+                for issue in issues {
+                    write!(f, "\n\t{}", issue)?;
+                }
+
+                write!(f, "\nHINT: use `submit --force` to submit it anyway")
+            }
         }
     }
 }
@@ -237,6 +288,12 @@ impl From<postgres::Error> for Error {
     }
 }
 
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        Self::Storage(Storage::Sqlite(err))
+    }
+}
+
 impl fmt::Display for Storage {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -247,6 +304,16 @@ impl fmt::Display for Storage {
             Self::Layer(err) => write!(f, "layer error: {}", err),
             Self::Object(err) => write!(f, "object error: {}", err),
             Self::Location(err) => write!(f, "location error: {}", err),
+            Self::Bookmark(err) => write!(f, "bookmark error: {}", err),
+            Self::Journal(err) => write!(f, "journal error: {}", err),
+            Self::Sqlite(err) => write!(f, "sqlite error: {}", err),
+            Self::Connection(desc) => write!(
+                f,
+                "unable to connect to the database: {}\nHINT: set the connection with `--db-url`, `APRIORI_DB_URL` or the config file",
+                desc
+            ),
+            Self::SchemaVersion(desc) => write!(f, "{}", desc),
+            Self::Backend(desc) => write!(f, "{}", desc),
         }
     }
 }
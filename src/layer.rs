@@ -2,25 +2,103 @@ use {
     crate::{
         make_error,
         object::{GenCoord, Object},
-        r#type::{LayerName, ObjectName, SessionId},
+        r#type::{Coord, Distance, LayerName, Mass, ObjectName, SessionId, TimeFormat, Vector},
         Result,
     },
-    std::collections::{hash_map::Entry, HashMap},
+    std::{
+        collections::{hash_map::Entry, HashMap},
+        fmt,
+    },
 };
 
 #[derive(Debug)]
 pub struct Layer {
     name: LayerName,
     session_id: SessionId,
+    start_time: chrono::Duration,
     objects_info: HashMap<Object, GenCoord>,
+    overrides: HashMap<ObjectName, ObjectOverride>,
+    history: Vec<LayerChange>,
+}
+
+/// Reverted by `Layer::undo`.
+#[derive(Debug)]
+enum LayerChange {
+    ObjectAdded(ObjectName),
+    ObjectRemoved(Object, GenCoord),
+    ObjectEdited(Object, GenCoord),
+    ObjectOverridden(ObjectName, Option<ObjectOverride>),
+}
+
+#[derive(Debug, Clone)]
+pub enum LayerIssue {
+    InvalidRadius(ObjectName, Distance),
+    InvalidMass(ObjectName, Mass),
+    InvalidComputeStep(ObjectName, chrono::Duration),
+    InvalidVector(ObjectName, &'static str),
+    Overlap(ObjectName, ObjectName, Distance),
+}
+
+impl fmt::Display for LayerIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidRadius(name, radius) => {
+                write!(f, "object \"{}\" has non-positive radius {}", name, radius)
+            }
+            Self::InvalidMass(name, mass) => {
+                write!(f, "object \"{}\" has non-positive mass {}", name, mass)
+            }
+            Self::InvalidComputeStep(name, step) => write!(
+                f,
+                "object \"{}\" has non-positive compute step {}",
+                name,
+                TimeFormat::VirtualTimeShort(*step)
+            ),
+            Self::InvalidVector(name, vector_name) => {
+                write!(f, "object \"{}\" has non-finite {}", name, vector_name)
+            }
+            Self::Overlap(lhs, rhs, depth) => write!(
+                f,
+                "objects \"{}\" and \"{}\" overlap by {}",
+                lhs, rhs, depth
+            ),
+        }
+    }
+}
+
+/// Changes of an inherited object applied from the layer's start time.
+#[derive(Debug, Clone, Default)]
+pub struct ObjectOverride {
+    pub location: Option<Vector>,
+    pub velocity: Option<Vector>,
+    pub velocity_scale: Option<Coord>,
+    pub mass: Option<Mass>,
+    pub removed: bool,
+}
+
+impl ObjectOverride {
+    /// Applies the location and velocity changes to the inherited object's state.
+    pub fn apply(&self, coord: &GenCoord) -> GenCoord {
+        let location = self.location.unwrap_or(*coord.location());
+
+        let mut velocity = self.velocity.unwrap_or(*coord.velocity());
+        if let Some(scale) = self.velocity_scale {
+            velocity.scale_mut(scale);
+        }
+
+        GenCoord::new(coord.time(), location, velocity)
+    }
 }
 
 impl Layer {
-    pub fn new(name: LayerName, session_id: SessionId) -> Self {
+    pub fn new(name: LayerName, session_id: SessionId, start_time: chrono::Duration) -> Self {
         Self {
             name,
             session_id,
+            start_time,
             objects_info: HashMap::new(),
+            overrides: HashMap::new(),
+            history: vec![],
         }
     }
 
@@ -28,10 +106,17 @@ impl Layer {
         &self.name
     }
 
+    pub fn start_time(&self) -> chrono::Duration {
+        self.start_time
+    }
+
     pub fn add_object(&mut self, object: Object, coord: GenCoord) -> Result<()> {
         match self.objects_info.entry(object) {
             Entry::Vacant(entry) => {
+                let object_name = entry.key().name().clone();
+
                 entry.insert(coord);
+                self.history.push(LayerChange::ObjectAdded(object_name));
                 Ok(())
             }
             Entry::Occupied(entry) => Err(make_error!(Error::Layer::ObjectAlreadyAdded(
@@ -52,6 +137,130 @@ impl Layer {
         self.objects_info.get_key_value(object_name)
     }
 
+    pub fn remove_object(&mut self, object_name: &ObjectName) -> Result<()> {
+        let (object, coord) = self
+            .objects_info
+            .remove_entry(object_name)
+            .ok_or_else(|| make_error!(Error::Layer::ObjectNotFound(object_name.clone())))?;
+
+        self.history.push(LayerChange::ObjectRemoved(object, coord));
+        Ok(())
+    }
+
+    /// Changes the added object's parameters and initial state.
+    pub fn edit_object(
+        &mut self,
+        object_name: &ObjectName,
+        edit: impl FnOnce(&mut Object, &mut GenCoord),
+    ) -> Result<()> {
+        let (object, coord) = self
+            .objects_info
+            .remove_entry(object_name)
+            .ok_or_else(|| make_error!(Error::Layer::ObjectNotFound(object_name.clone())))?;
+
+        let mut new_object = object.clone();
+        let mut new_coord = coord.clone();
+        edit(&mut new_object, &mut new_coord);
+
+        self.objects_info.insert(new_object, new_coord);
+        self.history.push(LayerChange::ObjectEdited(object, coord));
+        Ok(())
+    }
+
+    /// Reverts the last change of the layer.
+    pub fn undo(&mut self) -> Result<()> {
+        let change = self
+            .history
+            .pop()
+            .ok_or_else(|| make_error!(Error::Layer::NothingToUndo))?;
+
+        match change {
+            LayerChange::ObjectAdded(object_name) => {
+                self.objects_info.remove(&object_name);
+            }
+            LayerChange::ObjectRemoved(object, coord) | LayerChange::ObjectEdited(object, coord) => {
+                self.objects_info.remove(object.name());
+                self.objects_info.insert(object, coord);
+            }
+            LayerChange::ObjectOverridden(object_name, Some(object_override)) => {
+                self.overrides.insert(object_name, object_override);
+            }
+            LayerChange::ObjectOverridden(object_name, None) => {
+                self.overrides.remove(&object_name);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the override of the inherited object, creates an empty one if needed.
+    pub fn override_object(&mut self, object_name: ObjectName) -> &mut ObjectOverride {
+        let previous = self.overrides.get(&object_name).cloned();
+        self.history
+            .push(LayerChange::ObjectOverridden(object_name.clone(), previous));
+
+        self.overrides.entry(object_name).or_default()
+    }
+
+    pub fn overrides(&self) -> &HashMap<ObjectName, ObjectOverride> {
+        &self.overrides
+    }
+
+    /// Returns the issues of the objects' parameters.
+    /// Overlaps are checked by the engine since they depend on the inherited objects.
+    pub fn check_parameters(&self) -> Vec<LayerIssue> {
+        let mut issues = vec![];
+
+        let mut objects = self.objects_info.iter().collect::<Vec<_>>();
+        objects.sort_by(|(lhs, _), (rhs, _)| lhs.name().cmp(rhs.name()));
+
+        for (object, coord) in objects {
+            let name = object.name();
+
+            if !(object.radius() > 0.0) {
+                issues.push(LayerIssue::InvalidRadius(name.clone(), object.radius()));
+            }
+
+            if !(object.mass() > 0.0) {
+                issues.push(LayerIssue::InvalidMass(name.clone(), object.mass()));
+            }
+
+            if object.compute_step() <= chrono::Duration::zero() {
+                issues.push(LayerIssue::InvalidComputeStep(name.clone(), object.compute_step()));
+            }
+
+            if !is_finite(coord.location()) {
+                issues.push(LayerIssue::InvalidVector(name.clone(), "location"));
+            }
+
+            if !is_finite(coord.velocity()) {
+                issues.push(LayerIssue::InvalidVector(name.clone(), "velocity"));
+            }
+        }
+
+        let mut overrides = self.overrides.iter().collect::<Vec<_>>();
+        overrides.sort_by(|(lhs, _), (rhs, _)| lhs.cmp(rhs));
+
+        for (name, object_override) in overrides {
+            if let Some(mass) = object_override.mass.filter(|mass| !(*mass > 0.0)) {
+                issues.push(LayerIssue::InvalidMass(name.clone(), mass));
+            }
+
+            if !object_override.location.as_ref().map_or(true, is_finite) {
+                issues.push(LayerIssue::InvalidVector(name.clone(), "location"));
+            }
+
+            let is_velocity_finite = object_override.velocity.as_ref().map_or(true, is_finite)
+                && object_override.velocity_scale.map_or(true, Coord::is_finite);
+
+            if !is_velocity_finite {
+                issues.push(LayerIssue::InvalidVector(name.clone(), "velocity"));
+            }
+        }
+
+        issues
+    }
+
     pub fn iter_objects(&self) -> impl Iterator<Item = (&Object, &GenCoord)> {
         self.objects_info.iter()
     }
@@ -60,3 +269,7 @@ impl Layer {
         self.objects_info.into_iter()
     }
 }
+
+fn is_finite(vector: &Vector) -> bool {
+    vector.iter().all(|coord| coord.is_finite())
+}
@@ -2,6 +2,7 @@ use structopt::StructOpt;
 
 mod app;
 mod cli;
+mod config;
 mod engine;
 mod error;
 mod graphics;
@@ -9,6 +10,7 @@ mod layer;
 mod logger;
 mod message;
 mod object;
+mod report;
 mod storage;
 mod r#type;
 
@@ -23,7 +25,15 @@ pub use shared::Shared;
 fn main() {
     let options = app::Options::from_args();
 
-    let mut app = app::App::new(options.log_filter);
+    let result = options
+        .config()
+        .and_then(|config| match &options.command {
+            Some(app::Command::Migrate(command)) => command.run(&config),
+            None => app::App::new(options.log_filter, &config)?.run(options.history_file.clone()),
+        });
 
-    app.run(options.history_file).unwrap();
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
 }
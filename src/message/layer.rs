@@ -1,6 +1,6 @@
 use crate::{
     cli, messages,
-    r#type::{Color, Distance, Mass, ObjectName, Vector},
+    r#type::{Color, Coord, Distance, Mass, ObjectName, Vector},
 };
 
 messages! {
@@ -34,4 +34,79 @@ messages! {
         #[structopt(short, long, default_value = "1s", parse(try_from_str = cli::parse_time))]
         pub step: chrono::Duration,
     }
+
+    #[cli(name = "modify-obj", about = "change inherited object's location/velocity from the layer's start")]
+    message ModifyObject {
+        /// Object's name.
+        #[structopt(short, long)]
+        pub name: ObjectName,
+
+        /// New object's location.
+        #[structopt(short, long, allow_hyphen_values = true, parse(try_from_str = cli::parse_vector))]
+        pub location: Option<Vector>,
+
+        /// New object's velocity.
+        #[structopt(short, long, allow_hyphen_values = true, parse(try_from_str = cli::parse_vector))]
+        pub velocity: Option<Vector>,
+
+        /// Object's velocity multiplier (e.g. 1.1 to make it 10% faster).
+        #[structopt(long, allow_hyphen_values = true)]
+        pub velocity_scale: Option<Coord>,
+    }
+
+    #[cli(name = "remove-obj", about = "remove inherited object from the layer's start")]
+    message RemoveObject {
+        /// Object's name.
+        #[structopt(short, long)]
+        pub name: ObjectName,
+    }
+
+    #[cli(name = "set-mass", about = "change inherited object's mass from the layer's start")]
+    message SetMass {
+        /// Object's name.
+        #[structopt(short, long)]
+        pub name: ObjectName,
+
+        /// New object's mass.
+        #[structopt(short, long)]
+        pub mass: Mass,
+    }
+
+    #[cli(name = "rm-obj", about = "remove added object from the layer")]
+    message RmObject {
+        /// Object's name.
+        #[structopt(short, long)]
+        pub name: ObjectName,
+    }
+
+    #[cli(name = "edit-obj", about = "edit added object of the layer")]
+    message EditObject {
+        /// Object's name.
+        #[structopt(short, long)]
+        pub name: ObjectName,
+
+        /// New object's location.
+        #[structopt(short, long, allow_hyphen_values = true, parse(try_from_str = cli::parse_vector))]
+        pub location: Option<Vector>,
+
+        /// New object's velocity
+        #[structopt(short, long, allow_hyphen_values = true, parse(try_from_str = cli::parse_vector))]
+        pub velocity: Option<Vector>,
+
+        /// New object's color.
+        #[structopt(short, long, parse(try_from_str = cli::parse_color))]
+        pub color: Option<Color>,
+
+        /// New object's radius.
+        #[structopt(short, long)]
+        pub radius: Option<Distance>,
+
+        /// New object's mass.
+        #[structopt(short, long)]
+        pub mass: Option<Mass>,
+
+        /// New compute step
+        #[structopt(short, long, parse(try_from_str = cli::parse_time))]
+        pub step: Option<chrono::Duration>,
+    }
 }
@@ -1,10 +1,11 @@
 use {
     super::{
         cli,
-        r#type::{self, LayerName, ObjectName, SessionName},
+        r#type::{self, BookmarkName, BreakpointId, Coord, Distance, LayerName, ObjectName, SessionName, Vector},
     },
     log::LevelFilter,
     std::path::PathBuf,
+    structopt::StructOpt,
 };
 
 #[macro_use]
@@ -101,7 +102,45 @@ messages! {
     message NewLayer {
         /// New layer's name.
         #[structopt(short, long)]
-        pub name: LayerName
+        pub name: LayerName,
+
+        /// Layer's start time (the current virtual time by default).
+        #[structopt(long, parse(try_from_str = cli::parse_time))]
+        pub at: Option<chrono::Duration>
+    }
+
+    #[cli(name = "merge-layer", about = "merge layer into its parent (can't be undone, forgets the undo history)")]
+    message MergeLayer {
+        /// Layer's name to merge.
+        #[structopt(short, long)]
+        pub name: LayerName,
+
+        /// Only show what would be changed.
+        #[structopt(long)]
+        pub dry_run: bool
+    }
+
+    #[cli(name = "copy-layer", about = "copy layer into the current or another session (not journaled, remove the copy to undo)")]
+    message CopyLayer {
+        /// Layer's name to copy.
+        #[structopt(short, long)]
+        pub name: LayerName,
+
+        /// Target session's name (the current session by default).
+        #[structopt(long)]
+        pub to_session: Option<SessionName>,
+
+        /// Parent layer in the target session (the main layer by default).
+        #[structopt(short, long)]
+        pub parent: Option<LayerName>,
+
+        /// Copy the layer's descendants too.
+        #[structopt(short, long)]
+        pub recursive: bool,
+
+        /// Copy only the initial locations of the objects.
+        #[structopt(long)]
+        pub drop_computed: bool
     }
 
     #[cli(name = "rm-layer", about = "remove layer")]
@@ -129,7 +168,15 @@ messages! {
     message CurrentLayer {}
 
     #[cli(name = "list-layers", about = "list layers in the current session")]
-    message ListLayers {}
+    message ListLayers {
+        /// Output format
+        #[structopt(long, default_value = "tree", possible_values = &["tree", "dot", "json"])]
+        pub format: String,
+
+        /// Write the layers into the file instead of the terminal
+        #[structopt(short, long, parse(from_os_str))]
+        pub out: Option<PathBuf>
+    }
 
     #[cli(name = "select-layer", about = "select new active layer")]
     message SelectLayer {
@@ -139,11 +186,35 @@ messages! {
     }
 
     #[cli(name = "submit", about = "submit edition")]
-    message Submit {}
+    message Submit {
+        /// Submit the layer even if it is invalid
+        #[structopt(long)]
+        pub force: bool
+    }
 
     #[cli(name = "cancel", about = "cancel edition")]
     message Cancel {}
 
+    #[cli(name = "undo", about = "undo the last change of the layer being composed (or the last rm-layer/rename-layer/delete-session)")]
+    message Undo {}
+
+    #[cli(name = "redo", about = "redo the last undone layer/session operation")]
+    message Redo {}
+
+    #[cli(name = "purge-journal", about = "forget the undo history and delete the kept layers and sessions")]
+    message PurgeJournal {
+        /// Purge the journals of all sessions
+        #[structopt(long)]
+        pub all: bool
+    }
+
+    #[cli(name = "compact", about = "remove the stored locations between keyframes, they are recomputed when needed")]
+    message Compact {
+        /// Time between the kept locations
+        #[structopt(short, long, default_value = "1min", parse(try_from_str = cli::parse_time))]
+        pub keyframe_step: chrono::Duration
+    }
+
     #[cli(name = "list-sessions", about = "list all sessions")]
     message ListSessions {}
 
@@ -168,7 +239,12 @@ messages! {
     message LoadSession {
         /// Session's name.
         #[structopt(short, long)]
-        pub name: SessionName
+        pub name: SessionName,
+
+        /// Watch the session without locking it, e.g. while another user works in it.
+        /// Nothing is written into the session.
+        #[structopt(long)]
+        pub read_only: bool
     }
 
     #[cli(name = "rename-session", about = "rename session")]
@@ -189,6 +265,44 @@ messages! {
         pub name: SessionName
     }
 
+    #[cli(name = "clone-session", about = "copy session with all its layers into a new session (not journaled, delete the copy to undo)")]
+    message CloneSession {
+        /// Session's name (the current session by default).
+        #[structopt(short, long)]
+        pub name: Option<SessionName>,
+
+        /// New session's name.
+        #[structopt(long = "as")]
+        pub new_name: SessionName
+    }
+
+    #[cli(name = "export-session", about = "export session into a file")]
+    message ExportSession {
+        /// Session's name (the current session by default).
+        #[structopt(short, long)]
+        pub name: Option<SessionName>,
+
+        /// Output file.
+        #[structopt(short, long, parse(from_os_str))]
+        pub out: PathBuf,
+
+        /// Export the computed locations too (only the initial ones by default).
+        #[structopt(long)]
+        pub with_computed: bool
+    }
+
+    #[cli(name = "import-session", about = "import session from a file")]
+    message ImportSession {
+        /// Session file.
+        #[structopt(short, long, parse(from_os_str))]
+        pub file: PathBuf,
+
+        /// New session's name (the exported session's name by default,
+        /// required if the exported session is unnamed).
+        #[structopt(short, long)]
+        pub name: Option<SessionName>
+    }
+
     #[cli(name = "rename-obj", about = "rename object on the scene")]
     message RenameObject {
         /// Old object's name.
@@ -201,7 +315,11 @@ messages! {
     }
 
     #[cli(name = "list-objects", about = "list all objects in the current session (or in the new layer)")]
-    message ListObjects {}
+    message ListObjects {
+        /// Group the objects by the layers which introduced them
+        #[structopt(long)]
+        pub by_layer: bool
+    }
 
     #[cli(name = "object-info", about = "print object's info")]
     message ObjectInfo {
@@ -210,6 +328,82 @@ messages! {
         pub name: ObjectName
     }
 
+    #[cli(name = "state", about = "print object's location and velocity at the given time")]
+    message State {
+        /// Object's name
+        #[structopt(short, long)]
+        pub name: ObjectName,
+
+        /// Virtual time (the current one by default)
+        #[structopt(short, long, parse(try_from_str = cli::parse_time))]
+        pub time: Option<chrono::Duration>,
+
+        /// Print the state as JSON
+        #[structopt(long)]
+        pub json: bool
+    }
+
+    #[cli(name = "closest-approach", about = "find the minimal distance between objects in a time range")]
+    message ClosestApproach {
+        /// The first object's name
+        #[structopt(short = "a", long, required_unless = "all")]
+        pub lhs: Option<ObjectName>,
+
+        /// The second object's name
+        #[structopt(short = "b", long, required_unless = "all")]
+        pub rhs: Option<ObjectName>,
+
+        /// Start of the time range
+        #[structopt(short, long, default_value = "0s", parse(try_from_str = cli::parse_time))]
+        pub from: chrono::Duration,
+
+        /// End of the time range
+        #[structopt(short, long, parse(try_from_str = cli::parse_time))]
+        pub to: chrono::Duration,
+
+        /// Report all pairs of objects closer than the threshold
+        #[structopt(long, requires = "threshold", conflicts_with_all = &["lhs", "rhs"])]
+        pub all: bool,
+
+        /// Surface separation threshold (used with --all)
+        #[structopt(long)]
+        pub threshold: Option<Distance>
+    }
+
+    #[cli(name = "layer-diff", about = "compare objects' tracks of two layers")]
+    message LayerDiff {
+        /// The base layer's name
+        #[structopt(short = "a", long)]
+        pub lhs: LayerName,
+
+        /// The compared layer's name
+        #[structopt(short = "b", long)]
+        pub rhs: LayerName,
+
+        /// Start of the time range
+        #[structopt(short, long, default_value = "0s", parse(try_from_str = cli::parse_time))]
+        pub from: chrono::Duration,
+
+        /// End of the time range
+        #[structopt(short, long, parse(try_from_str = cli::parse_time))]
+        pub to: chrono::Duration,
+
+        /// Sampling step
+        #[structopt(short, long, default_value = "1s", parse(try_from_str = cli::parse_time))]
+        pub step: chrono::Duration,
+
+        /// Location difference at which the tracks are considered separated
+        #[structopt(short, long, default_value = "0.001")]
+        pub eps: Distance,
+
+        /// Show the compared layer's objects as ghost spheres
+        #[structopt(short, long)]
+        pub ghost: bool
+    }
+
+    #[cli(name = "hide-ghost", about = "hide the ghost objects of the compared layer")]
+    message HideGhost {}
+
     #[cli(name = "names", about = "show/hide scene's actors' names visualization")]
     message Names {}
 
@@ -226,7 +420,99 @@ messages! {
     #[cli(name = "stats", about = "show/hide statistics rendering")]
     message Stats {}
 
+    #[cli(name = "status", about = "show the context computation progress")]
+    message Status {}
+
+    #[cli(name = "break", about = "add/list/remove conditional breakpoints")]
+    message Break {
+        #[structopt(subcommand)]
+        pub command: BreakCommand
+    }
+
+    #[cli(name = "mark", about = "add/list/remove named virtual time bookmarks")]
+    message Mark {
+        #[structopt(subcommand)]
+        pub command: MarkCommand
+    }
+
     submessages {
         Layer(layer::Message)
     }
 }
+
+#[derive(Debug, StructOpt)]
+pub enum MarkCommand {
+    /// Add a new bookmark into the active layer.
+    Add {
+        /// Bookmark's name.
+        #[structopt(short, long)]
+        name: BookmarkName,
+
+        /// Virtual time (the current one by default).
+        #[structopt(short, long, parse(try_from_str = cli::parse_time))]
+        time: Option<chrono::Duration>,
+    },
+
+    /// Set the virtual time to the bookmark.
+    Goto {
+        /// Bookmark's name.
+        name: BookmarkName,
+    },
+
+    /// List the bookmarks of the session's layers.
+    List,
+
+    /// Remove a bookmark.
+    Rm {
+        /// Bookmark's name.
+        name: BookmarkName,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum BreakCommand {
+    /// Add a new breakpoint.
+    Add {
+        /// Break on a collision (of the object if the name is specified).
+        #[structopt(long, conflicts_with_all = &["closer-than", "region-min", "faster-than"])]
+        collision: bool,
+
+        /// Object's name.
+        #[structopt(short, long)]
+        name: Option<ObjectName>,
+
+        /// Second object's name (used with `--closer-than`).
+        #[structopt(short, long)]
+        other: Option<ObjectName>,
+
+        /// Break when the objects are closer than the distance.
+        #[structopt(long, requires_all = &["name", "other"], conflicts_with_all = &["region-min", "faster-than"])]
+        closer_than: Option<Distance>,
+
+        /// Region's minimal corner. Break when the object leaves the region.
+        #[structopt(long, allow_hyphen_values = true, parse(try_from_str = cli::parse_vector), requires_all = &["name", "region-max"], conflicts_with = "faster-than")]
+        region_min: Option<Vector>,
+
+        /// Region's maximal corner.
+        #[structopt(long, allow_hyphen_values = true, parse(try_from_str = cli::parse_vector), requires = "region-min")]
+        region_max: Option<Vector>,
+
+        /// Break when the speed is above the value (of any object if the name is not specified).
+        #[structopt(long)]
+        faster_than: Option<Coord>,
+    },
+
+    /// List all breakpoints.
+    List,
+
+    /// Remove a breakpoint.
+    Rm {
+        /// Breakpoint's id.
+        #[structopt(short, long, required_unless = "all")]
+        id: Option<BreakpointId>,
+
+        /// Remove all breakpoints.
+        #[structopt(long, conflicts_with = "id")]
+        all: bool,
+    },
+}
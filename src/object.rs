@@ -72,6 +72,22 @@ impl Object {
     pub fn compute_step(&self) -> chrono::Duration {
         self.compute_step
     }
+
+    pub fn set_radius(&mut self, radius: Distance) {
+        self.radius = radius;
+    }
+
+    pub fn set_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    pub fn set_mass(&mut self, mass: Mass) {
+        self.mass = mass;
+    }
+
+    pub fn set_compute_step(&mut self, compute_step: chrono::Duration) {
+        self.compute_step = compute_step;
+    }
 }
 
 impl Default for Object {
@@ -157,6 +173,14 @@ impl GenCoord {
     pub fn velocity(&self) -> &Vector {
         &self.velocity
     }
+
+    pub fn set_location(&mut self, location: Vector) {
+        self.location = location;
+    }
+
+    pub fn set_velocity(&mut self, velocity: Vector) {
+        self.velocity = velocity;
+    }
 }
 
 pub struct ObjectGenCoord(pub ObjectId, pub GenCoord);
@@ -0,0 +1,154 @@
+use {
+    crate::r#type::{IntoStorageDuration, LayerId, LayerInfo, SessionName, TimeFormat},
+    ptree::{item::StringItem, TreeBuilder},
+    std::collections::HashMap,
+};
+
+pub fn layers_tree(session_name: &SessionName, layers: &[LayerInfo]) -> StringItem {
+    let tree_title = format!("layers of the session \"{}\"", session_name);
+    let mut builder = TreeBuilder::new(tree_title);
+
+    let children = layers_children(layers);
+    for root in children.get(&None).into_iter().flatten() {
+        layers_tree_helper(&mut builder, &children, root);
+    }
+
+    builder.build()
+}
+
+fn layers_tree_helper(
+    builder: &mut TreeBuilder,
+    children: &HashMap<Option<LayerId>, Vec<&LayerInfo>>,
+    layer: &LayerInfo,
+) {
+    let layer_info = format!(
+        "{}{}: {}",
+        layer_status(layer),
+        layer.name,
+        TimeFormat::VirtualTimeShort(layer.start_time)
+    );
+
+    builder.begin_child(layer_info);
+
+    for child in children.get(&Some(layer.id)).into_iter().flatten() {
+        layers_tree_helper(builder, children, child);
+    }
+
+    builder.end_child();
+}
+
+/// Graphviz DOT representation of the layers tree.
+pub fn layers_dot(session_name: &SessionName, layers: &[LayerInfo]) -> String {
+    let mut dot = String::new();
+
+    dot.push_str("digraph layers {\n");
+    dot.push_str(&format!(
+        "    label=\"layers of the session \\\"{}\\\"\";\n",
+        escape_dot(session_name)
+    ));
+    dot.push_str("    node [shape=box];\n\n");
+
+    for layer in layers {
+        let last_computed_time = match layer.last_computed_time {
+            Some(time) => TimeFormat::VirtualTimeShort(time).to_string(),
+            None => "-".into(),
+        };
+
+        let mut attributes = format!(
+            "label=\"{}{}\\nstart: {}\\nobjects: {}\\ncomputed up to: {}\"",
+            layer_status(layer),
+            escape_dot(&layer.name),
+            TimeFormat::VirtualTimeShort(layer.start_time),
+            layer.objects_count,
+            last_computed_time
+        );
+
+        if layer.is_active {
+            attributes.push_str(", style=filled, fillcolor=lightgray");
+        }
+
+        if layer.is_current {
+            attributes.push_str(", penwidth=2");
+        }
+
+        dot.push_str(&format!("    layer_{} [{}];\n", layer.id, attributes));
+    }
+
+    dot.push('\n');
+
+    for layer in layers {
+        if let Some(parent_id) = layer.parent_id {
+            dot.push_str(&format!("    layer_{} -> layer_{};\n", parent_id, layer.id));
+        }
+    }
+
+    dot.push_str("}\n");
+
+    dot
+}
+
+pub fn layers_json(session_name: &SessionName, layers: &[LayerInfo]) -> serde_json::Value {
+    let json_layers = layers
+        .iter()
+        .map(|layer| {
+            serde_json::json!({
+                "id": layer.id,
+                "parent_id": layer.parent_id,
+                "name": layer.name,
+                "start_time_ms": layer.start_time.into_storage_duration(),
+                "start_time": TimeFormat::VirtualTimeShort(layer.start_time).to_string(),
+                "objects_count": layer.objects_count,
+                "last_computed_time_ms": layer.last_computed_time
+                    .map(|time| time.into_storage_duration()),
+                "is_active": layer.is_active,
+                "is_current": layer.is_current,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let edges = layers
+        .iter()
+        .filter_map(|layer| {
+            layer.parent_id.map(|parent_id| {
+                serde_json::json!({
+                    "parent": parent_id,
+                    "child": layer.id,
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+
+    serde_json::json!({
+        "session": session_name,
+        "layers": json_layers,
+        "edges": edges,
+    })
+}
+
+/// Groups the layers by their parents, children are ordered by the start time.
+fn layers_children(layers: &[LayerInfo]) -> HashMap<Option<LayerId>, Vec<&LayerInfo>> {
+    let mut children: HashMap<_, Vec<_>> = HashMap::new();
+
+    for layer in layers {
+        children.entry(layer.parent_id).or_default().push(layer);
+    }
+
+    for layers in children.values_mut() {
+        layers.sort_by_key(|layer| (layer.start_time, layer.id));
+    }
+
+    children
+}
+
+fn layer_status(layer: &LayerInfo) -> &'static str {
+    match (layer.is_active, layer.is_current) {
+        (true, true) => "[active/current] ",
+        (true, false) => "[active] ",
+        (false, true) => "[current] ",
+        (false, false) => "",
+    }
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
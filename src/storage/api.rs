@@ -0,0 +1,251 @@
+use crate::{
+    engine::context::{
+        db_util::{LocationInfo, MassOverrideInfo, ObjectInfo},
+        TimeRange,
+    },
+    object::{self, GenCoord},
+    r#type::{
+        BookmarkInfo, BookmarkName, JournalOperation, LayerCopyInfo, LayerId, LayerInfo,
+        LayerMergeInfo, LayerName, Mass, ObjectId, ObjectName, SessionId, SessionInfo, SessionName,
+        Vector,
+    },
+    storage::dump::SessionDump,
+    Result,
+};
+
+pub trait SessionApi {
+    /// Creates a new locked session with its main layer.
+    fn new(&mut self, name: Option<SessionName>) -> Result<(SessionId, LayerId)>;
+
+    fn update_access_time(&mut self, id: SessionId) -> Result<()>;
+
+    fn unlock(&mut self, id: SessionId) -> Result<()>;
+
+    fn save(&mut self, id: SessionId, name: &str) -> Result<()>;
+
+    /// Locks the session and returns its id with the main layer id.
+    fn load(&mut self, name: &str) -> Result<(SessionId, LayerId)>;
+
+    /// Returns the session id with the main layer id without locking the session.
+    /// The session may be locked by another application.
+    fn attach(&mut self, name: &str) -> Result<(SessionId, LayerId)>;
+
+    /// Returns the id of the session if it is not locked by another application.
+    fn get_unlocked_id(&mut self, name: &str, current_id: SessionId) -> Result<SessionId>;
+
+    fn rename(&mut self, old_name: &str, new_name: &str) -> Result<()>;
+
+    fn get_list(&mut self) -> Result<Vec<SessionInfo>>;
+
+    fn get_name(&mut self, id: SessionId) -> Result<SessionName>;
+
+    /// Collects the session's contents. Only the initial location of each object
+    /// in each layer is taken unless `with_computed` is set.
+    fn dump(&mut self, id: SessionId, with_computed: bool) -> Result<SessionDump>;
+}
+
+pub trait LayerApi {
+    fn get_name(&mut self, layer_id: LayerId) -> Result<LayerName>;
+
+    fn get_start_time(&mut self, layer_id: LayerId) -> Result<chrono::Duration>;
+
+    fn rename_layer(&mut self, layer_id: LayerId, new_layer_name: &LayerName) -> Result<()>;
+
+    fn get_layer_id(&mut self, session_id: SessionId, layer_name: &LayerName) -> Result<LayerId>;
+
+    fn get_main_layer(&mut self, session_id: SessionId) -> Result<LayerId>;
+
+    /// Returns the layer's children ordered by the start time.
+    fn get_layer_children(
+        &mut self,
+        session_id: SessionId,
+        layer_id: LayerId,
+    ) -> Result<Vec<LayerId>>;
+
+    /// Returns all layers of the session ordered by id (parents go before their children).
+    fn get_layers_info(&mut self, session_id: SessionId) -> Result<Vec<LayerInfo>>;
+
+    /// Returns the deepest active layer's ancestor which contains the virtual time.
+    fn get_current_layer_id(
+        &mut self,
+        active_layer_id: LayerId,
+        vtime: chrono::Duration,
+    ) -> Result<LayerId>;
+
+    /// Adds a new layer as a child of the active layer's ancestor current at the start time.
+    fn add_layer(
+        &mut self,
+        session_id: SessionId,
+        active_layer_id: LayerId,
+        new_layer_name: &LayerName,
+        new_layer_start_time: chrono::Duration,
+    ) -> Result<LayerId>;
+
+    /// Returns the layer itself and all of its ancestors.
+    fn layer_ancestors(&mut self, layer_id: LayerId) -> Result<Vec<LayerId>>;
+
+    fn get_parent_id(&mut self, layer_id: LayerId) -> Result<Option<LayerId>>;
+
+    /// Describes what `merge_layer` would change.
+    fn get_merge_info(&mut self, layer_id: LayerId) -> Result<LayerMergeInfo>;
+
+    /// Moves the layer's tracks, objects, overrides, bookmarks and children into its parent layer.
+    ///
+    /// The parent's locations replaced by the layer's tracks are removed,
+    /// the parent's tracks of the objects without locations in the layer are cut at its start.
+    /// The overrides keep applying from the layer's start time.
+    fn merge_layer(&mut self, layer_id: LayerId) -> Result<()>;
+
+    fn get_session_id(&mut self, layer_id: LayerId) -> Result<SessionId>;
+
+    /// Copies the layer (and its descendants if `with_descendants` is set)
+    /// into the session as a child of `dst_parent_id`.
+    ///
+    /// Objects are copied with new ids and names de-duplicated against the target session.
+    /// Only the initial locations are copied unless `with_computed` is set.
+    fn copy_layer(
+        &mut self,
+        src_layer_id: LayerId,
+        dst_session_id: SessionId,
+        dst_parent_id: LayerId,
+        with_descendants: bool,
+        with_computed: bool,
+    ) -> Result<LayerCopyInfo>;
+}
+
+pub trait ObjectApi {
+    fn add(
+        &mut self,
+        session_id: SessionId,
+        layer_id: LayerId,
+        object: object::Object,
+    ) -> Result<ObjectId>;
+
+    /// Overrides the inherited object's parameters inside the layer.
+    /// `None` keeps the previous override value.
+    ///
+    /// The override applies from `start_time`, the layer's start time by default.
+    /// The layers merged into their parent keep the start time of their overrides.
+    fn set_override(
+        &mut self,
+        layer_id: LayerId,
+        object_id: ObjectId,
+        start_time: Option<chrono::Duration>,
+        mass: Option<Mass>,
+        removed_time: Option<chrono::Duration>,
+    ) -> Result<()>;
+
+    /// Returns (layer, object) pairs of the objects visible from the active layer
+    /// overridden anywhere in the subtrees of the objects' layers.
+    fn get_overridden(&mut self, active_layer_id: LayerId) -> Result<Vec<(LayerId, ObjectId)>>;
+
+    fn is_object_exists(&mut self, session_id: SessionId, object_name: &ObjectName)
+        -> Result<bool>;
+
+    fn get_max_object_default_name_num(&mut self, session_id: SessionId) -> Result<i64>;
+
+    /// Returns the objects visible from the active layer except the known ones.
+    /// The removal time is resolved with the layers' overrides.
+    fn get_objects_delta(
+        &mut self,
+        active_layer_id: LayerId,
+        known_objects_ids: &[ObjectId],
+    ) -> Result<Vec<ObjectInfo>>;
+
+    /// Returns the mass overrides visible from the active layer
+    /// ordered by the object and the time they are applied from.
+    fn get_mass_overrides(&mut self, active_layer_id: LayerId) -> Result<Vec<MassOverrideInfo>>;
+}
+
+pub trait LocationApi {
+    fn add(&mut self, object_id: ObjectId, layer_id: LayerId, coord: GenCoord) -> Result<()>;
+
+    /// Stores the computed locations.
+    fn add_locations(&mut self, locations: &[LocationInfo]) -> Result<()>;
+
+    /// Returns the latest time up to which all the active layer's objects are computed.
+    fn get_min_valid_start_time(
+        &mut self,
+        layer_id: LayerId,
+        requested_time: chrono::Duration,
+    ) -> Result<chrono::Duration>;
+
+    /// Returns the latest time not after the requested one around which the stored tracks
+    /// of the active layer's objects are continuous. A track has gaps after the compaction.
+    /// Only the active layer's own locations are checked, the ancestors' gaps
+    /// aren't recomputed from a descendant layer.
+    fn get_keyframe_time(
+        &mut self,
+        layer_id: LayerId,
+        requested_time: chrono::Duration,
+    ) -> Result<chrono::Duration>;
+
+    /// Removes the session's computed locations except the initial ones, the collision points
+    /// and the latest location in each `keyframe_step` interval.
+    /// Returns the number of the removed locations.
+    fn compact(&mut self, session_id: SessionId, keyframe_step: chrono::Duration) -> Result<i64>;
+
+    /// Returns the locations visible from the active layer in the time range
+    /// (extended by the objects' compute steps) ordered by object and time.
+    fn get_range_locations(
+        &mut self,
+        layer_id: LayerId,
+        time_range: &TimeRange,
+        step_coeff: i16,
+    ) -> Result<Vec<LocationInfo>>;
+
+    /// Returns the object's stored locations around the requested time ordered by time.
+    ///
+    /// Each location is paired with the velocity after a collision if it is a collision point.
+    fn get_object_locations_around(
+        &mut self,
+        layer_id: LayerId,
+        object_id: ObjectId,
+        requested_time: chrono::Duration,
+    ) -> Result<Vec<(GenCoord, Option<Vector>)>>;
+}
+
+pub trait BookmarkApi {
+    fn add(
+        &mut self,
+        session_id: SessionId,
+        layer_id: LayerId,
+        name: &BookmarkName,
+        time: chrono::Duration,
+    ) -> Result<()>;
+
+    fn remove(&mut self, session_id: SessionId, name: &BookmarkName) -> Result<()>;
+
+    /// Returns the bookmarks of all the session's layers ordered by time.
+    /// The names are unique inside the session, so the list is the same from any layer.
+    fn get_list(&mut self, session_id: SessionId) -> Result<Vec<BookmarkInfo>>;
+}
+
+/// Only the layer removal, the layer renaming and the session deletion are journaled.
+/// The copies of layers and sessions aren't, they are undone by removing the copies.
+/// A merge of layers can't be undone and the session's journal is purged by it.
+pub trait JournalApi {
+    /// Removes the layer with its descendants keeping the deleted rows in the tombstones.
+    fn remove_layer(&mut self, session_id: SessionId, layer_id: LayerId) -> Result<()>;
+
+    fn rename_layer(
+        &mut self,
+        session_id: SessionId,
+        layer_id: LayerId,
+        new_layer_name: &LayerName,
+    ) -> Result<()>;
+
+    /// Deletes the session keeping the deleted rows in the tombstones.
+    /// The operation is recorded into the journal of the `session_id` session.
+    fn delete_session(&mut self, session_id: SessionId, name: &SessionName) -> Result<()>;
+
+    /// Rolls back the last operation of the session.
+    fn undo(&mut self, session_id: SessionId) -> Result<Option<JournalOperation>>;
+
+    /// Repeats the earliest undone operation of the session.
+    fn redo(&mut self, session_id: SessionId) -> Result<Option<JournalOperation>>;
+
+    /// Forgets the operations of the session (of all sessions if `None`)
+    /// and deletes the tombstones.
+    fn purge(&mut self, session_id: Option<SessionId>) -> Result<()>;
+}
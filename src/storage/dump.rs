@@ -0,0 +1,249 @@
+use {
+    super::StorageTransaction,
+    crate::{
+        engine::context::db_util::LocationInfo,
+        graphics, make_error, object,
+        r#type::{
+            BookmarkName, Coord, Distance, IntoRustDuration, LayerId, LayerName, Mass, ObjectId,
+            ObjectName, PackedColor, RawTime, SessionId, SessionName,
+        },
+        Result,
+    },
+    serde::{Deserialize, Serialize},
+    std::collections::HashMap,
+};
+
+/// The version of the session file format.
+/// Files of newer versions are refused, older ones must stay readable.
+pub const SESSION_DUMP_VERSION: u32 = 1;
+
+/// The portable representation of a session.
+///
+/// The ids are valid only inside the dump, they are reassigned on import.
+/// The times are in milliseconds.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionDump {
+    pub format_version: u32,
+
+    /// The current session may be unnamed, the new name is required on import then.
+    pub session_name: Option<SessionName>,
+
+    /// Ordered by id, so parents go before their children. The main layer has no parent.
+    pub layers: Vec<LayerDump>,
+    pub objects: Vec<ObjectDump>,
+    pub overrides: Vec<OverrideDump>,
+    pub bookmarks: Vec<BookmarkDump>,
+
+    /// Only the initial locations unless the computed ones were exported too.
+    pub locations: Vec<LocationDump>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LayerDump {
+    pub id: LayerId,
+    pub parent_id: Option<LayerId>,
+    pub name: LayerName,
+    pub start_time: RawTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ObjectDump {
+    pub id: ObjectId,
+    pub layer_id: LayerId,
+    pub name: ObjectName,
+    pub radius: Distance,
+    pub color: PackedColor,
+    pub mass: Mass,
+    pub compute_step: RawTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OverrideDump {
+    pub layer_id: LayerId,
+    pub object_id: ObjectId,
+
+    /// Missing in the older files, the layer's start time is used then.
+    #[serde(default)]
+    pub start_time: Option<RawTime>,
+    pub mass: Option<Mass>,
+    pub removed_time: Option<RawTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BookmarkDump {
+    pub layer_id: LayerId,
+    pub name: BookmarkName,
+    pub t: RawTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LocationDump {
+    pub object_id: ObjectId,
+    pub layer_id: LayerId,
+    pub t: RawTime,
+    pub x: Coord,
+    pub y: Coord,
+    pub z: Coord,
+    pub vx: Coord,
+    pub vy: Coord,
+    pub vz: Coord,
+
+    pub vcx: Option<Coord>, // vx after collision
+    pub vcy: Option<Coord>, // vy after collision
+    pub vcz: Option<Coord>, // vz after collision
+}
+
+impl SessionDump {
+    pub fn new(session_name: Option<SessionName>) -> Self {
+        Self {
+            format_version: SESSION_DUMP_VERSION,
+            session_name,
+            layers: vec![],
+            objects: vec![],
+            overrides: vec![],
+            bookmarks: vec![],
+            locations: vec![],
+        }
+    }
+
+    pub fn read(reader: impl std::io::Read) -> Result<Self> {
+        let dump: Self = serde_json::from_reader(reader)
+            .map_err(|err| make_error![Error::SessionFile(err.to_string())])?;
+
+        if dump.format_version > SESSION_DUMP_VERSION {
+            return Err(make_error![Error::SessionFile(format!(
+                "the file format version {} is newer than the supported one ({})",
+                dump.format_version, SESSION_DUMP_VERSION
+            ))]);
+        }
+
+        Ok(dump)
+    }
+
+    pub fn write(&self, writer: impl std::io::Write) -> Result<()> {
+        serde_json::to_writer_pretty(writer, self)
+            .map_err(|err| make_error![Error::SessionFile(err.to_string())])
+    }
+
+    /// Creates a new unlocked session with the dump's contents.
+    ///
+    /// Only the generic storage APIs are used, so a session exported
+    /// from one storage can be imported into any other.
+    pub fn import(&self, t: &mut dyn StorageTransaction, name: &SessionName) -> Result<SessionId> {
+        let (session_id, main_layer_id) = t.session().new(Some(name.clone()))?;
+        t.session().unlock(session_id)?;
+
+        let mut layers_map = HashMap::new();
+        for layer in self.layers.iter() {
+            let layer_id = match layer.parent_id {
+                None if layers_map.is_empty() => {
+                    if layer.name != t.layer().get_name(main_layer_id)? {
+                        t.layer().rename_layer(main_layer_id, &layer.name)?;
+                    }
+
+                    main_layer_id
+                }
+                None => return Err(Self::corrupted("the session has several main layers")),
+                Some(parent_id) => {
+                    let parent_id = *layers_map
+                        .get(&parent_id)
+                        .ok_or_else(|| Self::corrupted("a layer goes before its parent"))?;
+
+                    // The layer starts not earlier than its parent,
+                    // so the parent is the current layer at the start time.
+                    t.layer().add_layer(
+                        session_id,
+                        parent_id,
+                        &layer.name,
+                        layer.start_time.into_rust_duration(),
+                    )?
+                }
+            };
+
+            layers_map.insert(layer.id, layer_id);
+        }
+
+        let map_layer = |id: LayerId| {
+            layers_map
+                .get(&id)
+                .copied()
+                .ok_or_else(|| Self::corrupted("unknown layer id"))
+        };
+
+        let mut objects_map = HashMap::new();
+        for object in self.objects.iter() {
+            let layer_id = map_layer(object.layer_id)?;
+
+            let new_object_id = t.object().add(
+                session_id,
+                layer_id,
+                object::Object::new(
+                    layer_id,
+                    object.name.clone(),
+                    object.radius,
+                    graphics::unpack_color(&object.color),
+                    object.mass,
+                    object.compute_step.into_rust_duration(),
+                ),
+            )?;
+
+            objects_map.insert(object.id, new_object_id);
+        }
+
+        let map_object = |id: ObjectId| {
+            objects_map
+                .get(&id)
+                .copied()
+                .ok_or_else(|| Self::corrupted("unknown object id"))
+        };
+
+        for over in self.overrides.iter() {
+            t.object().set_override(
+                map_layer(over.layer_id)?,
+                map_object(over.object_id)?,
+                over.start_time.map(|time| time.into_rust_duration()),
+                over.mass,
+                over.removed_time.map(|time| time.into_rust_duration()),
+            )?;
+        }
+
+        for bookmark in self.bookmarks.iter() {
+            t.bookmark().add(
+                session_id,
+                map_layer(bookmark.layer_id)?,
+                &bookmark.name,
+                bookmark.t.into_rust_duration(),
+            )?;
+        }
+
+        let locations = self
+            .locations
+            .iter()
+            .map(|location| {
+                Ok(LocationInfo {
+                    layer_id: map_layer(location.layer_id)?,
+                    location_id: Default::default(),
+                    object_id: map_object(location.object_id)?,
+                    t: location.t.into_rust_duration(),
+                    x: location.x,
+                    y: location.y,
+                    z: location.z,
+                    vx: location.vx,
+                    vy: location.vy,
+                    vz: location.vz,
+                    vcx: location.vcx,
+                    vcy: location.vcy,
+                    vcz: location.vcz,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        t.location().add_locations(&locations)?;
+
+        Ok(session_id)
+    }
+
+    fn corrupted(desc: &str) -> crate::Error {
+        make_error![Error::SessionFile(format!("the file is corrupted: {}", desc))]
+    }
+}
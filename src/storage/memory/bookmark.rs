@@ -0,0 +1,75 @@
+use super::{BookmarkRow, MemoryTransaction};
+use crate::{
+    r#type::{BookmarkInfo, BookmarkName, IntoRustDuration, IntoStorageDuration, LayerId, SessionId},
+    storage::BookmarkApi,
+    Result,
+};
+
+impl BookmarkApi for MemoryTransaction {
+    fn add(
+        &mut self,
+        session_id: SessionId,
+        layer_id: LayerId,
+        name: &BookmarkName,
+        time: chrono::Duration,
+    ) -> Result<()> {
+        let is_taken = self
+            .db
+            .bookmarks
+            .iter()
+            .any(|(_, bookmark)| bookmark.session_id == session_id && &bookmark.name == name);
+
+        if is_taken {
+            return Err(Self::backend_error(format!("bookmark `{}` already exists", name)));
+        }
+
+        self.db.last_bookmark_id += 1;
+        self.db.bookmarks.insert(
+            self.db.last_bookmark_id,
+            BookmarkRow {
+                session_id,
+                layer_id,
+                name: name.clone(),
+                t: time.into_storage_duration(),
+            },
+        );
+
+        Ok(())
+    }
+
+    fn remove(&mut self, session_id: SessionId, name: &BookmarkName) -> Result<()> {
+        let removed = self
+            .db
+            .bookmarks
+            .remove_where(|_, bookmark| bookmark.session_id == session_id && &bookmark.name == name);
+
+        if removed.is_empty() {
+            return Err(Self::backend_error(format!("bookmark `{}` not found", name)));
+        }
+
+        Ok(())
+    }
+
+    fn get_list(&mut self, session_id: SessionId) -> Result<Vec<BookmarkInfo>> {
+        let mut bookmarks = self
+            .db
+            .bookmarks
+            .iter()
+            .filter(|(_, bookmark)| bookmark.session_id == session_id)
+            .map(|(_, bookmark)| {
+                Ok((
+                    bookmark.t,
+                    BookmarkInfo {
+                        name: bookmark.name.clone(),
+                        layer_name: self.layer_row(bookmark.layer_id)?.name.clone(),
+                        time: bookmark.t.into_rust_duration(),
+                    },
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        bookmarks.sort_by(|(lhs_t, lhs), (rhs_t, rhs)| (lhs_t, &lhs.name).cmp(&(rhs_t, &rhs.name)));
+
+        Ok(bookmarks.into_iter().map(|(_, info)| info).collect())
+    }
+}
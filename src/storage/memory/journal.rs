@@ -0,0 +1,185 @@
+use super::{JournalId, JournalRow, MemoryTransaction};
+use crate::{
+    r#type::{JournalOperation, LayerId, LayerName, SessionId, SessionName},
+    storage::{JournalApi, LayerApi},
+    Result,
+};
+
+impl MemoryTransaction {
+    fn journal_record(&mut self, session_id: SessionId, operation: JournalOperation) -> JournalId {
+        // A new operation makes the undone ones unreachable for redo.
+        self.remove_journal_entries(|entry| entry.session_id == session_id && entry.is_undone);
+
+        self.db.last_journal_id += 1;
+        self.db.journal.insert(
+            self.db.last_journal_id,
+            JournalRow {
+                session_id,
+                operation,
+                is_undone: false,
+            },
+        );
+
+        self.db.last_journal_id
+    }
+
+    fn remove_journal_entries(&mut self, predicate: impl Fn(&JournalRow) -> bool) {
+        let removed = self.db.journal.remove_where(|_, entry| predicate(entry));
+
+        for (journal_id, _) in removed {
+            self.db.tombs.remove(&journal_id);
+        }
+    }
+
+    fn bury_layer(&mut self, journal_id: JournalId, layer_id: LayerId) {
+        let subtree: Vec<_> = self
+            .layer_subtree(layer_id)
+            .into_iter()
+            .map(|(layer_id, _)| layer_id)
+            .collect();
+
+        let tomb = self.remove_layers(&subtree);
+        self.db.tombs.insert(journal_id, tomb);
+    }
+
+    fn bury_session(&mut self, journal_id: JournalId, session_id: SessionId) -> Result<()> {
+        match self.db.sessions.get(&session_id) {
+            Some(session) if !session.is_locked => {}
+            session => {
+                let name = session
+                    .and_then(|session| session.name.clone())
+                    .unwrap_or("/unnamed/".into());
+
+                return Err(Self::locked_or_not_exists(&name));
+            }
+        }
+
+        let tomb = self.remove_session(session_id);
+        self.db.tombs.insert(journal_id, tomb);
+
+        Ok(())
+    }
+
+    fn unbury(&mut self, journal_id: JournalId) {
+        if let Some(tomb) = self.db.tombs.remove(&journal_id) {
+            self.restore(tomb);
+        }
+    }
+
+    fn journal_entry(
+        &self,
+        session_id: SessionId,
+        is_undone: bool,
+    ) -> Option<(JournalId, JournalOperation)> {
+        let mut entries = self
+            .db
+            .journal
+            .iter()
+            .filter(|(_, entry)| entry.session_id == session_id && entry.is_undone == is_undone)
+            .map(|(journal_id, entry)| (*journal_id, entry.operation.clone()));
+
+        // The latest done entry is undone first, the earliest undone one is redone first.
+        if is_undone {
+            entries.next()
+        } else {
+            entries.last()
+        }
+    }
+
+    fn set_undone(&mut self, journal_id: JournalId, is_undone: bool) {
+        self.db
+            .journal
+            .update(&journal_id, |entry| entry.is_undone = is_undone);
+    }
+}
+
+impl JournalApi for MemoryTransaction {
+    fn remove_layer(&mut self, session_id: SessionId, layer_id: LayerId) -> Result<()> {
+        let layer_name = LayerApi::get_name(self, layer_id)?;
+        let journal_id =
+            self.journal_record(session_id, JournalOperation::RemoveLayer(layer_id, layer_name));
+
+        self.bury_layer(journal_id, layer_id);
+
+        Ok(())
+    }
+
+    fn rename_layer(
+        &mut self,
+        session_id: SessionId,
+        layer_id: LayerId,
+        new_layer_name: &LayerName,
+    ) -> Result<()> {
+        let layer_name = LayerApi::get_name(self, layer_id)?;
+
+        LayerApi::rename_layer(self, layer_id, new_layer_name)?;
+
+        self.journal_record(
+            session_id,
+            JournalOperation::RenameLayer(layer_id, layer_name, new_layer_name.clone()),
+        );
+
+        Ok(())
+    }
+
+    fn delete_session(&mut self, session_id: SessionId, name: &SessionName) -> Result<()> {
+        let deleted_session_id = match self.session_id_by_name(name) {
+            Some((deleted_session_id, session)) if !session.is_locked => deleted_session_id,
+            _ => return Err(Self::locked_or_not_exists(name)),
+        };
+
+        let journal_id = self.journal_record(
+            session_id,
+            JournalOperation::DeleteSession(deleted_session_id, name.clone()),
+        );
+
+        self.bury_session(journal_id, deleted_session_id)
+    }
+
+    fn undo(&mut self, session_id: SessionId) -> Result<Option<JournalOperation>> {
+        let (journal_id, operation) = match self.journal_entry(session_id, false) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        match &operation {
+            JournalOperation::RenameLayer(layer_id, old_name, _) => {
+                LayerApi::rename_layer(self, *layer_id, old_name)?
+            }
+            _ => self.unbury(journal_id),
+        }
+
+        self.set_undone(journal_id, true);
+
+        Ok(Some(operation))
+    }
+
+    fn redo(&mut self, session_id: SessionId) -> Result<Option<JournalOperation>> {
+        let (journal_id, operation) = match self.journal_entry(session_id, true) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        match &operation {
+            JournalOperation::RemoveLayer(layer_id, _) => self.bury_layer(journal_id, *layer_id),
+            JournalOperation::RenameLayer(layer_id, _, new_name) => {
+                LayerApi::rename_layer(self, *layer_id, new_name)?
+            }
+            JournalOperation::DeleteSession(deleted_session_id, _) => {
+                self.bury_session(journal_id, *deleted_session_id)?
+            }
+        }
+
+        self.set_undone(journal_id, false);
+
+        Ok(Some(operation))
+    }
+
+    fn purge(&mut self, session_id: Option<SessionId>) -> Result<()> {
+        self.remove_journal_entries(|entry| {
+            session_id.map_or(true, |session_id| entry.session_id == session_id)
+        });
+
+        Ok(())
+    }
+}
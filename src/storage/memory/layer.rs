@@ -0,0 +1,614 @@
+use super::{LayerRow, LocationRow, MemoryTransaction, ObjectRow, OverrideRow};
+use crate::{
+    make_error,
+    r#type::{
+        IntoRustDuration, IntoStorageDuration, LayerCopyInfo, LayerId, LayerInfo, LayerMergeInfo,
+        LayerName, LocationId, ObjectId, ObjectName, RawTime, SessionId,
+    },
+    storage::LayerApi,
+    Result,
+};
+use std::collections::{BTreeSet, HashMap};
+
+impl MemoryTransaction {
+    fn check_layer_name_is_free(&self, session_id: SessionId, name: &LayerName) -> Result<()> {
+        let is_taken = self
+            .db
+            .layers
+            .iter()
+            .any(|(_, layer)| layer.session_id == session_id && &layer.name == name);
+
+        if is_taken {
+            Err(make_error![Error::Layer::LayerAlreadyExists(name.clone())])
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The first time of the object's locations in each layer.
+    fn branch_times(&self, layer_id: LayerId) -> HashMap<ObjectId, RawTime> {
+        let mut branch_times = HashMap::new();
+
+        for (_, location) in self.db.locations.iter() {
+            if location.layer_id == layer_id {
+                let branch_time = branch_times.entry(location.object_id).or_insert(location.t);
+                *branch_time = location.t.min(*branch_time);
+            }
+        }
+
+        branch_times
+    }
+
+    /// The parent's locations replaced by the merge of the layer:
+    /// the ones from the child's first location of the object, and for the objects
+    /// without locations in the child, the ones after the child's start time.
+    /// The first location of an object is always kept, the track is recomputed from it.
+    fn replaced_locations(
+        &self,
+        layer_id: LayerId,
+        parent_id: LayerId,
+    ) -> Result<Vec<(LocationId, RawTime)>> {
+        let start_time = self.layer_row(layer_id)?.start_time;
+        let branch_times = self.branch_times(layer_id);
+        let first_times = self.branch_times(parent_id);
+
+        let replaced_locations = self
+            .db
+            .locations
+            .iter()
+            .filter(|(_, location)| location.layer_id == parent_id)
+            .filter(|(_, location)| {
+                let object_id = &location.object_id;
+
+                match branch_times.get(object_id) {
+                    Some(branch_time) => location.t >= *branch_time,
+                    None => location.t > start_time.max(first_times[object_id]),
+                }
+            })
+            .map(|(location_id, location)| (*location_id, location.t))
+            .collect();
+
+        Ok(replaced_locations)
+    }
+
+    fn layer_names(&self, mut predicate: impl FnMut(&LayerRow) -> bool) -> Vec<LayerName> {
+        let mut names: Vec<_> = self
+            .db
+            .layers
+            .iter()
+            .filter(|(_, layer)| predicate(layer))
+            .map(|(_, layer)| layer.name.clone())
+            .collect();
+
+        names.sort();
+        names
+    }
+
+    fn object_names(&self, object_ids: impl Iterator<Item = ObjectId>) -> Vec<ObjectName> {
+        let mut names: Vec<_> = object_ids
+            .filter_map(|object_id| self.db.objects.get(&object_id))
+            .map(|object| object.name.clone())
+            .collect();
+
+        names.sort();
+        names
+    }
+
+    fn copy_layer_row(
+        &mut self,
+        src_layer_id: LayerId,
+        dst_session_id: SessionId,
+        dst_parent_id: LayerId,
+    ) -> Result<LayerId> {
+        let src_layer = self.layer_row(src_layer_id)?;
+
+        let name = Self::unique_name(&src_layer.name, |name| {
+            self.db
+                .layers
+                .iter()
+                .any(|(_, layer)| layer.session_id == dst_session_id && layer.name == name)
+        });
+
+        let new_layer = LayerRow {
+            session_id: dst_session_id,
+            parent_id: Some(dst_parent_id),
+            name,
+            start_time: src_layer.start_time,
+        };
+
+        self.db.last_layer_id += 1;
+        let new_layer_id = self.db.last_layer_id;
+
+        self.db.layers.insert(new_layer_id, new_layer);
+
+        Ok(new_layer_id)
+    }
+
+    fn copy_object(
+        &mut self,
+        src_object_id: ObjectId,
+        dst_session_id: SessionId,
+        dst_layer_id: LayerId,
+    ) -> Result<(ObjectId, ObjectName)> {
+        let src_object = self.object_row(src_object_id)?;
+
+        let name = Self::unique_name(&src_object.name, |name| {
+            self.db
+                .objects
+                .iter()
+                .any(|(_, object)| object.session_id == dst_session_id && object.name == name)
+        });
+
+        let new_object = ObjectRow {
+            session_id: dst_session_id,
+            layer_id: dst_layer_id,
+            name: name.clone(),
+            ..src_object.clone()
+        };
+
+        self.db.last_object_id += 1;
+        let new_object_id = self.db.last_object_id;
+
+        self.db.objects.insert(new_object_id, new_object);
+
+        Ok((new_object_id, name))
+    }
+
+    /// Without the computed locations only the initial one is copied.
+    fn copy_locations(
+        &mut self,
+        src_object_id: ObjectId,
+        src_layer_id: LayerId,
+        dst_object_id: ObjectId,
+        dst_layer_id: LayerId,
+        with_computed: bool,
+    ) -> i64 {
+        let mut locations: Vec<LocationRow> = self
+            .db
+            .locations
+            .iter()
+            .filter(|(_, location)| {
+                location.object_id == src_object_id && location.layer_id == src_layer_id
+            })
+            .map(|(_, location)| location.clone())
+            .collect();
+
+        // The locations are ordered by id, so the stable sort keeps the order of the same times.
+        locations.sort_by_key(|location| location.t);
+
+        if !with_computed {
+            locations.truncate(1);
+        }
+
+        let copied_count = locations.len() as i64;
+
+        for location in locations {
+            let new_location = if with_computed {
+                location
+            } else {
+                LocationRow {
+                    vcx: None,
+                    vcy: None,
+                    vcz: None,
+                    ..location
+                }
+            };
+
+            self.db.last_location_id += 1;
+            self.db.locations.insert(
+                self.db.last_location_id,
+                LocationRow {
+                    object_id: dst_object_id,
+                    layer_id: dst_layer_id,
+                    ..new_location
+                },
+            );
+        }
+
+        copied_count
+    }
+}
+
+impl LayerApi for MemoryTransaction {
+    fn get_name(&mut self, layer_id: LayerId) -> Result<LayerName> {
+        Ok(self.layer_row(layer_id)?.name.clone())
+    }
+
+    fn get_start_time(&mut self, layer_id: LayerId) -> Result<chrono::Duration> {
+        Ok(self.layer_row(layer_id)?.start_time.into_rust_duration())
+    }
+
+    fn rename_layer(&mut self, layer_id: LayerId, new_layer_name: &LayerName) -> Result<()> {
+        let session_id = self.layer_row(layer_id)?.session_id;
+        self.check_layer_name_is_free(session_id, new_layer_name)?;
+
+        self.db
+            .layers
+            .update(&layer_id, |layer| layer.name = new_layer_name.clone());
+
+        Ok(())
+    }
+
+    fn get_layer_id(&mut self, session_id: SessionId, layer_name: &LayerName) -> Result<LayerId> {
+        self.db
+            .layers
+            .iter()
+            .find(|(_, layer)| layer.session_id == session_id && &layer.name == layer_name)
+            .map(|(layer_id, _)| *layer_id)
+            .ok_or_else(|| make_error![Error::Layer::LayerNotFound(layer_name.clone())])
+    }
+
+    fn get_main_layer(&mut self, session_id: SessionId) -> Result<LayerId> {
+        self.main_layer_id(session_id)
+    }
+
+    fn get_layer_children(
+        &mut self,
+        _session_id: SessionId,
+        layer_id: LayerId,
+    ) -> Result<Vec<LayerId>> {
+        let mut children: Vec<_> = self
+            .db
+            .layers
+            .iter()
+            .filter(|(_, layer)| layer.parent_id == Some(layer_id))
+            .map(|(child_id, child)| (child.start_time, *child_id))
+            .collect();
+
+        children.sort();
+
+        Ok(children.into_iter().map(|(_, child_id)| child_id).collect())
+    }
+
+    fn get_layers_info(&mut self, session_id: SessionId) -> Result<Vec<LayerInfo>> {
+        let infos = self
+            .db
+            .layers
+            .iter()
+            .filter(|(_, layer)| layer.session_id == session_id)
+            .map(|(layer_id, layer)| {
+                let objects_count = self
+                    .db
+                    .objects
+                    .iter()
+                    .filter(|(_, object)| object.layer_id == *layer_id)
+                    .count() as i64;
+
+                let last_computed_time = self
+                    .db
+                    .locations
+                    .iter()
+                    .filter(|(_, location)| location.layer_id == *layer_id)
+                    .map(|(_, location)| location.t)
+                    .max();
+
+                LayerInfo {
+                    id: *layer_id,
+                    parent_id: layer.parent_id,
+                    name: layer.name.clone(),
+                    start_time: layer.start_time.into_rust_duration(),
+                    objects_count,
+                    last_computed_time: last_computed_time.map(|t| t.into_rust_duration()),
+                    is_active: false,
+                    is_current: false,
+                }
+            })
+            .collect();
+
+        Ok(infos)
+    }
+
+    fn get_current_layer_id(
+        &mut self,
+        active_layer_id: LayerId,
+        vtime: chrono::Duration,
+    ) -> Result<LayerId> {
+        let vtime = vtime.into_storage_duration();
+        let ancestors = self
+            .ancestors(active_layer_id)
+            .into_iter()
+            .map(|layer_id| Ok((layer_id, self.layer_row(layer_id)?.start_time)))
+            .collect::<Result<Vec<_>>>()?;
+
+        // Each ancestor lasts until its child (the next one) starts.
+        // The deepest layer wins at the boundary.
+        let next_start_times = ancestors
+            .iter()
+            .skip(1)
+            .map(|&(_, start_time)| Some(start_time))
+            .chain(std::iter::once(None));
+
+        ancestors
+            .iter()
+            .zip(next_start_times)
+            .filter(|((_, start_time), next_start_time)| {
+                *start_time <= vtime && next_start_time.map_or(true, |next| vtime <= next)
+            })
+            .map(|((layer_id, _), _)| *layer_id)
+            .last()
+            .ok_or_else(|| {
+                Self::backend_error(format!(
+                    "no layer of the layer #{} contains the time {}",
+                    active_layer_id, vtime
+                ))
+            })
+    }
+
+    fn add_layer(
+        &mut self,
+        session_id: SessionId,
+        active_layer_id: LayerId,
+        new_layer_name: &LayerName,
+        new_layer_start_time: chrono::Duration,
+    ) -> Result<LayerId> {
+        self.check_layer_name_is_free(session_id, new_layer_name)?;
+
+        let parent_id = self.get_current_layer_id(active_layer_id, new_layer_start_time)?;
+
+        self.db.last_layer_id += 1;
+        let new_layer_id = self.db.last_layer_id;
+
+        self.db.layers.insert(
+            new_layer_id,
+            LayerRow {
+                session_id,
+                parent_id: Some(parent_id),
+                name: new_layer_name.clone(),
+                start_time: new_layer_start_time.into_storage_duration(),
+            },
+        );
+
+        Ok(new_layer_id)
+    }
+
+    fn layer_ancestors(&mut self, layer_id: LayerId) -> Result<Vec<LayerId>> {
+        Ok(self.ancestors(layer_id))
+    }
+
+    fn get_parent_id(&mut self, layer_id: LayerId) -> Result<Option<LayerId>> {
+        Ok(self.layer_row(layer_id)?.parent_id)
+    }
+
+    fn get_merge_info(&mut self, layer_id: LayerId) -> Result<LayerMergeInfo> {
+        let layer = self.layer_row(layer_id)?;
+
+        let parent_id = layer
+            .parent_id
+            .ok_or_else(|| make_error![Error::Layer::NoParentLayer(layer.name.clone())])?;
+
+        let start_time = layer.start_time.into_rust_duration();
+        let parent_name = self.layer_row(parent_id)?.name.clone();
+
+        let replaced = self.replaced_locations(layer_id, parent_id)?;
+        let replaced_locations = replaced.len() as i64;
+        let first_replaced_time = replaced.iter().map(|(_, t)| *t).min();
+
+        let moved_locations = self
+            .db
+            .locations
+            .iter()
+            .filter(|(_, location)| location.layer_id == layer_id)
+            .count() as i64;
+
+        let moved_objects = self.object_names(
+            self.db
+                .objects
+                .iter()
+                .filter(|(_, object)| object.layer_id == layer_id)
+                .map(|(object_id, _)| *object_id),
+        );
+
+        let overridden_objects = self.object_names(
+            self.db
+                .overrides
+                .iter()
+                .filter(|((override_layer_id, _, _), _)| *override_layer_id == layer_id)
+                .map(|((_, object_id, _), _)| *object_id)
+                .collect::<BTreeSet<_>>()
+                .into_iter(),
+        );
+
+        let reparented_layers = self.layer_names(|layer| layer.parent_id == Some(layer_id));
+
+        let conflicting_layers = match first_replaced_time {
+            Some(first_replaced_time) => self.layer_names(|sibling| {
+                sibling.parent_id == Some(parent_id)
+                    && sibling.name != layer.name
+                    && sibling.start_time > first_replaced_time
+            }),
+            None => vec![],
+        };
+
+        Ok(LayerMergeInfo {
+            parent_name,
+            start_time,
+            replaced_locations,
+            moved_locations,
+            moved_objects,
+            overridden_objects,
+            reparented_layers,
+            conflicting_layers,
+        })
+    }
+
+    fn merge_layer(&mut self, layer_id: LayerId) -> Result<()> {
+        let layer = self.layer_row(layer_id)?;
+
+        let parent_id = match layer.parent_id {
+            Some(parent_id) => parent_id,
+            None => return Err(make_error![Error::Layer::NoParentLayer(layer.name.clone())]),
+        };
+
+        for (location_id, _) in self.replaced_locations(layer_id, parent_id)? {
+            self.db.locations.remove(&location_id);
+        }
+
+        self.db.locations.update_where(
+            |_, location| location.layer_id == layer_id,
+            |location| location.layer_id = parent_id,
+        );
+
+        self.db.objects.update_where(
+            |_, object| object.layer_id == layer_id,
+            |object| object.layer_id = parent_id,
+        );
+
+        self.db.bookmarks.update_where(
+            |_, bookmark| bookmark.layer_id == layer_id,
+            |bookmark| bookmark.layer_id = parent_id,
+        );
+
+        // The overrides keep their start time, they still apply from the child's start.
+        let overrides = self
+            .db
+            .overrides
+            .remove_where(|(override_layer_id, _, _), _| *override_layer_id == layer_id);
+
+        for ((_, object_id, start_time), child_override) in overrides {
+            let key = (parent_id, object_id, start_time);
+
+            let merged_override = match self.db.overrides.get(&key) {
+                Some(parent_override) => OverrideRow {
+                    mass: child_override.mass.or(parent_override.mass),
+                    removed_time: match (child_override.removed_time, parent_override.removed_time) {
+                        (Some(child_time), Some(parent_time)) => Some(child_time.min(parent_time)),
+                        (child_time, parent_time) => child_time.or(parent_time),
+                    },
+                },
+                None => child_override,
+            };
+
+            self.db.overrides.insert(key, merged_override);
+        }
+
+        self.db.layers.update_where(
+            |_, layer| layer.parent_id == Some(layer_id),
+            |layer| layer.parent_id = Some(parent_id),
+        );
+
+        self.db.layers.remove(&layer_id);
+
+        Ok(())
+    }
+
+    fn get_session_id(&mut self, layer_id: LayerId) -> Result<SessionId> {
+        Ok(self.layer_row(layer_id)?.session_id)
+    }
+
+    fn copy_layer(
+        &mut self,
+        src_layer_id: LayerId,
+        dst_session_id: SessionId,
+        dst_parent_id: LayerId,
+        with_descendants: bool,
+        with_computed: bool,
+    ) -> Result<LayerCopyInfo> {
+        let is_same_session = self.get_session_id(src_layer_id)? == dst_session_id;
+
+        let subtree = if with_descendants {
+            self.layer_subtree(src_layer_id)
+        } else {
+            vec![(src_layer_id, None)]
+        };
+
+        let mut layers_map = HashMap::new();
+        let mut objects_map: HashMap<ObjectId, ObjectId> = HashMap::new();
+        let mut skipped_objects = BTreeSet::new();
+
+        let mut info = LayerCopyInfo {
+            layers: vec![],
+            renamed_objects: vec![],
+            copied_objects: 0,
+            copied_locations: 0,
+            skipped_objects: vec![],
+        };
+
+        // The subtree is ordered by id, so parents are always copied before their children.
+        for (src_id, src_parent_id) in subtree {
+            let parent_id = match src_parent_id {
+                Some(src_parent_id) => layers_map[&src_parent_id],
+                None => dst_parent_id,
+            };
+
+            let new_layer_id = self.copy_layer_row(src_id, dst_session_id, parent_id)?;
+
+            layers_map.insert(src_id, new_layer_id);
+            info.layers.push((self.get_name(src_id)?, self.get_name(new_layer_id)?));
+
+            let objects: Vec<(ObjectId, ObjectName)> = self
+                .db
+                .objects
+                .iter()
+                .filter(|(_, object)| object.layer_id == src_id)
+                .map(|(object_id, object)| (*object_id, object.name.clone()))
+                .collect();
+
+            for (object_id, object_name) in objects {
+                let (new_object_id, new_object_name) =
+                    self.copy_object(object_id, dst_session_id, new_layer_id)?;
+
+                if new_object_name != object_name {
+                    info.renamed_objects.push((object_name, new_object_name));
+                }
+
+                objects_map.insert(object_id, new_object_id);
+                info.copied_objects += 1;
+            }
+
+            // Tracks and overrides of both own and inherited objects.
+            let tracks: BTreeSet<ObjectId> = self
+                .db
+                .locations
+                .iter()
+                .filter(|(_, location)| location.layer_id == src_id)
+                .map(|(_, location)| location.object_id)
+                .chain(
+                    self.db
+                        .overrides
+                        .iter()
+                        .filter(|((layer_id, _, _), _)| *layer_id == src_id)
+                        .map(|((_, object_id, _), _)| *object_id),
+                )
+                .collect();
+
+            for object_id in tracks {
+                let dst_object_id = match objects_map.get(&object_id) {
+                    Some(dst_object_id) => *dst_object_id,
+                    None if is_same_session => object_id,
+                    None => {
+                        skipped_objects.insert(self.object_row(object_id)?.name.clone());
+                        continue;
+                    }
+                };
+
+                info.copied_locations += self.copy_locations(
+                    object_id,
+                    src_id,
+                    dst_object_id,
+                    new_layer_id,
+                    with_computed,
+                );
+
+                let src_overrides: Vec<_> = self
+                    .db
+                    .overrides
+                    .iter()
+                    .filter(|((layer_id, override_object_id, _), _)| {
+                        *layer_id == src_id && *override_object_id == object_id
+                    })
+                    .map(|((_, _, start_time), src_override)| (*start_time, src_override.clone()))
+                    .collect();
+
+                for (start_time, src_override) in src_overrides {
+                    self.db
+                        .overrides
+                        .insert((new_layer_id, dst_object_id, start_time), src_override);
+                }
+            }
+        }
+
+        info.skipped_objects = skipped_objects.into_iter().collect();
+
+        Ok(info)
+    }
+}
@@ -0,0 +1,351 @@
+use super::{LocationRow, MemoryTransaction};
+use crate::{
+    engine::context::{db_util::LocationInfo, TimeRange},
+    object::GenCoord,
+    r#type::{
+        IntoRustDuration, IntoStorageDuration, LayerId, LocationId, ObjectId, RawTime, SessionId,
+        Vector,
+    },
+    storage::LocationApi,
+    Result,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+impl MemoryTransaction {
+    fn insert_location(&mut self, location: LocationRow) {
+        self.db.last_location_id += 1;
+        self.db.locations.insert(self.db.last_location_id, location);
+    }
+
+    /// The in-memory version of the Postgres `range_locations` function.
+    ///
+    /// Each ancestor layer contributes the object's locations from the time the object
+    /// was first computed in the layer until it is first computed in the next one.
+    /// Only the latest location is kept for each time.
+    fn range_locations(
+        &self,
+        layer_id: LayerId,
+        start_time: RawTime,
+        stop_time: RawTime,
+        step_coeff: i16,
+    ) -> Vec<LocationInfo> {
+        let ancestors = self.ancestors(layer_id);
+
+        // The ancestors go from the main layer down to the layer.
+        let mut start_times = BTreeMap::new();
+        for (_, location) in self.db.locations.iter() {
+            if let Some(level) = ancestors.iter().position(|id| *id == location.layer_id) {
+                let min_t = start_times
+                    .entry((location.object_id, level))
+                    .or_insert(location.t);
+
+                *min_t = location.t.min(*min_t);
+            }
+        }
+
+        let mut layers_ranges = HashMap::new();
+        let mut start_times = start_times.into_iter().peekable();
+
+        while let Some(((object_id, level), min_t)) = start_times.next() {
+            let layer_id = ancestors[level];
+
+            let compute_step = match self.db.objects.get(&object_id) {
+                Some(object) => object.compute_step,
+                None => continue,
+            };
+
+            let (left_step, right_step) = if step_coeff == 0 {
+                (-compute_step, compute_step)
+            } else {
+                let step = compute_step * step_coeff as RawTime;
+                (step, step)
+            };
+
+            let next_min_t = match start_times.peek() {
+                Some(((next_object_id, _), next_min_t)) if *next_object_id == object_id => {
+                    Some(*next_min_t)
+                }
+                _ => None,
+            };
+
+            let layer_start_time = min_t.max(start_time + left_step);
+            let layer_stop_time = next_min_t
+                .unwrap_or(stop_time + right_step)
+                .min(stop_time + right_step);
+
+            layers_ranges.insert((object_id, layer_id), (layer_start_time, layer_stop_time));
+        }
+
+        let mut removed_times = HashMap::new();
+        let mut latest_locations: BTreeMap<(ObjectId, RawTime), (LocationId, &LocationRow)> =
+            BTreeMap::new();
+
+        // The locations are ordered by id, so the latest one overwrites the others.
+        for (location_id, location) in self.db.locations.iter() {
+            let (layer_start_time, layer_stop_time) =
+                match layers_ranges.get(&(location.object_id, location.layer_id)) {
+                    Some(range) => *range,
+                    None => continue,
+                };
+
+            let removed_time = *removed_times
+                .entry(location.object_id)
+                .or_insert_with(|| self.removed_time(&ancestors, location.object_id));
+
+            let is_in_range = layer_start_time <= location.t && location.t <= layer_stop_time;
+            let is_removed = removed_time.map_or(false, |removed_time| location.t > removed_time);
+
+            if is_in_range && !is_removed {
+                latest_locations.insert((location.object_id, location.t), (*location_id, location));
+            }
+        }
+
+        latest_locations
+            .into_iter()
+            .map(|(_, (location_id, location))| LocationInfo {
+                layer_id: LayerId::default(),
+                location_id,
+                object_id: location.object_id,
+                t: location.t.into_rust_duration(),
+                x: location.x,
+                y: location.y,
+                z: location.z,
+                vx: location.vx,
+                vy: location.vy,
+                vz: location.vz,
+                vcx: location.vcx,
+                vcy: location.vcy,
+                vcz: location.vcz,
+            })
+            .collect()
+    }
+}
+
+impl LocationApi for MemoryTransaction {
+    fn add(&mut self, object_id: ObjectId, layer_id: LayerId, coord: GenCoord) -> Result<()> {
+        let location = coord.location();
+        let velocity = coord.velocity();
+
+        self.insert_location(LocationRow {
+            object_id,
+            layer_id,
+            t: coord.time().into_storage_duration(),
+            x: location[0],
+            y: location[1],
+            z: location[2],
+            vx: velocity[0],
+            vy: velocity[1],
+            vz: velocity[2],
+            vcx: None,
+            vcy: None,
+            vcz: None,
+        });
+
+        Ok(())
+    }
+
+    fn add_locations(&mut self, locations: &[LocationInfo]) -> Result<()> {
+        for location_info in locations {
+            self.insert_location(LocationRow {
+                object_id: location_info.object_id,
+                layer_id: location_info.layer_id,
+                t: location_info.t.into_storage_duration(),
+                x: location_info.x,
+                y: location_info.y,
+                z: location_info.z,
+                vx: location_info.vx,
+                vy: location_info.vy,
+                vz: location_info.vz,
+                vcx: location_info.vcx,
+                vcy: location_info.vcy,
+                vcz: location_info.vcz,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn get_min_valid_start_time(
+        &mut self,
+        layer_id: LayerId,
+        requested_time: chrono::Duration,
+    ) -> Result<chrono::Duration> {
+        let ancestors = self.ancestors(layer_id);
+        let mut max_objects_times = HashMap::new();
+
+        for (_, location) in self.db.locations.iter() {
+            if location.layer_id != layer_id {
+                continue;
+            }
+
+            let is_visible = self
+                .db
+                .objects
+                .get(&location.object_id)
+                .map_or(false, |object| ancestors.contains(&object.layer_id));
+
+            if is_visible {
+                let max_t = max_objects_times
+                    .entry(location.object_id)
+                    .or_insert(location.t);
+
+                *max_t = location.t.max(*max_t);
+            }
+        }
+
+        let time = max_objects_times
+            .values()
+            .min()
+            .copied()
+            .unwrap_or_else(|| requested_time.into_storage_duration());
+
+        Ok(time.into_rust_duration())
+    }
+
+    fn get_keyframe_time(
+        &mut self,
+        layer_id: LayerId,
+        requested_time: chrono::Duration,
+    ) -> Result<chrono::Duration> {
+        let requested_time = requested_time.into_storage_duration();
+        let ancestors = self.ancestors(layer_id);
+
+        // object id -> (previous time, max time)
+        let mut objects_times: HashMap<ObjectId, (Option<RawTime>, RawTime)> = HashMap::new();
+
+        for (_, location) in self.db.locations.iter() {
+            if location.layer_id != layer_id {
+                continue;
+            }
+
+            let is_visible = self
+                .db
+                .objects
+                .get(&location.object_id)
+                .map_or(false, |object| ancestors.contains(&object.layer_id));
+
+            if is_visible {
+                let (prev_t, max_t) = objects_times
+                    .entry(location.object_id)
+                    .or_insert((None, location.t));
+
+                if location.t <= requested_time {
+                    *prev_t = Some(prev_t.map_or(location.t, |prev_t| prev_t.max(location.t)));
+                }
+
+                *max_t = location.t.max(*max_t);
+            }
+        }
+
+        // The object's track is continuous around the requested time
+        // unless the previous location is farther than the compute step.
+        // The ancestors' locations are skipped: their gaps are recomputed only in their own layer.
+        let time = objects_times
+            .into_iter()
+            .filter_map(|(object_id, (prev_t, max_t))| {
+                let prev_t = prev_t?;
+                let compute_step = self.db.objects.get(&object_id)?.compute_step;
+
+                if max_t > requested_time && requested_time - prev_t > compute_step {
+                    Some(prev_t)
+                } else {
+                    None
+                }
+            })
+            .min()
+            .unwrap_or(requested_time);
+
+        Ok(time.into_rust_duration())
+    }
+
+    fn compact(&mut self, session_id: SessionId, keyframe_step: chrono::Duration) -> Result<i64> {
+        let keyframe_step = keyframe_step.into_storage_duration();
+
+        let session_layers: HashSet<LayerId> = self
+            .db
+            .layers
+            .iter()
+            .filter(|(_, layer)| layer.session_id == session_id)
+            .map(|(layer_id, _)| *layer_id)
+            .collect();
+
+        // The initial locations, the collision points and the latest location
+        // of each keyframe interval are kept.
+        let mut initial_locations = HashMap::new();
+        let mut keyframes = HashMap::new();
+
+        for (location_id, location) in self.db.locations.iter() {
+            if !session_layers.contains(&location.layer_id) {
+                continue;
+            }
+
+            let candidate = (location.t, *location_id);
+
+            let initial = initial_locations
+                .entry((location.object_id, location.layer_id))
+                .or_insert(candidate);
+            *initial = candidate.min(*initial);
+
+            let keyframe = keyframes
+                .entry((location.object_id, location.layer_id, location.t / keyframe_step))
+                .or_insert(candidate);
+            *keyframe = candidate.max(*keyframe);
+        }
+
+        let kept_ids: HashSet<LocationId> = initial_locations
+            .values()
+            .chain(keyframes.values())
+            .map(|(_, location_id)| *location_id)
+            .collect();
+
+        let removed = self.db.locations.remove_where(|location_id, location| {
+            session_layers.contains(&location.layer_id)
+                && location.vcx.is_none()
+                && !kept_ids.contains(location_id)
+        });
+
+        Ok(removed.len() as i64)
+    }
+
+    fn get_range_locations(
+        &mut self,
+        layer_id: LayerId,
+        time_range: &TimeRange,
+        step_coeff: i16,
+    ) -> Result<Vec<LocationInfo>> {
+        Ok(self.range_locations(
+            layer_id,
+            time_range.start().into_storage_duration(),
+            time_range.end().into_storage_duration(),
+            step_coeff,
+        ))
+    }
+
+    fn get_object_locations_around(
+        &mut self,
+        layer_id: LayerId,
+        object_id: ObjectId,
+        requested_time: chrono::Duration,
+    ) -> Result<Vec<(GenCoord, Option<Vector>)>> {
+        let requested_time = requested_time.into_storage_duration();
+
+        let locations = self
+            .range_locations(layer_id, requested_time, requested_time, 0)
+            .into_iter()
+            .filter(|info| info.object_id == object_id)
+            .map(|info| {
+                let location = Vector::new(info.x, info.y, info.z);
+                let velocity = Vector::new(info.vx, info.vy, info.vz);
+
+                let collision_velocity = match (info.vcx, info.vcy, info.vcz) {
+                    (Some(vcx), Some(vcy), Some(vcz)) => Some(Vector::new(vcx, vcy, vcz)),
+                    _ => None,
+                };
+
+                (GenCoord::new(info.t, location, velocity), collision_velocity)
+            })
+            .collect();
+
+        Ok(locations)
+    }
+}
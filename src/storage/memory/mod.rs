@@ -0,0 +1,427 @@
+use {
+    super::{
+        BookmarkApi, IsolationLevel, JournalApi, LayerApi, LocationApi, ObjectApi, SessionApi,
+        StorageBackend, StorageTransaction,
+    },
+    crate::{
+        make_error,
+        r#type::{
+            BookmarkName, Coord, Distance, JournalOperation, LayerId, LayerName, LocationId, Mass,
+            ObjectId, ObjectName, PackedColor, RawTime, SessionId, SessionName,
+        },
+        Result,
+    },
+    std::{
+        collections::HashSet,
+        sync::{Arc, Condvar, Mutex},
+    },
+    table::Table,
+};
+
+mod bookmark;
+mod journal;
+mod layer;
+mod location;
+mod object;
+mod session;
+mod table;
+
+type BookmarkId = i32;
+type JournalId = i32;
+
+#[derive(Debug, Clone)]
+struct SessionRow {
+    name: Option<SessionName>,
+    last_access: RawTime,
+    is_locked: bool,
+}
+
+#[derive(Debug, Clone)]
+struct LayerRow {
+    session_id: SessionId,
+    parent_id: Option<LayerId>,
+    name: LayerName,
+    start_time: RawTime,
+}
+
+#[derive(Debug, Clone)]
+struct ObjectRow {
+    session_id: SessionId,
+    layer_id: LayerId,
+    name: ObjectName,
+    radius: Distance,
+    color: PackedColor,
+    mass: Mass,
+    compute_step: RawTime,
+}
+
+#[derive(Debug, Clone)]
+struct LocationRow {
+    object_id: ObjectId,
+    layer_id: LayerId,
+    t: RawTime,
+    x: Coord,
+    y: Coord,
+    z: Coord,
+    vx: Coord,
+    vy: Coord,
+    vz: Coord,
+
+    vcx: Option<Coord>, // vx after collision
+    vcy: Option<Coord>, // vy after collision
+    vcz: Option<Coord>, // vz after collision
+}
+
+#[derive(Debug, Clone)]
+struct OverrideRow {
+    mass: Option<Mass>,
+    removed_time: Option<RawTime>,
+}
+
+#[derive(Debug, Clone)]
+struct BookmarkRow {
+    session_id: SessionId,
+    layer_id: LayerId,
+    name: BookmarkName,
+    t: RawTime,
+}
+
+#[derive(Debug, Clone)]
+struct JournalRow {
+    session_id: SessionId,
+    operation: JournalOperation,
+    is_undone: bool,
+}
+
+/// The rows deleted by a journal operation, kept until the operation is purged.
+#[derive(Debug, Clone, Default)]
+struct Tomb {
+    sessions: Vec<(SessionId, SessionRow)>,
+    layers: Vec<(LayerId, LayerRow)>,
+    objects: Vec<(ObjectId, ObjectRow)>,
+    locations: Vec<(LocationId, LocationRow)>,
+    overrides: Vec<((LayerId, ObjectId, RawTime), OverrideRow)>,
+    bookmarks: Vec<(BookmarkId, BookmarkRow)>,
+}
+
+/// The whole in-memory database.
+///
+/// Ids are never reused, even if the transaction which took them is rolled back.
+#[derive(Debug, Default)]
+struct MemoryDb {
+    sessions: Table<SessionId, SessionRow>,
+    layers: Table<LayerId, LayerRow>,
+    objects: Table<ObjectId, ObjectRow>,
+    locations: Table<LocationId, LocationRow>,
+    /// Keyed by the layer, the object and the override's start time.
+    overrides: Table<(LayerId, ObjectId, RawTime), OverrideRow>,
+    bookmarks: Table<BookmarkId, BookmarkRow>,
+    journal: Table<JournalId, JournalRow>,
+    tombs: Table<JournalId, Tomb>,
+
+    last_session_id: SessionId,
+    last_layer_id: LayerId,
+    last_object_id: ObjectId,
+    last_location_id: LocationId,
+    last_bookmark_id: BookmarkId,
+    last_journal_id: JournalId,
+}
+
+macro_rules! for_each_table {
+    ($db:expr => $method:ident) => {
+        $db.sessions.$method();
+        $db.layers.$method();
+        $db.objects.$method();
+        $db.locations.$method();
+        $db.overrides.$method();
+        $db.bookmarks.$method();
+        $db.journal.$method();
+        $db.tombs.$method();
+    };
+}
+
+impl MemoryDb {
+    fn commit(&mut self) {
+        for_each_table![self => commit];
+    }
+
+    fn rollback(&mut self) {
+        for_each_table![self => rollback];
+    }
+}
+
+/// The database is taken by a transaction and returned back when it ends,
+/// so the transactions are serialized.
+struct DbSlot {
+    db: Mutex<Option<MemoryDb>>,
+    is_returned: Condvar,
+}
+
+pub struct MemoryBackend {
+    slot: Arc<DbSlot>,
+    session_max_hang_time: chrono::Duration,
+}
+
+impl MemoryBackend {
+    pub fn setup(session_max_hang_time: chrono::Duration) -> Self {
+        Self {
+            slot: Arc::new(DbSlot {
+                db: Mutex::new(Some(MemoryDb::default())),
+                is_returned: Condvar::new(),
+            }),
+            session_max_hang_time,
+        }
+    }
+}
+
+impl StorageBackend for MemoryBackend {
+    /// The transactions are always serializable, so the isolation level is ignored.
+    fn transaction(&self, _: IsolationLevel) -> Result<Box<dyn StorageTransaction>> {
+        let mut db = self
+            .slot
+            .db
+            .lock()
+            .map_err(|err| make_error![Error::Sync(err.to_string())])?;
+
+        let db = loop {
+            match db.take() {
+                Some(db) => break db,
+                None => {
+                    db = self
+                        .slot
+                        .is_returned
+                        .wait(db)
+                        .map_err(|err| make_error![Error::Sync(err.to_string())])?
+                }
+            }
+        };
+
+        Ok(Box::new(MemoryTransaction {
+            slot: self.slot.clone(),
+            db,
+            session_max_hang_time: self.session_max_hang_time,
+            is_committed: false,
+        }))
+    }
+}
+
+/// The transaction owns the database until it is committed or dropped.
+///
+/// The transaction implements all the storage APIs, see the submodules.
+pub struct MemoryTransaction {
+    slot: Arc<DbSlot>,
+    db: MemoryDb,
+    session_max_hang_time: chrono::Duration,
+    is_committed: bool,
+}
+
+impl MemoryTransaction {
+    fn now() -> RawTime {
+        chrono::Local::now().timestamp_millis()
+    }
+
+    /// Returns the layer itself and all of its ancestors
+    /// ordered from the main layer down to the layer.
+    fn ancestors(&self, layer_id: LayerId) -> Vec<LayerId> {
+        let mut ancestors = vec![];
+        let mut current_id = Some(layer_id);
+
+        while let Some(layer_id) = current_id {
+            ancestors.push(layer_id);
+            current_id = self.db.layers.get(&layer_id).and_then(|layer| layer.parent_id);
+        }
+
+        ancestors.reverse();
+        ancestors
+    }
+
+    /// Returns the layer with all of its descendants as (layer, parent) pairs ordered by id.
+    /// The parent of the layer itself is `None`.
+    fn layer_subtree(&self, layer_id: LayerId) -> Vec<(LayerId, Option<LayerId>)> {
+        let mut subtree = vec![(layer_id, None)];
+        let mut idx = 0;
+
+        while idx < subtree.len() {
+            let (parent_id, _) = subtree[idx];
+
+            subtree.extend(
+                self.db
+                    .layers
+                    .iter()
+                    .filter(|(_, layer)| layer.parent_id == Some(parent_id))
+                    .map(|(child_id, _)| (*child_id, Some(parent_id))),
+            );
+
+            idx += 1;
+        }
+
+        subtree.sort_by_key(|(layer_id, _)| *layer_id);
+        subtree
+    }
+
+    fn layer_row(&self, layer_id: LayerId) -> Result<&LayerRow> {
+        self.db
+            .layers
+            .get(&layer_id)
+            .ok_or_else(|| Self::backend_error(format!("layer #{} not found", layer_id)))
+    }
+
+    fn object_row(&self, object_id: ObjectId) -> Result<&ObjectRow> {
+        self.db
+            .objects
+            .get(&object_id)
+            .ok_or_else(|| Self::backend_error(format!("object #{} not found", object_id)))
+    }
+
+    /// Removes the layers with everything which depends on them.
+    /// Returns the removed rows.
+    fn remove_layers(&mut self, layer_ids: &[LayerId]) -> Tomb {
+        let db = &mut self.db;
+
+        let layers = db.layers.remove_where(|layer_id, _| layer_ids.contains(layer_id));
+        let objects = db
+            .objects
+            .remove_where(|_, object| layer_ids.contains(&object.layer_id));
+
+        let removed_objects: HashSet<_> = objects.iter().map(|(object_id, _)| *object_id).collect();
+        let is_removed_object = |object_id: &ObjectId| removed_objects.contains(object_id);
+
+        let locations = db.locations.remove_where(|_, location| {
+            layer_ids.contains(&location.layer_id) || is_removed_object(&location.object_id)
+        });
+
+        let overrides = db.overrides.remove_where(|(layer_id, object_id, _), _| {
+            layer_ids.contains(layer_id) || is_removed_object(object_id)
+        });
+
+        let bookmarks = db
+            .bookmarks
+            .remove_where(|_, bookmark| layer_ids.contains(&bookmark.layer_id));
+
+        Tomb {
+            sessions: vec![],
+            layers,
+            objects,
+            locations,
+            overrides,
+            bookmarks,
+        }
+    }
+
+    /// Removes the session with its layers and journal.
+    /// Returns the removed rows except the journal ones.
+    fn remove_session(&mut self, session_id: SessionId) -> Tomb {
+        let layer_ids: Vec<_> = self
+            .db
+            .layers
+            .iter()
+            .filter(|(_, layer)| layer.session_id == session_id)
+            .map(|(layer_id, _)| *layer_id)
+            .collect();
+
+        let mut tomb = self.remove_layers(&layer_ids);
+
+        let journal = self
+            .db
+            .journal
+            .remove_where(|_, entry| entry.session_id == session_id);
+
+        for (journal_id, _) in journal {
+            self.db.tombs.remove(&journal_id);
+        }
+
+        if let Some(session) = self.db.sessions.remove(&session_id) {
+            tomb.sessions.push((session_id, session));
+        }
+
+        tomb
+    }
+
+    /// Puts the removed rows back.
+    fn restore(&mut self, tomb: Tomb) {
+        let db = &mut self.db;
+
+        macro_rules! restore {
+            ($($table:ident),+) => {
+                $(
+                    for (id, row) in tomb.$table {
+                        db.$table.insert(id, row);
+                    }
+                )+
+            };
+        }
+
+        restore![sessions, layers, objects, locations, overrides, bookmarks];
+    }
+
+    /// The same name if it is free, otherwise the name with the first free `-N` suffix.
+    fn unique_name(base_name: &str, is_taken: impl Fn(&str) -> bool) -> String {
+        let mut new_name = base_name.to_string();
+        let mut suffix_num = 0;
+
+        while is_taken(&new_name) {
+            suffix_num += 1;
+
+            let suffix = format!("-{}", suffix_num);
+            let base_len = 50usize.saturating_sub(suffix.len());
+
+            new_name = base_name.chars().take(base_len).collect::<String>() + &suffix;
+        }
+
+        new_name
+    }
+
+    fn backend_error(desc: String) -> crate::Error {
+        make_error![Error::Storage::Backend(desc)]
+    }
+}
+
+impl StorageTransaction for MemoryTransaction {
+    fn session(&mut self) -> &mut dyn SessionApi {
+        self
+    }
+
+    fn layer(&mut self) -> &mut dyn LayerApi {
+        self
+    }
+
+    fn object(&mut self) -> &mut dyn ObjectApi {
+        self
+    }
+
+    fn location(&mut self) -> &mut dyn LocationApi {
+        self
+    }
+
+    fn bookmark(&mut self) -> &mut dyn BookmarkApi {
+        self
+    }
+
+    fn journal(&mut self) -> &mut dyn JournalApi {
+        self
+    }
+
+    fn commit(mut self: Box<Self>) -> Result<()> {
+        self.db.commit();
+        self.is_committed = true;
+
+        Ok(())
+    }
+}
+
+impl Drop for MemoryTransaction {
+    fn drop(&mut self) {
+        if !self.is_committed {
+            self.db.rollback();
+        }
+
+        // Even a poisoned slot has to get the database back.
+        let mut db = match self.slot.db.lock() {
+            Ok(db) => db,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        *db = Some(std::mem::take(&mut self.db));
+        self.slot.is_returned.notify_one();
+    }
+}
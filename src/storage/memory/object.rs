@@ -0,0 +1,218 @@
+use super::{MemoryTransaction, ObjectRow, OverrideRow};
+use crate::{
+    engine::context::db_util::{MassOverrideInfo, ObjectInfo},
+    graphics, make_error, object,
+    r#type::{
+        IntoRustDuration, IntoStorageDuration, LayerId, Mass, ObjectId, ObjectName, RawTime,
+        SessionId,
+    },
+    storage::ObjectApi,
+    Result,
+};
+
+impl MemoryTransaction {
+    /// The earliest removal time of the object among the ancestors.
+    pub(super) fn removed_time(
+        &self,
+        ancestors: &[LayerId],
+        object_id: ObjectId,
+    ) -> Option<RawTime> {
+        self.db
+            .overrides
+            .iter()
+            .filter(|((layer_id, override_object_id, _), _)| {
+                *override_object_id == object_id && ancestors.contains(layer_id)
+            })
+            .filter_map(|(_, object_override)| object_override.removed_time)
+            .min()
+    }
+
+    /// The objects of the layer itself and of all of its ancestors.
+    pub(super) fn visible_objects<'a>(
+        &'a self,
+        ancestors: &'a [LayerId],
+    ) -> impl Iterator<Item = (ObjectId, &'a ObjectRow)> + 'a {
+        self.db
+            .objects
+            .iter()
+            .filter(move |(_, object)| ancestors.contains(&object.layer_id))
+            .map(|(object_id, object)| (*object_id, object))
+    }
+}
+
+impl ObjectApi for MemoryTransaction {
+    fn add(
+        &mut self,
+        session_id: SessionId,
+        layer_id: LayerId,
+        object: object::Object,
+    ) -> Result<ObjectId> {
+        if self.is_object_exists(session_id, object.name())? {
+            return Err(make_error![Error::Layer::ObjectAlreadyExists(
+                object.name().clone()
+            )]);
+        }
+
+        self.db.last_object_id += 1;
+        let object_id = self.db.last_object_id;
+
+        self.db.objects.insert(
+            object_id,
+            ObjectRow {
+                session_id,
+                layer_id,
+                name: object.name().clone(),
+                radius: object.radius(),
+                color: graphics::pack_color(object.color()),
+                mass: object.mass(),
+                compute_step: object.compute_step().into_storage_duration(),
+            },
+        );
+
+        Ok(object_id)
+    }
+
+    fn set_override(
+        &mut self,
+        layer_id: LayerId,
+        object_id: ObjectId,
+        start_time: Option<chrono::Duration>,
+        mass: Option<Mass>,
+        removed_time: Option<chrono::Duration>,
+    ) -> Result<()> {
+        let start_time = match start_time {
+            Some(start_time) => start_time.into_storage_duration(),
+            None => self.layer_row(layer_id)?.start_time,
+        };
+        let removed_time = removed_time.map(|time| time.into_storage_duration());
+        let key = (layer_id, object_id, start_time);
+
+        let new_override = match self.db.overrides.get(&key) {
+            Some(old_override) => OverrideRow {
+                mass: mass.or(old_override.mass),
+                removed_time: removed_time.or(old_override.removed_time),
+            },
+            None => OverrideRow { mass, removed_time },
+        };
+
+        self.db.overrides.insert(key, new_override);
+
+        Ok(())
+    }
+
+    fn get_overridden(&mut self, active_layer_id: LayerId) -> Result<Vec<(LayerId, ObjectId)>> {
+        let ancestors = self.ancestors(active_layer_id);
+
+        // The objects are overridden in the descendants of the layers introducing them.
+        let mut overridden: Vec<_> = self
+            .db
+            .overrides
+            .iter()
+            .map(|((layer_id, object_id, _), _)| (*layer_id, *object_id))
+            .filter(|(layer_id, object_id)| match self.db.objects.get(object_id) {
+                Some(object) if ancestors.contains(&object.layer_id) => self
+                    .layer_subtree(object.layer_id)
+                    .iter()
+                    .any(|(subtree_layer_id, _)| subtree_layer_id == layer_id),
+                _ => false,
+            })
+            .collect();
+
+        // The merged layers may keep several overrides of the object.
+        overridden.dedup();
+
+        Ok(overridden)
+    }
+
+    fn is_object_exists(
+        &mut self,
+        session_id: SessionId,
+        object_name: &ObjectName,
+    ) -> Result<bool> {
+        let is_exists = self
+            .db
+            .objects
+            .iter()
+            .any(|(_, object)| object.session_id == session_id && &object.name == object_name);
+
+        Ok(is_exists)
+    }
+
+    fn get_max_object_default_name_num(&mut self, session_id: SessionId) -> Result<i64> {
+        let max_num = self
+            .db
+            .objects
+            .iter()
+            .filter(|(_, object)| object.session_id == session_id)
+            .filter_map(|(_, object)| object.name.strip_prefix("object-"))
+            .map(|num| {
+                if !num.is_empty() && num.chars().all(|c| c.is_ascii_digit()) {
+                    num.parse().unwrap_or(-1)
+                } else {
+                    -1
+                }
+            })
+            .max()
+            .unwrap_or(-1);
+
+        Ok(max_num)
+    }
+
+    fn get_objects_delta(
+        &mut self,
+        active_layer_id: LayerId,
+        known_objects_ids: &[ObjectId],
+    ) -> Result<Vec<ObjectInfo>> {
+        let ancestors = self.ancestors(active_layer_id);
+
+        let objects = self
+            .visible_objects(&ancestors)
+            .filter(|(object_id, _)| !known_objects_ids.contains(object_id))
+            .map(|(object_id, object)| {
+                let removed_time = self.removed_time(&ancestors, object_id);
+
+                let object = object::Object::new(
+                    object.layer_id,
+                    object.name.clone(),
+                    object.radius,
+                    graphics::unpack_color(&object.color),
+                    object.mass,
+                    object.compute_step.into_rust_duration(),
+                );
+
+                ObjectInfo(
+                    object_id,
+                    object,
+                    removed_time.map(|time| time.into_rust_duration()),
+                )
+            })
+            .collect();
+
+        Ok(objects)
+    }
+
+    fn get_mass_overrides(&mut self, active_layer_id: LayerId) -> Result<Vec<MassOverrideInfo>> {
+        let ancestors = self.ancestors(active_layer_id);
+
+        let mut mass_overrides = vec![];
+        for ((layer_id, object_id, start_time), object_override) in self.db.overrides.iter() {
+            let depth = match ancestors.iter().position(|id| id == layer_id) {
+                Some(depth) => depth,
+                None => continue,
+            };
+
+            if let Some(mass) = object_override.mass {
+                mass_overrides.push(((*object_id, *start_time, depth), mass));
+            }
+        }
+
+        mass_overrides.sort_by_key(|(key, _)| *key);
+
+        Ok(mass_overrides
+            .into_iter()
+            .map(|((object_id, start_time, _), mass)| {
+                MassOverrideInfo(object_id, start_time.into_rust_duration(), mass)
+            })
+            .collect())
+    }
+}
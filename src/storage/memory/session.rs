@@ -0,0 +1,318 @@
+use super::{LayerRow, MemoryTransaction, SessionRow};
+use crate::{
+    r#type::{LayerId, SessionId, SessionInfo, SessionName},
+    storage::{
+        dump::{BookmarkDump, LayerDump, LocationDump, ObjectDump, OverrideDump, SessionDump},
+        SessionApi,
+    },
+    Result,
+};
+use std::collections::{HashMap, HashSet};
+use chrono::TimeZone;
+
+impl MemoryTransaction {
+    pub(super) fn main_layer_id(&self, session_id: SessionId) -> Result<LayerId> {
+        self.db
+            .layers
+            .iter()
+            .find(|(_, layer)| layer.session_id == session_id)
+            .map(|(layer_id, _)| *layer_id)
+            .ok_or_else(|| Self::backend_error(format!("session #{} has no layers", session_id)))
+    }
+
+    /// Unlocks the hanged sessions and removes the unlocked unnamed ones
+    /// (the session triggers of the Postgres backend).
+    pub(super) fn maintain_sessions(&mut self) {
+        let now = Self::now();
+        let max_hang_time = self.session_max_hang_time.num_milliseconds();
+
+        self.db.sessions.update_where(
+            |_, session| session.is_locked && now - session.last_access > max_hang_time,
+            |session| session.is_locked = false,
+        );
+
+        let unnamed_sessions: Vec<_> = self
+            .db
+            .sessions
+            .iter()
+            .filter(|(_, session)| !session.is_locked && session.name.is_none())
+            .map(|(session_id, _)| *session_id)
+            .collect();
+
+        for session_id in unnamed_sessions {
+            self.remove_session(session_id);
+        }
+    }
+
+    pub(super) fn session_id_by_name(&self, name: &str) -> Option<(SessionId, &SessionRow)> {
+        self.db
+            .sessions
+            .iter()
+            .find(|(_, session)| session.name.as_deref() == Some(name))
+            .map(|(session_id, session)| (*session_id, session))
+    }
+
+    fn session_name(&self, id: SessionId) -> Option<SessionName> {
+        self.db
+            .sessions
+            .get(&id)
+            .and_then(|session| session.name.clone())
+    }
+
+    pub(super) fn locked_or_not_exists(name: &str) -> crate::Error {
+        Self::backend_error(format!("session `{}` is either locked or not exists", name))
+    }
+
+    fn check_session_name_is_free(&self, name: &str) -> Result<()> {
+        match self.session_id_by_name(name) {
+            Some(_) => Err(Self::backend_error(format!(
+                "session `{}` already exists",
+                name
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl SessionApi for MemoryTransaction {
+    fn new(&mut self, name: Option<SessionName>) -> Result<(SessionId, LayerId)> {
+        if let Some(name) = &name {
+            self.check_session_name_is_free(name)?;
+        }
+
+        self.db.last_session_id += 1;
+        let session_id = self.db.last_session_id;
+
+        self.db.sessions.insert(
+            session_id,
+            SessionRow {
+                name,
+                last_access: Self::now(),
+                is_locked: true,
+            },
+        );
+
+        self.db.last_layer_id += 1;
+        let layer_id = self.db.last_layer_id;
+
+        self.db.layers.insert(
+            layer_id,
+            LayerRow {
+                session_id,
+                parent_id: None,
+                name: "main".into(),
+                start_time: 0,
+            },
+        );
+
+        self.maintain_sessions();
+
+        Ok((session_id, layer_id))
+    }
+
+    fn update_access_time(&mut self, id: SessionId) -> Result<()> {
+        let now = Self::now();
+
+        self.db.sessions.update(&id, |session| session.last_access = now);
+        self.maintain_sessions();
+
+        Ok(())
+    }
+
+    fn unlock(&mut self, id: SessionId) -> Result<()> {
+        self.db.sessions.update(&id, |session| session.is_locked = false);
+        self.maintain_sessions();
+
+        Ok(())
+    }
+
+    fn save(&mut self, id: SessionId, name: &str) -> Result<()> {
+        match self.session_id_by_name(name) {
+            Some((session_id, _)) if session_id == id => return Ok(()),
+            _ => self.check_session_name_is_free(name)?,
+        }
+
+        self.db.sessions.update(&id, |session| session.name = Some(name.into()));
+
+        Ok(())
+    }
+
+    fn load(&mut self, name: &str) -> Result<(SessionId, LayerId)> {
+        let session_id = match self.session_id_by_name(name) {
+            Some((session_id, session)) if !session.is_locked => session_id,
+            _ => return Err(Self::locked_or_not_exists(name)),
+        };
+
+        let now = Self::now();
+
+        self.db.sessions.update(&session_id, |session| {
+            session.last_access = now;
+            session.is_locked = true;
+        });
+
+        self.maintain_sessions();
+
+        Ok((session_id, self.main_layer_id(session_id)?))
+    }
+
+    fn attach(&mut self, name: &str) -> Result<(SessionId, LayerId)> {
+        let session_id = self
+            .session_id_by_name(name)
+            .map(|(session_id, _)| session_id)
+            .ok_or_else(|| Self::backend_error(format!("session `{}` not exists", name)))?;
+
+        Ok((session_id, self.main_layer_id(session_id)?))
+    }
+
+    fn get_unlocked_id(&mut self, name: &str, current_id: SessionId) -> Result<SessionId> {
+        match self.session_id_by_name(name) {
+            Some((session_id, session)) if !session.is_locked || session_id == current_id => {
+                Ok(session_id)
+            }
+            _ => Err(Self::locked_or_not_exists(name)),
+        }
+    }
+
+    fn rename(&mut self, old_name: &str, new_name: &str) -> Result<()> {
+        let session_id = match self.session_id_by_name(old_name) {
+            Some((session_id, _)) => session_id,
+            None => return Err(Self::backend_error(format!("session `{}` not found", old_name))),
+        };
+
+        self.check_session_name_is_free(new_name)?;
+        self.db
+            .sessions
+            .update(&session_id, |session| session.name = Some(new_name.into()));
+
+        Ok(())
+    }
+
+    fn get_list(&mut self) -> Result<Vec<SessionInfo>> {
+        let mut infos: Vec<_> = self
+            .db
+            .sessions
+            .iter()
+            .filter_map(|(_, session)| {
+                session.name.as_ref().map(|name| SessionInfo {
+                    name: name.clone(),
+                    last_access: chrono::Local.timestamp_millis(session.last_access),
+                    is_locked: session.is_locked,
+                })
+            })
+            .collect();
+
+        infos.sort_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
+
+        Ok(infos)
+    }
+
+    fn get_name(&mut self, id: SessionId) -> Result<SessionName> {
+        Ok(self.session_name(id).unwrap_or("/unnamed/".into()))
+    }
+
+    fn dump(&mut self, id: SessionId, with_computed: bool) -> Result<SessionDump> {
+        let mut dump = SessionDump::new(self.session_name(id));
+
+        dump.layers = self
+            .db
+            .layers
+            .iter()
+            .filter(|(_, layer)| layer.session_id == id)
+            .map(|(layer_id, layer)| LayerDump {
+                id: *layer_id,
+                parent_id: layer.parent_id,
+                name: layer.name.clone(),
+                start_time: layer.start_time,
+            })
+            .collect();
+
+        let layer_ids: HashSet<_> = dump.layers.iter().map(|layer| layer.id).collect();
+
+        dump.objects = self
+            .db
+            .objects
+            .iter()
+            .filter(|(_, object)| object.session_id == id)
+            .map(|(object_id, object)| ObjectDump {
+                id: *object_id,
+                layer_id: object.layer_id,
+                name: object.name.clone(),
+                radius: object.radius,
+                color: object.color,
+                mass: object.mass,
+                compute_step: object.compute_step,
+            })
+            .collect();
+
+        dump.overrides = self
+            .db
+            .overrides
+            .iter()
+            .filter(|((layer_id, _, _), _)| layer_ids.contains(layer_id))
+            .map(|((layer_id, object_id, start_time), over)| OverrideDump {
+                layer_id: *layer_id,
+                object_id: *object_id,
+                start_time: Some(*start_time),
+                mass: over.mass,
+                removed_time: over.removed_time,
+            })
+            .collect();
+
+        dump.bookmarks = self
+            .db
+            .bookmarks
+            .iter()
+            .filter(|(_, bookmark)| bookmark.session_id == id)
+            .map(|(_, bookmark)| BookmarkDump {
+                layer_id: bookmark.layer_id,
+                name: bookmark.name.clone(),
+                t: bookmark.t,
+            })
+            .collect();
+
+        dump.bookmarks.sort_by(|lhs, rhs| (lhs.t, &lhs.name).cmp(&(rhs.t, &rhs.name)));
+
+        // The initial location is the earliest one of the object in the layer.
+        let mut initial_locations = HashMap::new();
+        for (location_id, location) in self.db.locations.iter() {
+            if layer_ids.contains(&location.layer_id) {
+                let initial = initial_locations
+                    .entry((location.object_id, location.layer_id))
+                    .or_insert((location.t, *location_id));
+
+                *initial = (location.t, *location_id).min(*initial);
+            }
+        }
+
+        let initial_location_ids: HashSet<_> = initial_locations
+            .values()
+            .map(|(_, location_id)| *location_id)
+            .collect();
+
+        dump.locations = self
+            .db
+            .locations
+            .iter()
+            .filter(|(location_id, location)| {
+                layer_ids.contains(&location.layer_id)
+                    && (with_computed || initial_location_ids.contains(*location_id))
+            })
+            .map(|(_, location)| LocationDump {
+                object_id: location.object_id,
+                layer_id: location.layer_id,
+                t: location.t,
+                x: location.x,
+                y: location.y,
+                z: location.z,
+                vx: location.vx,
+                vy: location.vy,
+                vz: location.vz,
+                vcx: location.vcx.filter(|_| with_computed),
+                vcy: location.vcy.filter(|_| with_computed),
+                vcz: location.vcz.filter(|_| with_computed),
+            })
+            .collect();
+
+        Ok(dump)
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::{btree_map, BTreeMap};
+
+/// A table of rows ordered by their keys.
+///
+/// Every change made since the last commit is logged,
+/// so the table can be rolled back to the committed state.
+#[derive(Debug)]
+pub struct Table<K: Ord + Copy, V: Clone> {
+    rows: BTreeMap<K, V>,
+    undo_log: Vec<(K, Option<V>)>,
+}
+
+impl<K: Ord + Copy, V: Clone> Default for Table<K, V> {
+    fn default() -> Self {
+        Self {
+            rows: BTreeMap::new(),
+            undo_log: vec![],
+        }
+    }
+}
+
+impl<K: Ord + Copy, V: Clone> Table<K, V> {
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.rows.get(key)
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.rows.contains_key(key)
+    }
+
+    pub fn iter(&self) -> btree_map::Iter<K, V> {
+        self.rows.iter()
+    }
+
+    pub fn insert(&mut self, key: K, row: V) {
+        let old_row = self.rows.insert(key, row);
+        self.undo_log.push((key, old_row));
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let old_row = self.rows.remove(key)?;
+        self.undo_log.push((*key, Some(old_row.clone())));
+
+        Some(old_row)
+    }
+
+    /// Removes the rows matching the predicate and returns them.
+    pub fn remove_where(&mut self, mut predicate: impl FnMut(&K, &V) -> bool) -> Vec<(K, V)> {
+        let keys: Vec<K> = self
+            .rows
+            .iter()
+            .filter(|(key, row)| predicate(key, row))
+            .map(|(key, _)| *key)
+            .collect();
+
+        keys.into_iter()
+            .filter_map(|key| self.remove(&key).map(|row| (key, row)))
+            .collect()
+    }
+
+    /// Updates the row if it exists. Returns `false` if there is no such row.
+    pub fn update(&mut self, key: &K, f: impl FnOnce(&mut V)) -> bool {
+        match self.rows.get_mut(key) {
+            Some(row) => {
+                self.undo_log.push((*key, Some(row.clone())));
+                f(row);
+
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Updates all the rows matching the predicate. Returns the number of updated rows.
+    pub fn update_where(
+        &mut self,
+        mut predicate: impl FnMut(&K, &V) -> bool,
+        mut f: impl FnMut(&mut V),
+    ) -> usize {
+        let keys: Vec<K> = self
+            .rows
+            .iter()
+            .filter(|(key, row)| predicate(key, row))
+            .map(|(key, _)| *key)
+            .collect();
+
+        keys.iter().filter(|key| self.update(key, &mut f)).count()
+    }
+
+    pub fn commit(&mut self) {
+        self.undo_log.clear();
+    }
+
+    pub fn rollback(&mut self) {
+        while let Some((key, old_row)) = self.undo_log.pop() {
+            match old_row {
+                Some(old_row) => self.rows.insert(key, old_row),
+                None => self.rows.remove(&key),
+            };
+        }
+    }
+}
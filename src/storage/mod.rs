@@ -1,28 +1,26 @@
 use {
     crate::{
+        config::Config,
         make_error,
+        r#type::{LayerId, SessionId},
         Result,
-        // r#type::{
-        //     ObjectId,
-        //     Coord,
-        //     Distance,
-        //     RelativeTime,
-        //     Vector,
-        // }
     },
-    postgres::Transaction,
-    r2d2_postgres::PostgresConnectionManager,
+    std::{
+        path::PathBuf,
+        sync::{mpsc, Arc},
+    },
 };
 
-pub mod layer;
-pub mod location;
-pub mod object;
-pub mod session;
+pub mod api;
+pub mod dump;
+pub mod memory;
+pub mod postgres;
+pub mod sqlite;
+
+#[cfg(test)]
+mod tests;
 
-pub use layer::Layer;
-pub use location::Location;
-pub use object::Object;
-pub use session::Session;
+pub use api::{BookmarkApi, JournalApi, LayerApi, LocationApi, ObjectApi, SessionApi};
 
 #[macro_export]
 macro_rules! map_err {
@@ -33,10 +31,10 @@ macro_rules! map_err {
 
 #[macro_export]
 macro_rules! query {
-    ($query:expr $(, $($additional:tt)*)?) => {
+    ($schema_name:expr => $query:expr $(, $($additional:tt)*)?) => {
         format!(
             $query,
-            schema_name = $crate::app::APP_NAME
+            schema_name = $schema_name
             $(, $($additional)*)?
         ).as_str()
     };
@@ -44,14 +42,18 @@ macro_rules! query {
 
 #[macro_export]
 macro_rules! transaction {
-    ($storage:expr => $trans:ident $(($isolation_level:ident))? { $($tt:tt)* }) => {{
-        let mut pooled_connection = $storage.pool.get()?;
+    (@isolation) => {
+        $crate::storage::IsolationLevel::ReadCommitted
+    };
 
-        let mut $trans = pooled_connection
-            .build_transaction()
-            $(.isolation_level(postgres::IsolationLevel::$isolation_level))?
-            .start()
-            .map_err(|err| $crate::make_error![Error::Storage::Transaction(err)])?;
+    (@isolation $isolation_level:ident) => {
+        $crate::storage::IsolationLevel::$isolation_level
+    };
+
+    ($storage:expr => $trans:ident $(($isolation_level:ident))? { $($tt:tt)* }) => {{
+        let mut $trans = $storage.transaction(
+            $crate::transaction![@isolation $($isolation_level)?]
+        )?;
 
         $($tt)*
 
@@ -59,94 +61,102 @@ macro_rules! transaction {
     }};
 }
 
-#[derive(Clone)]
-pub struct StorageManager {
-    pub(in crate) pool: r2d2::Pool<PostgresConnectionManager<postgres::NoTls>>,
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IsolationLevel {
+    ReadCommitted,
+    RepeatableRead,
 }
 
-impl StorageManager {
-    pub fn setup(connection_string: &str, session_max_hang_time: chrono::Duration) -> Result<Self> {
-        let mgr = PostgresConnectionManager::new(connection_string.parse()?, postgres::NoTls);
+#[derive(Debug, Clone)]
+pub enum StorageKind {
+    Postgres,
+    Sqlite(PathBuf),
+    Memory,
+}
 
-        let pool = r2d2::Pool::new(mgr)?;
-        {
-            let mut client = pool.get()?;
-            Self::setup_schema(&mut client, session_max_hang_time)?;
-        }
+/// A change committed by any application sharing the storage.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StorageEvent {
+    /// Layers were added, removed or renamed.
+    LayersChanged(SessionId),
+    LocationsAdded(SessionId, LayerId),
+    BookmarksChanged(SessionId),
+}
 
-        let storage_mgr = Self { pool };
+pub trait StorageBackend: Send + Sync {
+    fn transaction(&self, isolation_level: IsolationLevel) -> Result<Box<dyn StorageTransaction>>;
 
-        Ok(storage_mgr)
+    /// Starts receiving the storage changes.
+    /// The storages which can't be shared between applications return `None`.
+    fn subscribe(&self) -> Result<Option<mpsc::Receiver<StorageEvent>>> {
+        Ok(None)
     }
+}
 
-    fn setup_schema(
-        psql: &mut postgres::Client,
-        session_max_hang_time: chrono::Duration,
-    ) -> Result<()> {
-        let setup_query = format! {
-            r#"
-                {schema}
-                {session}
-                {layer}
-                {object}
-                {location}
-                {session_triggers}
-                {layer_triggers}
-                {location_triggers}
-            "#,
-            schema = query![include_str!("sql/setup/schema.sql")],
-            session = query! {
-                include_str!("sql/setup/session.sql"),
-                session_max_hang_time = session_max_hang_time.num_seconds()
-            },
-            layer = query![include_str!["sql/setup/layer.sql"]],
-            object = query![include_str!["sql/setup/object.sql"]],
-            location = query![include_str!["sql/setup/location.sql"]],
-            session_triggers = query![include_str!("sql/setup/session_triggers.sql")],
-            layer_triggers = query![include_str!("sql/setup/layer_triggers.sql")],
-            location_triggers = query![include_str!("sql/setup/location_triggers.sql")],
-        };
+pub trait StorageTransaction {
+    fn session(&mut self) -> &mut dyn SessionApi;
 
-        psql.batch_execute(setup_query.as_str())
-            .map_err(|err| make_error![Error::Storage::SetupSchema(err)])
-    }
+    fn layer(&mut self) -> &mut dyn LayerApi;
 
-    // pub fn session(&mut self) -> Session {
-    //     Session::new_api(self)
-    // }
+    fn object(&mut self) -> &mut dyn ObjectApi;
 
-    // pub fn layer(&mut self) -> Result<Layer> {
-    //     self.transaction().map(|transaction| Layer::new_api(transaction))
-    // }
-    // pub fn object(&mut self) -> Object {
-    //     Object::new_api(self)
-    // }
-}
+    fn location(&mut self) -> &mut dyn LocationApi;
 
-pub trait StorageTransaction<'storage> {
-    fn session<'t>(&'t mut self) -> session::Session<'t, 'storage>;
+    fn bookmark(&mut self) -> &mut dyn BookmarkApi;
 
-    fn layer<'t>(&'t mut self) -> layer::Layer<'t, 'storage>;
+    fn journal(&mut self) -> &mut dyn JournalApi;
 
-    fn object<'t>(&'t mut self) -> object::Object<'t, 'storage>;
+    /// Commits the transaction. A transaction dropped without the commit is rolled back.
+    fn commit(self: Box<Self>) -> Result<()>;
+}
 
-    fn location<'t>(&'t mut self) -> location::Location<'t, 'storage>;
+#[derive(Clone)]
+pub struct StorageManager {
+    backend: Arc<dyn StorageBackend>,
 }
 
-impl<'storage> StorageTransaction<'storage> for Transaction<'storage> {
-    fn session<'t>(&'t mut self) -> session::Session<'t, 'storage> {
-        session::Session::new_api(self)
+impl StorageManager {
+    pub fn setup(config: &Config) -> Result<Self> {
+        let session_max_hang_time = config.session_max_hang_time;
+
+        let backend: Arc<dyn StorageBackend> = match &config.storage_kind {
+            StorageKind::Postgres => Arc::new(postgres::PostgresBackend::setup(config)?),
+            StorageKind::Sqlite(path) => Arc::new(sqlite::SqliteBackend::setup(
+                path.clone(),
+                session_max_hang_time,
+            )?),
+            StorageKind::Memory => Arc::new(memory::MemoryBackend::setup(session_max_hang_time)),
+        };
+
+        Ok(Self { backend })
+    }
+
+    pub fn migration_status(config: &Config) -> Result<postgres::migration::MigrationStatus> {
+        Self::migration_backend(config)?.get_migration_status()
     }
 
-    fn layer<'t>(&'t mut self) -> layer::Layer<'t, 'storage> {
-        layer::Layer::new_api(self)
+    pub fn migrate_up(config: &Config) -> Result<Vec<&'static postgres::migration::Migration>> {
+        Self::migration_backend(config)?.apply_migrations()
+    }
+
+    /// SQLite and in-memory storages create their schema as is, without versioning.
+    fn migration_backend(config: &Config) -> Result<postgres::PostgresBackend> {
+        match config.storage_kind {
+            StorageKind::Postgres => postgres::PostgresBackend::connect(config),
+            _ => Err(make_error![Error::Storage::Backend(
+                "the migrations are supported by the postgres storage only".into()
+            )]),
+        }
     }
 
-    fn object<'t>(&'t mut self) -> object::Object<'t, 'storage> {
-        object::Object::new_api(self)
+    pub fn transaction(
+        &self,
+        isolation_level: IsolationLevel,
+    ) -> Result<Box<dyn StorageTransaction>> {
+        self.backend.transaction(isolation_level)
     }
 
-    fn location<'t>(&'t mut self) -> location::Location<'t, 'storage> {
-        location::Location::new_api(self)
+    pub fn subscribe(&self) -> Result<Option<mpsc::Receiver<StorageEvent>>> {
+        self.backend.subscribe()
     }
 }
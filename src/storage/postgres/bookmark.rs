@@ -0,0 +1,60 @@
+use super::PostgresTransaction;
+use crate::{
+    map_err, query,
+    r#type::{BookmarkInfo, BookmarkName, IntoRustDuration, IntoStorageDuration, LayerId, RawTime, SessionId},
+    storage::BookmarkApi,
+    Result,
+};
+
+impl BookmarkApi for PostgresTransaction {
+    fn add(
+        &mut self,
+        session_id: SessionId,
+        layer_id: LayerId,
+        name: &BookmarkName,
+        time: chrono::Duration,
+    ) -> Result<()> {
+        self.client
+            .execute(
+                query![self.schema => "CALL {schema_name}.add_bookmark($1, $2, $3, $4)"],
+                &[&session_id, &layer_id, name, &time.into_storage_duration()],
+            )
+            .map(|_| {})
+            .map_err(map_err!(Error::Storage::Bookmark))
+    }
+
+    fn remove(&mut self, session_id: SessionId, name: &BookmarkName) -> Result<()> {
+        self.client
+            .execute(
+                query![self.schema => "CALL {schema_name}.remove_bookmark($1, $2)"],
+                &[&session_id, name],
+            )
+            .map(|_| {})
+            .map_err(map_err!(Error::Storage::Bookmark))
+    }
+
+    fn get_list(&mut self, session_id: SessionId) -> Result<Vec<BookmarkInfo>> {
+        let rows = self
+            .client
+            .query(
+                query![self.schema => "SELECT * FROM {schema_name}.session_bookmarks($1)"],
+                &[&session_id],
+            )
+            .map_err(map_err!(Error::Storage::Bookmark))?;
+
+        let infos = rows
+            .into_iter()
+            .map(|row| {
+                let time: RawTime = row.get(2);
+
+                BookmarkInfo {
+                    name: row.get(0),
+                    layer_name: row.get(1),
+                    time: time.into_rust_duration(),
+                }
+            })
+            .collect();
+
+        Ok(infos)
+    }
+}
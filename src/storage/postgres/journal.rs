@@ -0,0 +1,87 @@
+use super::PostgresTransaction;
+use crate::{
+    map_err, query,
+    r#type::{JournalOperation, LayerId, LayerName, SessionId, SessionName},
+    storage::JournalApi,
+    Result,
+};
+use postgres::Row;
+
+impl PostgresTransaction {
+    fn journal_operation(row: &Row) -> JournalOperation {
+        let operation: String = row.get(0);
+        let target_id = row.get(1);
+        let old_name = row.get(2);
+
+        match operation.as_str() {
+            "rm-layer" => JournalOperation::RemoveLayer(target_id, old_name),
+            "rename-layer" => JournalOperation::RenameLayer(target_id, old_name, row.get(3)),
+            "delete-session" => JournalOperation::DeleteSession(target_id, old_name),
+            _ => unreachable!("unknown journal operation \"{}\"", operation),
+        }
+    }
+}
+
+impl JournalApi for PostgresTransaction {
+    fn remove_layer(&mut self, session_id: SessionId, layer_id: LayerId) -> Result<()> {
+        self.client
+            .execute(
+                query![self.schema => "CALL {schema_name}.journal_remove_layer($1, $2)"],
+                &[&session_id, &layer_id],
+            )
+            .map(|_| {})
+            .map_err(map_err!(Error::Storage::Journal))
+    }
+
+    fn rename_layer(
+        &mut self,
+        session_id: SessionId,
+        layer_id: LayerId,
+        new_layer_name: &LayerName,
+    ) -> Result<()> {
+        self.client
+            .execute(
+                query![self.schema => "CALL {schema_name}.journal_rename_layer($1, $2, $3)"],
+                &[&session_id, &layer_id, new_layer_name],
+            )
+            .map(|_| {})
+            .map_err(map_err!(Error::Storage::Journal))
+    }
+
+    fn delete_session(&mut self, session_id: SessionId, name: &SessionName) -> Result<()> {
+        self.client
+            .execute(
+                query![self.schema => "CALL {schema_name}.journal_delete_session($1, $2)"],
+                &[&session_id, name],
+            )
+            .map(|_| {})
+            .map_err(map_err!(Error::Storage::Journal))
+    }
+
+    fn undo(&mut self, session_id: SessionId) -> Result<Option<JournalOperation>> {
+        self.client
+            .query_opt(
+                query![self.schema => "SELECT * FROM {schema_name}.journal_undo($1)"],
+                &[&session_id],
+            )
+            .map(|row| row.map(|row| Self::journal_operation(&row)))
+            .map_err(map_err!(Error::Storage::Journal))
+    }
+
+    fn redo(&mut self, session_id: SessionId) -> Result<Option<JournalOperation>> {
+        self.client
+            .query_opt(
+                query![self.schema => "SELECT * FROM {schema_name}.journal_redo($1)"],
+                &[&session_id],
+            )
+            .map(|row| row.map(|row| Self::journal_operation(&row)))
+            .map_err(map_err!(Error::Storage::Journal))
+    }
+
+    fn purge(&mut self, session_id: Option<SessionId>) -> Result<()> {
+        self.client
+            .execute(query![self.schema => "CALL {schema_name}.purge_journal($1)"], &[&session_id])
+            .map(|_| {})
+            .map_err(map_err!(Error::Storage::Journal))
+    }
+}
@@ -0,0 +1,464 @@
+use super::PostgresTransaction;
+use crate::{
+    make_error, map_err, query,
+    r#type::{
+        IntoRustDuration, IntoStorageDuration, LayerCopyInfo, LayerId, LayerInfo, LayerMergeInfo,
+        LayerName, ObjectId, ObjectName, RawTime, SessionId,
+    },
+    storage::LayerApi,
+    Result,
+};
+use std::collections::{BTreeSet, HashMap};
+
+impl PostgresTransaction {
+    fn query_names(&mut self, query: &str, layer_id: LayerId) -> Result<Vec<String>> {
+        let rows = self
+            .client
+            .query(query, &[&layer_id])
+            .map_err(map_err!(Error::Storage::Layer))?;
+
+        Ok(rows.into_iter().map(|row| row.get(0)).collect())
+    }
+}
+
+impl LayerApi for PostgresTransaction {
+    fn get_name(&mut self, layer_id: LayerId) -> Result<LayerName> {
+        self.client
+            .query_one(query![self.schema => "SELECT {schema_name}.layer_name($1);"], &[&layer_id])
+            .map(|row| row.get(0))
+            .map_err(map_err!(Error::Storage::Layer))
+    }
+
+    fn get_start_time(&mut self, layer_id: LayerId) -> Result<chrono::Duration> {
+        self.client
+            .query_one(
+                query![self.schema => "SELECT {schema_name}.layer_start_time($1)"],
+                &[&layer_id],
+            )
+            .map(|row| {
+                let raw_time: RawTime = row.get(0);
+
+                raw_time.into_rust_duration()
+            })
+            .map_err(map_err!(Error::Storage::Layer))
+    }
+
+    fn rename_layer(&mut self, layer_id: LayerId, new_layer_name: &LayerName) -> Result<()> {
+        self.client
+            .execute(
+                query![self.schema => "CALL {schema_name}.rename_layer($1, $2)"],
+                &[&layer_id, &new_layer_name],
+            )
+            .map(|_| {})
+            .map_err(map_err!(Error::Storage::Layer))
+    }
+
+    fn get_layer_id(
+        &mut self,
+        session_id: SessionId,
+        layer_name: &LayerName,
+    ) -> Result<LayerId> {
+        let row = self
+            .client
+            .query_one(
+                query![self.schema => "SELECT {schema_name}.layer_id($1, $2)"],
+                &[&session_id, layer_name],
+            )
+            .map_err(map_err!(Error::Storage::Layer))?;
+
+        row.try_get(0)
+            .map_err(|_| make_error![Error::Layer::LayerNotFound(layer_name.clone())])
+    }
+
+    // pub fn get_layer_object_ids(&mut self, layer_id: LayerId) -> Result<Vec<ObjectId>> {
+    //     self.client
+    //         .query_one(
+    //             query![self.schema => "SELECT {schema_name}.layer_object_ids($1)"],
+    //             &[&layer_id],
+    //         )
+    //         .map(|row| row.try_get(0).unwrap_or(vec![]))
+    //         .map_err(map_err!(Error::Storage::Layer))
+    // }
+
+    fn get_main_layer(&mut self, session_id: SessionId) -> Result<LayerId> {
+        self.client
+            .query_one(
+                query![self.schema => "SELECT {schema_name}.main_layer_id($1)"],
+                &[&session_id],
+            )
+            .map(|row| row.get(0))
+            .map_err(map_err!(Error::Storage::Layer))
+    }
+
+    fn get_layer_children(
+        &mut self,
+        session_id: SessionId,
+        layer_id: LayerId,
+    ) -> Result<Vec<LayerId>> {
+        self.client
+            .query_one(
+                query![self.schema => "SELECT {schema_name}.layer_children($1, $2)"],
+                &[&session_id, &layer_id],
+            )
+            .map(|row| row.try_get(0).unwrap_or(vec![]))
+            .map_err(map_err!(Error::Storage::Layer))
+    }
+
+    fn get_layers_info(&mut self, session_id: SessionId) -> Result<Vec<LayerInfo>> {
+        let rows = self
+            .client
+            .query(
+                query![self.schema => "SELECT * FROM {schema_name}.session_layers_info($1)"],
+                &[&session_id],
+            )
+            .map_err(map_err!(Error::Storage::Layer))?;
+
+        let infos = rows
+            .into_iter()
+            .map(|row| {
+                let start_time: RawTime = row.get(3);
+                let last_computed_time: Option<RawTime> = row.get(5);
+
+                LayerInfo {
+                    id: row.get(0),
+                    parent_id: row.get(1),
+                    name: row.get(2),
+                    start_time: start_time.into_rust_duration(),
+                    objects_count: row.get(4),
+                    last_computed_time: last_computed_time.map(|t| t.into_rust_duration()),
+                    is_active: false,
+                    is_current: false,
+                }
+            })
+            .collect();
+
+        Ok(infos)
+    }
+
+    fn get_current_layer_id(
+        &mut self,
+        active_layer_id: LayerId,
+        vtime: chrono::Duration,
+    ) -> Result<LayerId> {
+        self.client
+            .query_one(
+                query![self.schema => "SELECT {schema_name}.current_layer_id($1, $2)"],
+                &[&active_layer_id, &vtime.into_storage_duration()],
+            )
+            .map(|row| row.get(0))
+            .map_err(map_err!(Error::Storage::Layer))
+    }
+
+    fn add_layer(
+        &mut self,
+        session_id: SessionId,
+        active_layer_id: LayerId,
+        new_layer_name: &LayerName,
+        new_layer_start_time: chrono::Duration,
+    ) -> Result<LayerId> {
+        self.client
+            .query_one(
+                query! {self.schema => "
+                    SELECT {schema_name}.add_layer(
+                        $1,
+                        $2,
+                        $3,
+                        $4
+                    )
+                "},
+                &[
+                    &session_id,
+                    &active_layer_id,
+                    &new_layer_name,
+                    &new_layer_start_time.into_storage_duration(),
+                ],
+            )
+            .map(|row| row.get(0))
+            .map_err(map_err!(Error::Storage::Layer))
+    }
+
+    fn layer_ancestors(&mut self, layer_id: LayerId) -> Result<Vec<LayerId>> {
+        let rows = self
+            .client
+            .query(
+                query![self.schema => "SELECT layer_id FROM {schema_name}.layer_ancestors($1)"],
+                &[&layer_id],
+            )
+            .map_err(map_err!(Error::Storage::Layer))?;
+
+        Ok(rows.into_iter().map(|row| row.get(0)).collect())
+    }
+
+    fn get_parent_id(&mut self, layer_id: LayerId) -> Result<Option<LayerId>> {
+        self.client
+            .query_one(
+                query![self.schema => "SELECT {schema_name}.layer_parent_id($1)"],
+                &[&layer_id],
+            )
+            .map(|row| row.get(0))
+            .map_err(map_err!(Error::Storage::Layer))
+    }
+
+    fn get_merge_info(&mut self, layer_id: LayerId) -> Result<LayerMergeInfo> {
+        let name = self.get_name(layer_id)?;
+
+        let parent_id = self
+            .get_parent_id(layer_id)?
+            .ok_or_else(|| make_error![Error::Layer::NoParentLayer(name)])?;
+
+        let parent_name = self.get_name(parent_id)?;
+        let start_time = self.get_start_time(layer_id)?;
+
+        let replaced_locations = self
+            .client
+            .query_one(
+                query![self.schema => "SELECT {schema_name}.merge_replaced_locations_count($1)"],
+                &[&layer_id],
+            )
+            .map(|row| row.get(0))
+            .map_err(map_err!(Error::Storage::Layer))?;
+
+        let moved_locations = self
+            .client
+            .query_one(
+                query![self.schema => "SELECT COUNT(*) FROM {schema_name}.location WHERE layer_fk_id = $1"],
+                &[&layer_id],
+            )
+            .map(|row| row.get(0))
+            .map_err(map_err!(Error::Storage::Layer))?;
+
+        let moved_objects = self.query_names(
+            query! {self.schema => "
+                SELECT object_name
+                FROM {schema_name}.object
+                WHERE layer_fk_id = $1
+                ORDER BY object_name
+            "},
+            layer_id,
+        )?;
+
+        let overridden_objects = self.query_names(
+            query! {self.schema => "
+                SELECT DISTINCT object_name
+                FROM {schema_name}.object_override
+                INNER JOIN {schema_name}.object
+                    ON object_fk_id = object_id
+                WHERE object_override.layer_fk_id = $1
+                ORDER BY object_name
+            "},
+            layer_id,
+        )?;
+
+        let reparented_layers = self.query_names(
+            query! {self.schema => "
+                SELECT layer_name
+                FROM {schema_name}.layer
+                INNER JOIN {schema_name}.layer_family
+                    ON child_layer_id = layer_id
+                WHERE parent_layer_id = $1
+                ORDER BY layer_name
+            "},
+            layer_id,
+        )?;
+
+        let conflicting_layers = self.query_names(
+            query! {self.schema => "
+                SELECT sibling.layer_name
+                FROM {schema_name}.layer sibling
+                INNER JOIN {schema_name}.layer_family family
+                    ON family.child_layer_id = sibling.layer_id
+                WHERE
+                    family.parent_layer_id = {schema_name}.layer_parent_id($1)
+                    AND sibling.layer_id <> $1
+                    AND sibling.start_time > (
+                        SELECT MIN(out_t)
+                        FROM {schema_name}.merge_replaced_locations($1)
+                    )
+                ORDER BY sibling.layer_name
+            "},
+            layer_id,
+        )?;
+
+        Ok(LayerMergeInfo {
+            parent_name,
+            start_time,
+            replaced_locations,
+            moved_locations,
+            moved_objects,
+            overridden_objects,
+            reparented_layers,
+            conflicting_layers,
+        })
+    }
+
+    fn merge_layer(&mut self, layer_id: LayerId) -> Result<()> {
+        self.client
+            .execute(query![self.schema => "CALL {schema_name}.merge_layer($1)"], &[&layer_id])
+            .map(|_| {})
+            .map_err(map_err!(Error::Storage::Layer))
+    }
+
+    fn get_session_id(&mut self, layer_id: LayerId) -> Result<SessionId> {
+        self.client
+            .query_one(
+                query![self.schema => "SELECT session_fk_id FROM {schema_name}.layer WHERE layer_id = $1"],
+                &[&layer_id],
+            )
+            .map(|row| row.get(0))
+            .map_err(map_err!(Error::Storage::Layer))
+    }
+
+    fn copy_layer(
+        &mut self,
+        src_layer_id: LayerId,
+        dst_session_id: SessionId,
+        dst_parent_id: LayerId,
+        with_descendants: bool,
+        with_computed: bool,
+    ) -> Result<LayerCopyInfo> {
+        let is_same_session = self.get_session_id(src_layer_id)? == dst_session_id;
+
+        let subtree: Vec<(LayerId, Option<LayerId>)> = if with_descendants {
+            self.client
+                .query(
+                    query![self.schema => "SELECT layer_id, parent_layer_id FROM {schema_name}.layer_subtree($1)"],
+                    &[&src_layer_id],
+                )
+                .map_err(map_err!(Error::Storage::Layer))?
+                .into_iter()
+                .map(|row| (row.get(0), row.get(1)))
+                .collect()
+        } else {
+            vec![(src_layer_id, None)]
+        };
+
+        let mut layers_map = HashMap::new();
+        let mut objects_map: HashMap<ObjectId, ObjectId> = HashMap::new();
+        let mut skipped_objects = BTreeSet::new();
+
+        let mut info = LayerCopyInfo {
+            layers: vec![],
+            renamed_objects: vec![],
+            copied_objects: 0,
+            copied_locations: 0,
+            skipped_objects: vec![],
+        };
+
+        // The subtree is ordered by id, so parents are always copied before their children.
+        for (src_id, src_parent_id) in subtree {
+            let parent_id = match src_parent_id {
+                Some(src_parent_id) => layers_map[&src_parent_id],
+                None => dst_parent_id,
+            };
+
+            let new_layer_id: LayerId = self
+                .client
+                .query_one(
+                    query![self.schema => "SELECT {schema_name}.copy_layer_row($1, $2, $3)"],
+                    &[&src_id, &dst_session_id, &parent_id],
+                )
+                .map(|row| row.get(0))
+                .map_err(map_err!(Error::Storage::Layer))?;
+
+            layers_map.insert(src_id, new_layer_id);
+            info.layers.push((self.get_name(src_id)?, self.get_name(new_layer_id)?));
+
+            let objects = self
+                .client
+                .query(
+                    query! {self.schema => "
+                        SELECT object_id, object_name
+                        FROM {schema_name}.object
+                        WHERE layer_fk_id = $1
+                        ORDER BY object_id
+                    "},
+                    &[&src_id],
+                )
+                .map_err(map_err!(Error::Storage::Layer))?;
+
+            for row in objects {
+                let object_id: ObjectId = row.get(0);
+                let object_name: ObjectName = row.get(1);
+
+                let row = self
+                    .client
+                    .query_one(
+                        query! {self.schema => "
+                            SELECT object_id, object_name
+                            FROM {schema_name}.object
+                            WHERE object_id = {schema_name}.copy_object($1, $2, $3)
+                        "},
+                        &[&object_id, &dst_session_id, &new_layer_id],
+                    )
+                    .map_err(map_err!(Error::Storage::Layer))?;
+
+                let new_object_id: ObjectId = row.get(0);
+                let new_object_name: ObjectName = row.get(1);
+
+                if new_object_name != object_name {
+                    info.renamed_objects.push((object_name, new_object_name));
+                }
+
+                objects_map.insert(object_id, new_object_id);
+                info.copied_objects += 1;
+            }
+
+            // Tracks and overrides of both own and inherited objects.
+            let tracks = self
+                .client
+                .query(
+                    query! {self.schema => "
+                        SELECT object_id, object_name
+                        FROM {schema_name}.object
+                        WHERE object_id IN (
+                            SELECT object_fk_id
+                            FROM {schema_name}.location
+                            WHERE layer_fk_id = $1
+                            UNION
+                            SELECT object_fk_id
+                            FROM {schema_name}.object_override
+                            WHERE layer_fk_id = $1
+                        )
+                        ORDER BY object_id
+                    "},
+                    &[&src_id],
+                )
+                .map_err(map_err!(Error::Storage::Layer))?;
+
+            for row in tracks {
+                let object_id: ObjectId = row.get(0);
+
+                let dst_object_id = match objects_map.get(&object_id) {
+                    Some(dst_object_id) => *dst_object_id,
+                    None if is_same_session => object_id,
+                    None => {
+                        skipped_objects.insert(row.get::<_, ObjectName>(1));
+                        continue;
+                    }
+                };
+
+                let copied_locations: i64 = self
+                    .client
+                    .query_one(
+                        query![self.schema => "SELECT {schema_name}.copy_locations($1, $2, $3, $4, $5)"],
+                        &[&object_id, &src_id, &dst_object_id, &new_layer_id, &with_computed],
+                    )
+                    .map(|row| row.get(0))
+                    .map_err(map_err!(Error::Storage::Layer))?;
+
+                self.client
+                    .execute(
+                        query![self.schema => "CALL {schema_name}.copy_object_override($1, $2, $3, $4)"],
+                        &[&src_id, &object_id, &new_layer_id, &dst_object_id],
+                    )
+                    .map_err(map_err!(Error::Storage::Layer))?;
+
+                info.copied_locations += copied_locations;
+            }
+        }
+
+        info.skipped_objects = skipped_objects.into_iter().collect();
+
+        Ok(info)
+    }
+}
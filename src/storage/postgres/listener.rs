@@ -0,0 +1,76 @@
+use {
+    super::PostgresBackend,
+    crate::{make_error, map_err, query, storage::StorageEvent, Result},
+    log::{error, warn},
+    postgres::{fallible_iterator::FallibleIterator, NoTls},
+    std::sync::mpsc,
+};
+
+const LOG_TARGET: &'static str = "storage";
+
+impl PostgresBackend {
+    /// Listens to the changes sent by the notify triggers, see `sql/setup/notify_triggers.sql`.
+    ///
+    /// The listening connection isn't taken from the pool since it is held forever.
+    /// The listening thread stops when the receiver is dropped or the connection is lost.
+    pub(super) fn listen(&self) -> Result<mpsc::Receiver<StorageEvent>> {
+        let mut client = self
+            .connection_config
+            .connect(NoTls)
+            .map_err(|err| make_error![Error::Storage::Connection(err.to_string())])?;
+
+        client
+            .batch_execute(query![self.schema => "LISTEN {schema_name}_changes"])
+            .map_err(map_err!(Error::Storage::Raw))?;
+
+        let (sender, receiver) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            let mut notifications = client.notifications();
+            let mut notifications = notifications.blocking_iter();
+
+            loop {
+                match notifications.next() {
+                    Ok(Some(notification)) => match parse_event(notification.payload()) {
+                        Some(event) => {
+                            if sender.send(event).is_err() {
+                                break;
+                            }
+                        }
+                        None => warn! {
+                            target: LOG_TARGET,
+                            "unknown storage event `{}`", notification.payload()
+                        },
+                    },
+                    Ok(None) => break,
+                    Err(err) => {
+                        error! {
+                            target: LOG_TARGET,
+                            "the storage events are no longer received: {}", err
+                        }
+
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(receiver)
+    }
+}
+
+/// Parses the `<kind>:<session_id>[:<layer_id>]` payload.
+fn parse_event(payload: &str) -> Option<StorageEvent> {
+    let mut parts = payload.split(':');
+    let kind = parts.next()?;
+    let session_id = parts.next()?.parse().ok()?;
+
+    let event = match kind {
+        "layers" => StorageEvent::LayersChanged(session_id),
+        "locations" => StorageEvent::LocationsAdded(session_id, parts.next()?.parse().ok()?),
+        "bookmarks" => StorageEvent::BookmarksChanged(session_id),
+        _ => return None,
+    };
+
+    Some(event)
+}
@@ -0,0 +1,216 @@
+use super::PostgresTransaction;
+use crate::{
+    engine::context::{db_util::LocationInfo, TimeRange},
+    map_err,
+    object::GenCoord,
+    query,
+    r#type::{
+        Coord, IntoRustDuration, IntoStorageDuration, LayerId, ObjectId, RawTime, SessionId,
+        Vector,
+    },
+    storage::LocationApi,
+    Error, Result,
+};
+
+impl LocationApi for PostgresTransaction {
+    fn add(&mut self, object_id: ObjectId, layer_id: LayerId, coord: GenCoord) -> Result<()> {
+        let location = coord.location();
+        let velocity = coord.velocity();
+
+        self.client
+            .execute(
+                query![self.schema => "CALL {schema_name}.add_location($1, $2, $3, $4, $5, $6, $7, $8, $9)"],
+                &[
+                    &object_id,
+                    &layer_id,
+                    &coord.time().into_storage_duration(),
+                    &location[0],
+                    &location[1],
+                    &location[2],
+                    &velocity[0],
+                    &velocity[1],
+                    &velocity[2],
+                ],
+            )
+            .map(|_| {})
+            .map_err(map_err!(Error::Storage::Location))
+    }
+
+    fn add_locations(&mut self, locations: &[LocationInfo]) -> Result<()> {
+        let writer = self
+            .client
+            .copy_in(query![
+                self.schema => "COPY {schema_name}.location(
+                    object_fk_id,
+                    layer_fk_id,
+                    t,
+                    x,
+                    y,
+                    z,
+                    vx,
+                    vy,
+                    vz,
+                    vcx,
+                    vcy,
+                    vcz
+                ) FROM stdin WITH (FORCE_NULL(vcx, vcy, vcz), FORMAT CSV)"
+            ])
+            .map_err(map_err!(Error::Storage::Location))?;
+
+        let mut writer = csv::WriterBuilder::new()
+            .quote_style(csv::QuoteStyle::NonNumeric)
+            .from_writer(writer);
+
+        for location_info in locations {
+            writer.serialize(location_info).map_err(Error::SerializeCSV)?;
+        }
+
+        writer
+            .into_inner()
+            .map_err(|err| Error::WriterCSV(err.to_string()))?
+            .finish()
+            .map(|_| {})
+            .map_err(map_err!(Error::Storage::Location))
+    }
+
+    fn get_min_valid_start_time(
+        &mut self,
+        layer_id: LayerId,
+        requested_time: chrono::Duration,
+    ) -> Result<chrono::Duration> {
+        self.client
+            .query_one(
+                query![self.schema => "SELECT {schema_name}.min_valid_start_time($1, $2)"],
+                &[&layer_id, &requested_time.into_storage_duration()],
+            )
+            .map(|row| {
+                let time: RawTime = row.get(0);
+
+                time.into_rust_duration()
+            })
+            .map_err(map_err!(Error::Storage::Location))
+    }
+
+    fn get_keyframe_time(
+        &mut self,
+        layer_id: LayerId,
+        requested_time: chrono::Duration,
+    ) -> Result<chrono::Duration> {
+        self.client
+            .query_one(
+                query![self.schema => "SELECT {schema_name}.keyframe_time($1, $2)"],
+                &[&layer_id, &requested_time.into_storage_duration()],
+            )
+            .map(|row| {
+                let time: RawTime = row.get(0);
+
+                time.into_rust_duration()
+            })
+            .map_err(map_err!(Error::Storage::Location))
+    }
+
+    fn compact(&mut self, session_id: SessionId, keyframe_step: chrono::Duration) -> Result<i64> {
+        self.client
+            .query_one(
+                query![self.schema => "SELECT {schema_name}.compact_locations($1, $2)"],
+                &[&session_id, &keyframe_step.into_storage_duration()],
+            )
+            .map(|row| row.get(0))
+            .map_err(map_err!(Error::Storage::Location))
+    }
+
+    fn get_range_locations(
+        &mut self,
+        layer_id: LayerId,
+        time_range: &TimeRange,
+        step_coeff: i16,
+    ) -> Result<Vec<LocationInfo>> {
+        let reader = self
+            .client
+            .copy_out(query![
+                self.schema => "COPY
+                    (
+                        SELECT
+                            out_location_id,
+                            out_object_fk_id,
+                            out_t,
+                            out_x,
+                            out_y,
+                            out_z,
+                            out_vx,
+                            out_vy,
+                            out_vz,
+
+                            out_vcx,
+                            out_vcy,
+                            out_vcz
+                        FROM
+                            {schema_name}.range_locations({layer_id}, {start_time}, {stop_time}, {step_coeff})
+                    )
+                TO stdout WITH (FORMAT CSV)",
+                layer_id = layer_id,
+                start_time = time_range.start().into_storage_duration(),
+                stop_time = time_range.end().into_storage_duration(),
+                step_coeff = step_coeff
+            ])
+            .map_err(map_err!(Error::Storage::Location))?;
+
+        csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(reader)
+            .deserialize()
+            .map(|result| result.map_err(Error::SerializeCSV))
+            .collect()
+    }
+
+    fn get_object_locations_around(
+        &mut self,
+        layer_id: LayerId,
+        object_id: ObjectId,
+        requested_time: chrono::Duration,
+    ) -> Result<Vec<(GenCoord, Option<Vector>)>> {
+        let rows = self
+            .client
+            .query(
+                query! {self.schema => "
+                    SELECT
+                        out_t,
+                        out_x, out_y, out_z,
+                        out_vx, out_vy, out_vz,
+                        out_vcx, out_vcy, out_vcz
+                    FROM
+                        {schema_name}.range_locations($1, $2, $2, 0)
+                    WHERE
+                        out_object_fk_id = $3
+                    ORDER BY out_t
+                "},
+                &[&layer_id, &requested_time.into_storage_duration(), &object_id],
+            )
+            .map_err(map_err!(Error::Storage::Location))?;
+
+        let locations = rows
+            .into_iter()
+            .map(|row| {
+                let t: RawTime = row.get(0);
+                let location = Vector::new(row.get(1), row.get(2), row.get(3));
+                let velocity = Vector::new(row.get(4), row.get(5), row.get(6));
+
+                let vcx: Option<Coord> = row.get(7);
+                let vcy: Option<Coord> = row.get(8);
+                let vcz: Option<Coord> = row.get(9);
+
+                let collision_velocity = match (vcx, vcy, vcz) {
+                    (Some(vcx), Some(vcy), Some(vcz)) => Some(Vector::new(vcx, vcy, vcz)),
+                    _ => None,
+                };
+
+                (
+                    GenCoord::new(t.into_rust_duration(), location, velocity),
+                    collision_velocity,
+                )
+            })
+            .collect();
+
+        Ok(locations)
+    }
+}
@@ -0,0 +1,164 @@
+use {
+    super::PostgresBackend,
+    crate::{make_error, map_err, query, Result},
+};
+
+pub type SchemaVersion = i32;
+
+pub struct Migration {
+    pub version: SchemaVersion,
+    pub name: &'static str,
+    sql: &'static str,
+}
+
+/// The migrations ordered by version. A migration must never be changed once released,
+/// the schema changes go into a new one.
+///
+/// Only the tables live here. The functions and the triggers are recreated on every start,
+/// see `PostgresBackend::setup_routines`.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "baseline",
+        sql: include_str!("sql/migrations/0001_baseline.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "bookmark",
+        sql: include_str!("sql/migrations/0002_bookmark.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "object_override",
+        sql: include_str!("sql/migrations/0003_object_override.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "journal",
+        sql: include_str!("sql/migrations/0004_journal.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "override_start_time",
+        sql: include_str!("sql/migrations/0005_override_start_time.sql"),
+    },
+];
+
+pub fn latest_version() -> SchemaVersion {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+pub struct AppliedMigration {
+    pub version: SchemaVersion,
+    pub name: String,
+    pub applied_at: chrono::DateTime<chrono::Local>,
+}
+
+pub struct MigrationStatus {
+    pub applied: Vec<AppliedMigration>,
+    pub pending: Vec<&'static Migration>,
+}
+
+impl MigrationStatus {
+    pub fn current_version(&self) -> SchemaVersion {
+        self.applied.last().map_or(0, |migration| migration.version)
+    }
+
+    /// The database was migrated by a newer version of the app.
+    pub fn is_newer(&self) -> bool {
+        self.current_version() > latest_version()
+    }
+}
+
+impl PostgresBackend {
+    pub(super) fn migration_status(
+        psql: &mut impl postgres::GenericClient,
+        schema: &str,
+    ) -> Result<MigrationStatus> {
+        let applied: Vec<AppliedMigration> = psql
+            .query(
+                query! {schema => "
+                    SELECT version, migration_name, applied_at
+                    FROM {schema_name}.schema_version
+                    ORDER BY version
+                "},
+                &[],
+            )
+            .map_err(map_err!(Error::Storage::SetupSchema))?
+            .into_iter()
+            .map(|row| AppliedMigration {
+                version: row.get(0),
+                name: row.get(1),
+                applied_at: row.get(2),
+            })
+            .collect();
+
+        let pending = MIGRATIONS
+            .iter()
+            .filter(|migration| {
+                applied
+                    .iter()
+                    .all(|applied| applied.version != migration.version)
+            })
+            .collect();
+
+        Ok(MigrationStatus { applied, pending })
+    }
+
+    /// Applies all the pending migrations in a single transaction.
+    /// Returns the applied ones.
+    pub(super) fn migrate_up(
+        psql: &mut postgres::Client,
+        schema: &str,
+    ) -> Result<Vec<&'static Migration>> {
+        let mut transaction = psql
+            .transaction()
+            .map_err(map_err!(Error::Storage::Transaction))?;
+
+        // Concurrent migrations wait for each other and then find nothing to apply.
+        transaction
+            .batch_execute(query![
+                schema => "LOCK TABLE {schema_name}.schema_version IN EXCLUSIVE MODE"
+            ])
+            .map_err(map_err!(Error::Storage::SetupSchema))?;
+
+        let status = Self::migration_status(&mut transaction, schema)?;
+        Self::check_not_newer(&status)?;
+
+        for migration in status.pending.iter() {
+            // The migrations aren't format strings, so the schema name is substituted directly.
+            transaction
+                .batch_execute(migration.sql.replace("{schema_name}", schema).as_str())
+                .map_err(map_err!(Error::Storage::SetupSchema))?;
+
+            transaction
+                .execute(
+                    query! {schema => "
+                        INSERT INTO {schema_name}.schema_version(version, migration_name)
+                        VALUES($1, $2)
+                    "},
+                    &[&migration.version, &migration.name],
+                )
+                .map_err(map_err!(Error::Storage::SetupSchema))?;
+        }
+
+        transaction
+            .commit()
+            .map_err(map_err!(Error::Storage::Transaction))?;
+
+        Ok(status.pending)
+    }
+
+    pub(super) fn check_not_newer(status: &MigrationStatus) -> Result<()> {
+        if status.is_newer() {
+            return Err(make_error![Error::Storage::SchemaVersion(format!(
+                "the database schema version {} is newer than the latest supported one ({})\n\
+                HINT: update the app",
+                status.current_version(),
+                latest_version()
+            ))]);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,225 @@
+use {
+    super::{
+        BookmarkApi, IsolationLevel, JournalApi, LayerApi, LocationApi, ObjectApi, SessionApi,
+        StorageBackend, StorageEvent, StorageTransaction,
+    },
+    crate::{config::Config, make_error, map_err, query, Result},
+    postgres::NoTls,
+    r2d2_postgres::PostgresConnectionManager,
+    std::sync::{mpsc, Arc},
+};
+
+mod bookmark;
+mod journal;
+mod layer;
+mod listener;
+mod location;
+pub mod migration;
+mod object;
+mod session;
+
+use migration::{Migration, MigrationStatus};
+
+type ConnectionPool = r2d2::Pool<PostgresConnectionManager<NoTls>>;
+type PooledConnection = r2d2::PooledConnection<PostgresConnectionManager<NoTls>>;
+
+pub struct PostgresBackend {
+    pool: ConnectionPool,
+    schema: Arc<str>,
+
+    /// Used to open the connections outside the pool, see `PostgresBackend::listen`.
+    connection_config: postgres::Config,
+}
+
+impl PostgresBackend {
+    /// Connects to the database and brings its schema to the latest version.
+    ///
+    /// The pending migrations are applied automatically, including the databases
+    /// created before the versioned migrations. Only a newer schema is refused.
+    pub fn setup(config: &Config) -> Result<Self> {
+        let backend = Self::connect(config)?;
+        {
+            let mut client = backend.pool.get()?;
+            let schema = backend.schema.as_ref();
+
+            Self::setup_version_table(&mut client, schema)?;
+
+            let status = Self::migration_status(&mut *client, schema)?;
+            Self::check_not_newer(&status)?;
+
+            if !status.pending.is_empty() {
+                Self::migrate_up(&mut client, schema)?;
+            }
+
+            Self::setup_routines(&mut client, schema, config.session_max_hang_time)?;
+        }
+
+        Ok(backend)
+    }
+
+    /// Connects to the database without touching its schema besides the version table.
+    pub fn connect(config: &Config) -> Result<Self> {
+        // The connection string isn't reported since it may contain a password.
+        let connection_config: postgres::Config = config.db_url.parse().map_err(|err| {
+            make_error![Error::Storage::Connection(format!(
+                "invalid connection string: {}",
+                err
+            ))]
+        })?;
+
+        let mgr = PostgresConnectionManager::new(connection_config.clone(), NoTls);
+
+        let connection_timeout = config.connection_timeout.to_std().map_err(|_| {
+            make_error![Error::Config("the connection timeout must be positive".into())]
+        })?;
+
+        let pool = r2d2::Pool::builder()
+            .max_size(config.pool_size)
+            .connection_timeout(connection_timeout)
+            .build(mgr)
+            .map_err(|err| make_error![Error::Storage::Connection(err.to_string())])?;
+
+        Ok(Self {
+            pool,
+            schema: config.schema_name.as_str().into(),
+            connection_config,
+        })
+    }
+
+    pub fn get_migration_status(&self) -> Result<MigrationStatus> {
+        let mut client = self.pool.get()?;
+        Self::setup_version_table(&mut client, &self.schema)?;
+
+        Self::migration_status(&mut *client, &self.schema)
+    }
+
+    pub fn apply_migrations(&self) -> Result<Vec<&'static Migration>> {
+        let mut client = self.pool.get()?;
+        Self::setup_version_table(&mut client, &self.schema)?;
+
+        Self::migrate_up(&mut client, &self.schema)
+    }
+
+    fn setup_version_table(psql: &mut postgres::Client, schema: &str) -> Result<()> {
+        psql.batch_execute(query![schema => include_str!("sql/setup/schema.sql")])
+            .map_err(|err| make_error![Error::Storage::SetupSchema(err)])
+    }
+
+    /// (Re)creates the functions, the procedures and the triggers.
+    /// Unlike the tables, they are always replaced with the ones of the running app.
+    fn setup_routines(
+        psql: &mut postgres::Client,
+        schema: &str,
+        session_max_hang_time: chrono::Duration,
+    ) -> Result<()> {
+        let setup_query = format! {
+            r#"
+                {session}
+                {layer}
+                {object}
+                {location}
+                {object_override}
+                {bookmark}
+                {journal}
+                {session_triggers}
+                {layer_triggers}
+                {location_triggers}
+                {notify_triggers}
+            "#,
+            session = query! {
+                schema => include_str!("sql/setup/session.sql"),
+                session_max_hang_time = session_max_hang_time.num_seconds()
+            },
+            layer = query![schema => include_str!["sql/setup/layer.sql"]],
+            object = query![schema => include_str!["sql/setup/object.sql"]],
+            location = query![schema => include_str!["sql/setup/location.sql"]],
+            object_override = query![schema => include_str!["sql/setup/object_override.sql"]],
+            bookmark = query![schema => include_str!["sql/setup/bookmark.sql"]],
+            journal = query![schema => include_str!["sql/setup/journal.sql"]],
+            session_triggers = query![schema => include_str!("sql/setup/session_triggers.sql")],
+            layer_triggers = query![schema => include_str!("sql/setup/layer_triggers.sql")],
+            location_triggers = query![schema => include_str!("sql/setup/location_triggers.sql")],
+            notify_triggers = query![schema => include_str!("sql/setup/notify_triggers.sql")],
+        };
+
+        psql.batch_execute(setup_query.as_str())
+            .map_err(|err| make_error![Error::Storage::SetupSchema(err)])
+    }
+}
+
+impl StorageBackend for PostgresBackend {
+    fn transaction(&self, isolation_level: IsolationLevel) -> Result<Box<dyn StorageTransaction>> {
+        let mut client = self.pool.get()?;
+
+        let begin = match isolation_level {
+            IsolationLevel::ReadCommitted => "BEGIN ISOLATION LEVEL READ COMMITTED",
+            IsolationLevel::RepeatableRead => "BEGIN ISOLATION LEVEL REPEATABLE READ",
+        };
+
+        client
+            .batch_execute(begin)
+            .map_err(map_err!(Error::Storage::Transaction))?;
+
+        Ok(Box::new(PostgresTransaction {
+            client,
+            schema: self.schema.clone(),
+            is_committed: false,
+        }))
+    }
+
+    fn subscribe(&self) -> Result<Option<mpsc::Receiver<StorageEvent>>> {
+        self.listen().map(Some)
+    }
+}
+
+/// The transaction owns its pooled connection,
+/// so it is controlled by the plain `BEGIN`/`COMMIT`/`ROLLBACK` statements.
+///
+/// The transaction implements all the storage APIs, see the submodules.
+pub struct PostgresTransaction {
+    client: PooledConnection,
+    schema: Arc<str>,
+    is_committed: bool,
+}
+
+impl StorageTransaction for PostgresTransaction {
+    fn session(&mut self) -> &mut dyn SessionApi {
+        self
+    }
+
+    fn layer(&mut self) -> &mut dyn LayerApi {
+        self
+    }
+
+    fn object(&mut self) -> &mut dyn ObjectApi {
+        self
+    }
+
+    fn location(&mut self) -> &mut dyn LocationApi {
+        self
+    }
+
+    fn bookmark(&mut self) -> &mut dyn BookmarkApi {
+        self
+    }
+
+    fn journal(&mut self) -> &mut dyn JournalApi {
+        self
+    }
+
+    fn commit(mut self: Box<Self>) -> Result<()> {
+        self.client.batch_execute("COMMIT")?;
+        self.is_committed = true;
+
+        Ok(())
+    }
+}
+
+impl Drop for PostgresTransaction {
+    fn drop(&mut self) {
+        if !self.is_committed {
+            // The connection goes back to the pool, so it must not stay inside the transaction.
+            let _ = self.client.batch_execute("ROLLBACK");
+        }
+    }
+}
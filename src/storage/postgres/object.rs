@@ -0,0 +1,193 @@
+use super::PostgresTransaction;
+use crate::{
+    engine::context::db_util::{MassOverrideInfo, ObjectInfo},
+    graphics, map_err, object, query,
+    r#type::{IntoStorageDuration, LayerId, Mass, ObjectId, ObjectName, SessionId},
+    storage::ObjectApi,
+    Error, Result,
+};
+use itertools::Itertools;
+
+impl ObjectApi for PostgresTransaction {
+    fn add(
+        &mut self,
+        session_id: SessionId,
+        layer_id: LayerId,
+        object: object::Object,
+    ) -> Result<ObjectId> {
+        self.client
+            .query_one(
+                query! {self.schema => "
+                    SELECT {schema_name}.add_object(
+                        $1,
+                        $2,
+                        $3,
+                        $4,
+                        $5,
+                        $6,
+                        $7
+                    )
+                "},
+                &[
+                    &session_id,
+                    &layer_id,
+                    object.name(),
+                    &object.radius(),
+                    &graphics::pack_color(object.color()),
+                    &object.mass(),
+                    &object.compute_step().into_storage_duration(),
+                ],
+            )
+            .map(|row| row.get(0))
+            .map_err(map_err!(Error::Storage::Object))
+    }
+
+    fn set_override(
+        &mut self,
+        layer_id: LayerId,
+        object_id: ObjectId,
+        start_time: Option<chrono::Duration>,
+        mass: Option<Mass>,
+        removed_time: Option<chrono::Duration>,
+    ) -> Result<()> {
+        self.client
+            .execute(
+                query![self.schema => "CALL {schema_name}.set_object_override($1, $2, $3, $4, $5)"],
+                &[
+                    &layer_id,
+                    &object_id,
+                    &start_time.map(|time| time.into_storage_duration()),
+                    &mass,
+                    &removed_time.map(|time| time.into_storage_duration()),
+                ],
+            )
+            .map(|_| {})
+            .map_err(map_err!(Error::Storage::Object))
+    }
+
+    fn get_overridden(&mut self, active_layer_id: LayerId) -> Result<Vec<(LayerId, ObjectId)>> {
+        let rows = self
+            .client
+            .query(
+                query![self.schema => "SELECT * FROM {schema_name}.overridden_objects($1)"],
+                &[&active_layer_id],
+            )
+            .map_err(map_err!(Error::Storage::Object))?;
+
+        Ok(rows.into_iter().map(|row| (row.get(0), row.get(1))).collect())
+    }
+
+    fn is_object_exists(
+        &mut self,
+        session_id: SessionId,
+        object_name: &ObjectName,
+    ) -> Result<bool> {
+        self.client
+            .query_one(
+                query![self.schema => "SELECT {schema_name}.is_object_exists($1, $2)"],
+                &[&session_id, object_name],
+            )
+            .map(|row| row.get(0))
+            .map_err(map_err!(Error::Storage::Object))
+    }
+
+    fn get_max_object_default_name_num(&mut self, session_id: SessionId) -> Result<i64> {
+        self.client
+            .query_one(
+                query![self.schema => "SELECT {schema_name}.last_object_default_name_num($1)"],
+                &[&session_id]
+            )
+            .map(|row| row.get(0))
+            .map_err(map_err!(Error::Storage::Object))
+    }
+
+    fn get_objects_delta(
+        &mut self,
+        active_layer_id: LayerId,
+        known_objects_ids: &[ObjectId],
+    ) -> Result<Vec<ObjectInfo>> {
+        let reader = self
+            .client
+            .copy_out(query![
+                self.schema => "COPY (
+                    SELECT * FROM {schema_name}.current_objects_delta(
+                        {layer_id},
+                        ARRAY[{known_objects_ids}]::bigint[]
+                    )
+                ) TO stdout WITH (FORMAT CSV)",
+                layer_id = active_layer_id,
+                known_objects_ids = known_objects_ids.iter().join(",")
+            ])
+            .map_err(map_err!(Error::Storage::Object))?;
+
+        csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(reader)
+            .deserialize()
+            .map(|result| result.map_err(Error::SerializeCSV))
+            .collect()
+    }
+
+    fn get_mass_overrides(&mut self, active_layer_id: LayerId) -> Result<Vec<MassOverrideInfo>> {
+        let reader = self
+            .client
+            .copy_out(query![
+                "COPY (
+                    SELECT * FROM {schema_name}.object_mass_overrides({layer_id})
+                ) TO stdout WITH (FORMAT CSV)",
+                layer_id = active_layer_id
+            ])
+            .map_err(map_err!(Error::Storage::Object))?;
+
+        csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(reader)
+            .deserialize()
+            .map(|result| result.map_err(Error::SerializeCSV))
+            .collect()
+    }
+
+    // pub fn get_last_object_id(&mut self, session_id: SessionId) -> Result<ObjectId> {
+    //     self.client
+    //         .query_one(
+    //             query![self.schema => "SELECT {schema_name}.last_object_id($1)"],
+    //             &[&session_id]
+    //         )
+    //         .map(|row| row.get(0))
+    //         .map_err(map_err!(Error::Storage::Object))
+    // }
+
+    // pub fn rename(&mut self, session_id: SessionId, object_id: ObjectId, new_name: &str) -> Result<()> {
+    //     self.manager
+    //         .pool
+    //         .get()?
+    //         .execute(
+    //             query![self.schema => "CALL {schema_name}.rename_object($1, $2, $3)"],
+    //             &[&session_id, &object_id, &new_name],
+    //         ).map(|_| {})
+    //         .map_err(map_err!(Error::Storage::RenameObject))
+    // }
+
+    // pub fn print_list(&mut self, session_id: SessionId) -> Result<()> {
+    //     let set = self
+    //         .manager
+    //         .pool
+    //         .get()?
+    //         .query(
+    //             query! {self.schema => "
+    //                 SELECT object_name
+    //                 FROM {schema_name}.object
+    //                 WHERE session_fk_id = $1
+    //                 ORDER BY object_name
+    //             "},
+    //             &[&session_id]
+    //         ).map_err(map_err![Error::Storage::ObjectList])?;
+
+    //     for row in set {
+    //         let name: &str = row.get(0);
+    //         println!("\t{}", name);
+    //     }
+
+    //     Ok(())
+    // }
+}
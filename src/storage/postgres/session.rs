@@ -0,0 +1,309 @@
+use super::PostgresTransaction;
+use crate::{
+    map_err, query,
+    r#type::{Coord, LayerId, SessionId, SessionInfo, SessionName},
+    storage::{
+        dump::{BookmarkDump, LayerDump, LocationDump, ObjectDump, OverrideDump, SessionDump},
+        SessionApi,
+    },
+    Result,
+};
+
+impl PostgresTransaction {
+    fn session_name(&mut self, id: SessionId) -> Result<Option<SessionName>> {
+        self.client
+            .query_one(query![self.schema => "SELECT {schema_name}.get_session_name($1)"], &[&id])
+            .map(|row| row.get(0))
+            .map_err(map_err![Error::Storage::Session])
+    }
+}
+
+impl SessionApi for PostgresTransaction {
+    fn new(&mut self, name: Option<SessionName>) -> Result<(SessionId, LayerId)> {
+        let row = self
+            .client
+            .query_one(
+                query![self.schema => "SELECT {schema_name}.create_new_session($1)"],
+                &[&name],
+            )
+            .map_err(map_err!(Error::Storage::Session))?;
+
+        let session_id: SessionId = row.get(0);
+        let row = self
+            .client
+            .query_one(
+                query!(self.schema => "SELECT {schema_name}.main_layer_id($1)"),
+                &[&session_id],
+            )
+            .map_err(map_err!(Error::Storage::Session))?;
+
+        let layer_id = row.get(0);
+
+        Ok((session_id, layer_id))
+    }
+
+    fn update_access_time(&mut self, id: SessionId) -> Result<()> {
+        self.client
+            .execute(
+                query![self.schema => "CALL {schema_name}.update_session_access_time($1)"],
+                &[&id],
+            )
+            .map_err(map_err![Error::Storage::Session])?;
+
+        Ok(())
+    }
+
+    fn unlock(&mut self, id: SessionId) -> Result<()> {
+        self.client
+            .execute(query![self.schema => "CALL {schema_name}.unlock_session($1)"], &[&id])
+            .map(|_| {})
+            .map_err(map_err![Error::Storage::Session])
+    }
+
+    fn save(&mut self, id: SessionId, name: &str) -> Result<()> {
+        self.client
+            .execute(
+                query![self.schema => "CALL {schema_name}.save_session($1, $2)"],
+                &[&id, &name],
+            )
+            .map(|_| {})
+            .map_err(map_err!(Error::Storage::Session))
+    }
+
+    fn load(&mut self, name: &str) -> Result<(SessionId, LayerId)> {
+        let row = self
+            .client
+            .query_one(query![self.schema => "SELECT {schema_name}.load_session($1)"], &[&name])
+            .map_err(map_err![Error::Storage::Session])?;
+
+        let session_id: SessionId = row.get(0);
+
+        let row = self
+            .client
+            .query_one(
+                query!(self.schema => "SELECT {schema_name}.main_layer_id($1)"),
+                &[&session_id],
+            )
+            .map_err(map_err!(Error::Storage::Session))?;
+
+        let layer_id: LayerId = row.get(0);
+
+        Ok((session_id, layer_id))
+    }
+
+    fn attach(&mut self, name: &str) -> Result<(SessionId, LayerId)> {
+        let row = self
+            .client
+            .query_one(
+                query![self.schema => "SELECT * FROM {schema_name}.attached_session_id($1)"],
+                &[&name],
+            )
+            .map_err(map_err![Error::Storage::Session])?;
+
+        Ok((row.get(0), row.get(1)))
+    }
+
+    fn get_unlocked_id(&mut self, name: &str, current_id: SessionId) -> Result<SessionId> {
+        self.client
+            .query_one(
+                query![self.schema => "SELECT {schema_name}.unlocked_session_id($1, $2)"],
+                &[&name, &current_id],
+            )
+            .map(|row| row.get(0))
+            .map_err(map_err![Error::Storage::Session])
+    }
+
+    fn rename(&mut self, old_name: &str, new_name: &str) -> Result<()> {
+        self.client
+            .execute(
+                query![self.schema => "CALL {schema_name}.rename_session($1, $2)"],
+                &[&old_name, &new_name],
+            )
+            .map(|_| {})
+            .map_err(map_err!(Error::Storage::Session))
+    }
+
+    fn get_list(&mut self) -> Result<Vec<SessionInfo>> {
+        let row = self
+            .client
+            .query(
+                query! {self.schema => "
+                    SELECT session_name, last_access, is_locked
+                    FROM {schema_name}.session
+                    WHERE session_name IS NOT NULL
+                    ORDER BY session_name
+                "},
+                &[],
+            )
+            .map_err(map_err![Error::Storage::Session])?;
+
+        let infos = row
+            .into_iter()
+            .map(|row| SessionInfo {
+                name: row.get(0),
+                last_access: row.get(1),
+                is_locked: row.get(2),
+            })
+            .collect();
+
+        Ok(infos)
+    }
+
+    fn get_name(&mut self, id: SessionId) -> Result<SessionName> {
+        self.session_name(id)
+            .map(|name| name.unwrap_or("/unnamed/".into()))
+    }
+
+    fn dump(&mut self, id: SessionId, with_computed: bool) -> Result<SessionDump> {
+        let mut dump = SessionDump::new(self.session_name(id)?);
+
+        dump.layers = self
+            .client
+            .query(
+                query! {self.schema => "
+                    SELECT layer_id, parent_layer_id, layer_name, start_time
+                    FROM {schema_name}.layer
+                    LEFT JOIN {schema_name}.layer_family
+                        ON child_layer_id = layer_id
+                    WHERE session_fk_id = $1
+                    ORDER BY layer_id
+                "},
+                &[&id],
+            )
+            .map_err(map_err!(Error::Storage::Session))?
+            .into_iter()
+            .map(|row| LayerDump {
+                id: row.get(0),
+                parent_id: row.get(1),
+                name: row.get(2),
+                start_time: row.get(3),
+            })
+            .collect();
+
+        dump.objects = self
+            .client
+            .query(
+                query! {self.schema => "
+                    SELECT object_id, layer_fk_id, object_name, radius, color, mass, compute_step
+                    FROM {schema_name}.object
+                    WHERE session_fk_id = $1
+                    ORDER BY object_id
+                "},
+                &[&id],
+            )
+            .map_err(map_err!(Error::Storage::Session))?
+            .into_iter()
+            .map(|row| ObjectDump {
+                id: row.get(0),
+                layer_id: row.get(1),
+                name: row.get(2),
+                radius: row.get(3),
+                color: row.get(4),
+                mass: row.get(5),
+                compute_step: row.get(6),
+            })
+            .collect();
+
+        dump.overrides = self
+            .client
+            .query(
+                query! {self.schema => "
+                    SELECT
+                        object_override.layer_fk_id,
+                        object_fk_id,
+                        object_override.start_time,
+                        object_override.mass,
+                        removed_time
+                    FROM {schema_name}.object_override
+                    INNER JOIN {schema_name}.layer
+                        ON object_override.layer_fk_id = layer_id
+                    WHERE session_fk_id = $1
+                    ORDER BY object_override.layer_fk_id, object_fk_id, object_override.start_time
+                "},
+                &[&id],
+            )
+            .map_err(map_err!(Error::Storage::Session))?
+            .into_iter()
+            .map(|row| OverrideDump {
+                layer_id: row.get(0),
+                object_id: row.get(1),
+                start_time: row.get(2),
+                mass: row.get(3),
+                removed_time: row.get(4),
+            })
+            .collect();
+
+        dump.bookmarks = self
+            .client
+            .query(
+                query! {self.schema => "
+                    SELECT layer_fk_id, bookmark_name, t
+                    FROM {schema_name}.bookmark
+                    WHERE session_fk_id = $1
+                    ORDER BY t, bookmark_name
+                "},
+                &[&id],
+            )
+            .map_err(map_err!(Error::Storage::Session))?
+            .into_iter()
+            .map(|row| BookmarkDump {
+                layer_id: row.get(0),
+                name: row.get(1),
+                t: row.get(2),
+            })
+            .collect();
+
+        // The initial location is the earliest one of the object in the layer.
+        dump.locations = self
+            .client
+            .query(
+                query! {self.schema => "
+                    SELECT object_fk_id, layer_fk_id, t, x, y, z, vx, vy, vz, vcx, vcy, vcz
+                    FROM (
+                        SELECT
+                            location.*,
+                            ROW_NUMBER() OVER (
+                                PARTITION BY object_fk_id, layer_fk_id
+                                ORDER BY t, location_id
+                            ) AS location_rank
+                        FROM {schema_name}.location
+                        INNER JOIN {schema_name}.layer
+                            ON layer_fk_id = layer_id
+                        WHERE session_fk_id = $1
+                    ) AS session_location
+                    WHERE $2 OR location_rank = 1
+                    ORDER BY location_id
+                "},
+                &[&id, &with_computed],
+            )
+            .map_err(map_err!(Error::Storage::Session))?
+            .into_iter()
+            .map(|row| {
+                let vc = |idx: usize| -> Option<Coord> {
+                    if with_computed {
+                        row.get(idx)
+                    } else {
+                        None
+                    }
+                };
+
+                LocationDump {
+                    object_id: row.get(0),
+                    layer_id: row.get(1),
+                    t: row.get(2),
+                    x: row.get(3),
+                    y: row.get(4),
+                    z: row.get(5),
+                    vx: row.get(6),
+                    vy: row.get(7),
+                    vz: row.get(8),
+                    vcx: vc(9),
+                    vcy: vc(10),
+                    vcz: vc(11),
+                }
+            })
+            .collect();
+
+        Ok(dump)
+    }
+}
@@ -0,0 +1,91 @@
+-- The tables which existed before the versioned migrations were introduced.
+-- `IF NOT EXISTS` lets this migration adopt such databases as they are.
+
+CREATE TABLE IF NOT EXISTS {schema_name}.session
+(
+	session_id serial PRIMARY KEY,
+	session_name varchar(50) UNIQUE,
+	last_access timestamptz NOT NULL,
+	is_locked boolean NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS {schema_name}.layer
+(
+    layer_id serial PRIMARY KEY,
+    session_fk_id serial
+        NOT NULL
+        REFERENCES {schema_name}.session(session_id)
+        ON DELETE CASCADE,
+    layer_name varchar(50) NOT NULL,
+    start_time bigint NOT NULL,
+
+    UNIQUE(session_fk_id, layer_name)
+);
+
+CREATE TABLE IF NOT EXISTS {schema_name}.layer_family
+(
+    parent_layer_id serial
+        NOT NULL
+        REFERENCES {schema_name}.layer(layer_id)
+        ON DELETE CASCADE,
+    child_layer_id serial
+        NOT NULL
+        REFERENCES {schema_name}.layer(layer_id)
+        ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS {schema_name}.object
+(
+    object_id bigserial PRIMARY KEY,
+    session_fk_id serial
+        NOT NULL
+        REFERENCES {schema_name}.session(session_id)
+        ON DELETE CASCADE,
+    layer_fk_id serial
+        NOT NULL
+        REFERENCES {schema_name}.layer(layer_id)
+        ON DELETE CASCADE,
+    object_name varchar(50) NOT NULL,
+    radius real NOT NULL,
+    color integer NOT NULL,
+    mass real NOT NULL,
+    compute_step bigint NOT NULL,
+
+    UNIQUE (session_fk_id, object_name)
+);
+
+CREATE TABLE IF NOT EXISTS {schema_name}.location
+(
+    location_id bigserial PRIMARY KEY,
+    object_fk_id bigserial
+        NOT NULL
+        REFERENCES {schema_name}.object
+        ON DELETE CASCADE,
+    layer_fk_id serial
+        NOT NULL
+        REFERENCES {schema_name}.layer
+        ON DELETE CASCADE,
+    t bigint NOT NULL,
+    x real NOT NULL,
+    y real NOT NULL,
+    z real NOT NULL,
+    vx real NOT NULL,
+    vy real NOT NULL,
+    vz real NOT NULL,
+
+    vcx real NULL, -- vx after collision
+    vcy real NULL, -- vy after collision
+    vcz real NULL  -- vz after collision
+);
+
+CREATE TABLE IF NOT EXISTS {schema_name}.collision_partners
+(
+    location_fk_id bigserial
+        NOT NULL
+        REFERENCES {schema_name}.location
+        ON DELETE CASCADE,
+    partner_fk_id bigserial
+        NOT NULL
+        REFERENCES {schema_name}.location
+        ON DELETE CASCADE
+);
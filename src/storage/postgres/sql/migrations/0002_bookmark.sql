@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS {schema_name}.bookmark
+(
+    bookmark_id serial PRIMARY KEY,
+    session_fk_id serial
+        NOT NULL
+        REFERENCES {schema_name}.session(session_id)
+        ON DELETE CASCADE,
+    layer_fk_id serial
+        NOT NULL
+        REFERENCES {schema_name}.layer(layer_id)
+        ON DELETE CASCADE,
+    bookmark_name varchar(50) NOT NULL,
+    t bigint NOT NULL,
+
+    UNIQUE (session_fk_id, bookmark_name)
+);
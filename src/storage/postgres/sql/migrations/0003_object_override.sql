@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS {schema_name}.object_override
+(
+    layer_fk_id serial
+        NOT NULL
+        REFERENCES {schema_name}.layer(layer_id)
+        ON DELETE CASCADE,
+    object_fk_id bigserial
+        NOT NULL
+        REFERENCES {schema_name}.object(object_id)
+        ON DELETE CASCADE,
+    mass real NULL,
+    removed_time bigint NULL,
+
+    PRIMARY KEY (layer_fk_id, object_fk_id)
+);
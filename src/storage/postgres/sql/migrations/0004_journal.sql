@@ -0,0 +1,64 @@
+CREATE TABLE IF NOT EXISTS {schema_name}.journal
+(
+    journal_id serial PRIMARY KEY,
+    session_fk_id serial
+        NOT NULL
+        REFERENCES {schema_name}.session(session_id)
+        ON DELETE CASCADE,
+    operation varchar(20) NOT NULL, -- 'rm-layer', 'rename-layer' or 'delete-session'
+    target_id integer NOT NULL, -- the layer's or the session's id
+    old_name varchar(50) NULL,
+    new_name varchar(50) NULL,
+    is_undone boolean NOT NULL DEFAULT false,
+    record_time timestamptz NOT NULL DEFAULT now()
+);
+
+-- Tombstones keep the deleted rows until the journal record is purged.
+
+CREATE TABLE IF NOT EXISTS {schema_name}.tomb_session
+(
+    journal_fk_id integer NOT NULL REFERENCES {schema_name}.journal ON DELETE CASCADE,
+    LIKE {schema_name}.session
+);
+
+CREATE TABLE IF NOT EXISTS {schema_name}.tomb_layer
+(
+    journal_fk_id integer NOT NULL REFERENCES {schema_name}.journal ON DELETE CASCADE,
+    LIKE {schema_name}.layer
+);
+
+CREATE TABLE IF NOT EXISTS {schema_name}.tomb_layer_family
+(
+    journal_fk_id integer NOT NULL REFERENCES {schema_name}.journal ON DELETE CASCADE,
+    LIKE {schema_name}.layer_family
+);
+
+CREATE TABLE IF NOT EXISTS {schema_name}.tomb_object
+(
+    journal_fk_id integer NOT NULL REFERENCES {schema_name}.journal ON DELETE CASCADE,
+    LIKE {schema_name}.object
+);
+
+CREATE TABLE IF NOT EXISTS {schema_name}.tomb_location
+(
+    journal_fk_id integer NOT NULL REFERENCES {schema_name}.journal ON DELETE CASCADE,
+    LIKE {schema_name}.location
+);
+
+CREATE TABLE IF NOT EXISTS {schema_name}.tomb_collision_partners
+(
+    journal_fk_id integer NOT NULL REFERENCES {schema_name}.journal ON DELETE CASCADE,
+    LIKE {schema_name}.collision_partners
+);
+
+CREATE TABLE IF NOT EXISTS {schema_name}.tomb_object_override
+(
+    journal_fk_id integer NOT NULL REFERENCES {schema_name}.journal ON DELETE CASCADE,
+    LIKE {schema_name}.object_override
+);
+
+CREATE TABLE IF NOT EXISTS {schema_name}.tomb_bookmark
+(
+    journal_fk_id integer NOT NULL REFERENCES {schema_name}.journal ON DELETE CASCADE,
+    LIKE {schema_name}.bookmark
+);
@@ -0,0 +1,50 @@
+-- An override keeps the start time of the layer it was created in, so that merging the
+-- layer into its parent doesn't move the override to the parent's start.
+
+ALTER TABLE {schema_name}.object_override
+    ADD COLUMN IF NOT EXISTS start_time bigint NULL;
+
+UPDATE {schema_name}.object_override AS o
+SET
+    start_time=l.start_time
+FROM
+    {schema_name}.layer AS l
+WHERE
+    o.start_time IS NULL
+    AND l.layer_id=o.layer_fk_id;
+
+ALTER TABLE {schema_name}.object_override
+    ALTER COLUMN start_time SET NOT NULL;
+
+ALTER TABLE {schema_name}.object_override
+    DROP CONSTRAINT IF EXISTS object_override_pkey;
+
+ALTER TABLE {schema_name}.object_override
+    ADD PRIMARY KEY (layer_fk_id, object_fk_id, start_time);
+
+ALTER TABLE {schema_name}.tomb_object_override
+    ADD COLUMN IF NOT EXISTS start_time bigint NULL;
+
+UPDATE {schema_name}.tomb_object_override AS o
+SET
+    start_time=COALESCE(
+        (
+            SELECT
+                tl.start_time
+            FROM
+                {schema_name}.tomb_layer AS tl
+            WHERE
+                tl.journal_fk_id=o.journal_fk_id
+                AND tl.layer_id=o.layer_fk_id
+        ),
+        (
+            SELECT
+                l.start_time
+            FROM
+                {schema_name}.layer AS l
+            WHERE
+                l.layer_id=o.layer_fk_id
+        )
+    )
+WHERE
+    o.start_time IS NULL;
@@ -0,0 +1,46 @@
+CREATE OR REPLACE PROCEDURE {schema_name}.add_bookmark(
+    session_id integer,
+    layer_id integer,
+    name varchar(50),
+    time bigint
+) AS $$
+    BEGIN
+        INSERT INTO {schema_name}.bookmark
+        VALUES(DEFAULT, session_id, layer_id, name, time);
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE PROCEDURE {schema_name}.remove_bookmark(
+    session_id integer,
+    name varchar(50)
+) AS $$
+    BEGIN
+        DELETE FROM {schema_name}.bookmark
+        WHERE session_fk_id = session_id AND bookmark_name = name;
+
+        IF (NOT FOUND) THEN
+            RAISE 'bookmark `%` not found', name;
+        END IF;
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION {schema_name}.session_bookmarks(
+    in_session_id integer
+) RETURNS TABLE(
+    out_bookmark_name varchar(50),
+    out_layer_name varchar(50),
+    out_t bigint
+) AS $$
+    BEGIN
+        RETURN QUERY
+        SELECT
+            bookmark_name,
+            layer_name,
+            t
+        FROM {schema_name}.bookmark
+        INNER JOIN {schema_name}.layer
+            ON layer_fk_id = layer.layer_id
+        WHERE bookmark.session_fk_id = in_session_id
+        ORDER BY t, bookmark_name;
+    END
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,391 @@
+CREATE OR REPLACE PROCEDURE {schema_name}.bury_layers(
+    jid integer,
+    layer_ids integer[]
+) AS $$
+    BEGIN
+        INSERT INTO {schema_name}.tomb_layer(
+            journal_fk_id, layer_id, session_fk_id, layer_name, start_time
+        )
+        SELECT jid, layer_id, session_fk_id, layer_name, start_time
+        FROM {schema_name}.layer l
+        WHERE layer_id = ANY(layer_ids);
+
+        INSERT INTO {schema_name}.tomb_layer_family(
+            journal_fk_id, parent_layer_id, child_layer_id
+        )
+        SELECT jid, parent_layer_id, child_layer_id
+        FROM {schema_name}.layer_family f
+        WHERE child_layer_id = ANY(layer_ids);
+
+        INSERT INTO {schema_name}.tomb_object(
+            journal_fk_id,
+            object_id,
+            session_fk_id,
+            layer_fk_id,
+            object_name,
+            radius,
+            color,
+            mass,
+            compute_step
+        )
+        SELECT
+            jid,
+            object_id,
+            session_fk_id,
+            layer_fk_id,
+            object_name,
+            radius,
+            color,
+            mass,
+            compute_step
+        FROM {schema_name}.object o
+        WHERE layer_fk_id = ANY(layer_ids);
+
+        INSERT INTO {schema_name}.tomb_location(
+            journal_fk_id,
+            location_id,
+            object_fk_id,
+            layer_fk_id,
+            t, x, y, z, vx, vy, vz, vcx, vcy, vcz
+        )
+        SELECT
+            jid,
+            location_id,
+            object_fk_id,
+            layer_fk_id,
+            t, x, y, z, vx, vy, vz, vcx, vcy, vcz
+        FROM {schema_name}.location l
+        WHERE
+            layer_fk_id = ANY(layer_ids)
+            OR object_fk_id IN (
+                SELECT object_id
+                FROM {schema_name}.tomb_object
+                WHERE journal_fk_id = jid
+            );
+
+        INSERT INTO {schema_name}.tomb_collision_partners(
+            journal_fk_id, location_fk_id, partner_fk_id
+        )
+        SELECT jid, location_fk_id, partner_fk_id
+        FROM {schema_name}.collision_partners c
+        WHERE
+            location_fk_id IN (
+                SELECT location_id
+                FROM {schema_name}.tomb_location
+                WHERE journal_fk_id = jid
+            )
+            OR partner_fk_id IN (
+                SELECT location_id
+                FROM {schema_name}.tomb_location
+                WHERE journal_fk_id = jid
+            );
+
+        INSERT INTO {schema_name}.tomb_object_override(
+            journal_fk_id, layer_fk_id, object_fk_id, start_time, mass, removed_time
+        )
+        SELECT jid, layer_fk_id, object_fk_id, start_time, mass, removed_time
+        FROM {schema_name}.object_override o
+        WHERE
+            layer_fk_id = ANY(layer_ids)
+            OR object_fk_id IN (
+                SELECT object_id
+                FROM {schema_name}.tomb_object
+                WHERE journal_fk_id = jid
+            );
+
+        INSERT INTO {schema_name}.tomb_bookmark(
+            journal_fk_id, bookmark_id, session_fk_id, layer_fk_id, bookmark_name, t
+        )
+        SELECT jid, bookmark_id, session_fk_id, layer_fk_id, bookmark_name, t
+        FROM {schema_name}.bookmark b
+        WHERE layer_fk_id = ANY(layer_ids);
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE PROCEDURE {schema_name}.bury_layer(
+    jid integer,
+    in_layer_id integer
+) AS $$
+    BEGIN
+        CALL {schema_name}.bury_layers(
+            jid,
+            ARRAY(SELECT layer_id FROM {schema_name}.layer_subtree(in_layer_id))
+        );
+
+        -- The descendants are removed by the layer_family trigger.
+        DELETE FROM {schema_name}.layer
+        WHERE layer_id = in_layer_id;
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE PROCEDURE {schema_name}.bury_session(
+    jid integer,
+    in_session_id integer
+) AS $$
+    BEGIN
+        INSERT INTO {schema_name}.tomb_session(
+            journal_fk_id, session_id, session_name, last_access, is_locked
+        )
+        SELECT jid, session_id, session_name, last_access, is_locked
+        FROM {schema_name}.session s
+        WHERE session_id = in_session_id AND is_locked = false;
+
+        IF (NOT FOUND) THEN
+            RAISE 'session `%` is either locked or not exists',
+                {schema_name}.get_session_name(in_session_id);
+        END IF;
+
+        CALL {schema_name}.bury_layers(
+            jid,
+            ARRAY(
+                SELECT layer_id
+                FROM {schema_name}.layer
+                WHERE session_fk_id = in_session_id
+            )
+        );
+
+        DELETE FROM {schema_name}.session
+        WHERE session_id = in_session_id;
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE PROCEDURE {schema_name}.unbury(
+    jid integer
+) AS $$
+    BEGIN
+        INSERT INTO {schema_name}.session
+        SELECT session_id, session_name, last_access, is_locked
+        FROM {schema_name}.tomb_session
+        WHERE journal_fk_id = jid;
+
+        INSERT INTO {schema_name}.layer
+        SELECT layer_id, session_fk_id, layer_name, start_time
+        FROM {schema_name}.tomb_layer
+        WHERE journal_fk_id = jid;
+
+        INSERT INTO {schema_name}.layer_family
+        SELECT parent_layer_id, child_layer_id
+        FROM {schema_name}.tomb_layer_family
+        WHERE journal_fk_id = jid;
+
+        INSERT INTO {schema_name}.object
+        SELECT
+            object_id,
+            session_fk_id,
+            layer_fk_id,
+            object_name,
+            radius,
+            color,
+            mass,
+            compute_step
+        FROM {schema_name}.tomb_object
+        WHERE journal_fk_id = jid;
+
+        INSERT INTO {schema_name}.location
+        SELECT
+            location_id,
+            object_fk_id,
+            layer_fk_id,
+            t, x, y, z, vx, vy, vz, vcx, vcy, vcz
+        FROM {schema_name}.tomb_location
+        WHERE journal_fk_id = jid;
+
+        INSERT INTO {schema_name}.collision_partners
+        SELECT location_fk_id, partner_fk_id
+        FROM {schema_name}.tomb_collision_partners
+        WHERE journal_fk_id = jid;
+
+        INSERT INTO {schema_name}.object_override(
+            layer_fk_id, object_fk_id, start_time, mass, removed_time
+        )
+        SELECT layer_fk_id, object_fk_id, start_time, mass, removed_time
+        FROM {schema_name}.tomb_object_override
+        WHERE journal_fk_id = jid;
+
+        INSERT INTO {schema_name}.bookmark
+        SELECT bookmark_id, session_fk_id, layer_fk_id, bookmark_name, t
+        FROM {schema_name}.tomb_bookmark
+        WHERE journal_fk_id = jid;
+
+        DELETE FROM {schema_name}.tomb_session WHERE journal_fk_id = jid;
+        DELETE FROM {schema_name}.tomb_layer WHERE journal_fk_id = jid;
+        DELETE FROM {schema_name}.tomb_layer_family WHERE journal_fk_id = jid;
+        DELETE FROM {schema_name}.tomb_object WHERE journal_fk_id = jid;
+        DELETE FROM {schema_name}.tomb_location WHERE journal_fk_id = jid;
+        DELETE FROM {schema_name}.tomb_collision_partners WHERE journal_fk_id = jid;
+        DELETE FROM {schema_name}.tomb_object_override WHERE journal_fk_id = jid;
+        DELETE FROM {schema_name}.tomb_bookmark WHERE journal_fk_id = jid;
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION {schema_name}.journal_record(
+    session_id integer,
+    in_operation varchar(20),
+    in_target_id integer,
+    in_old_name varchar(50),
+    in_new_name varchar(50)
+) RETURNS integer
+AS $$
+    DECLARE
+        jid integer;
+    BEGIN
+        -- A new operation makes the undone ones unreachable for redo.
+        DELETE FROM {schema_name}.journal
+        WHERE session_fk_id = session_id AND is_undone = true;
+
+        INSERT INTO {schema_name}.journal
+        VALUES(DEFAULT, session_id, in_operation, in_target_id, in_old_name, in_new_name)
+        RETURNING journal_id INTO jid;
+
+        RETURN jid;
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE PROCEDURE {schema_name}.journal_remove_layer(
+    session_id integer,
+    in_layer_id integer
+) AS $$
+    DECLARE
+        jid integer;
+    BEGIN
+        jid := {schema_name}.journal_record(
+            session_id,
+            'rm-layer',
+            in_layer_id,
+            {schema_name}.layer_name(in_layer_id),
+            NULL
+        );
+
+        CALL {schema_name}.bury_layer(jid, in_layer_id);
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE PROCEDURE {schema_name}.journal_rename_layer(
+    session_id integer,
+    in_layer_id integer,
+    new_layer_name varchar(50)
+) AS $$
+    BEGIN
+        PERFORM {schema_name}.journal_record(
+            session_id,
+            'rename-layer',
+            in_layer_id,
+            {schema_name}.layer_name(in_layer_id),
+            new_layer_name
+        );
+
+        CALL {schema_name}.rename_layer(in_layer_id, new_layer_name);
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE PROCEDURE {schema_name}.journal_delete_session(
+    session_id integer,
+    name varchar(50)
+) AS $$
+    DECLARE
+        deleted_session_id integer;
+        jid integer;
+    BEGIN
+        SELECT s.session_id
+        FROM {schema_name}.session s
+        WHERE s.session_name = name AND s.is_locked = false
+        INTO deleted_session_id;
+
+        IF (NOT FOUND) THEN
+            RAISE 'session `%` is either locked or not exists', name;
+        END IF;
+
+        jid := {schema_name}.journal_record(
+            session_id,
+            'delete-session',
+            deleted_session_id,
+            name,
+            NULL
+        );
+
+        CALL {schema_name}.bury_session(jid, deleted_session_id);
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION {schema_name}.journal_undo(
+    session_id integer
+) RETURNS TABLE (
+    out_operation varchar(20),
+    out_target_id integer,
+    out_old_name varchar(50),
+    out_new_name varchar(50)
+) AS $$
+    DECLARE
+        entry {schema_name}.journal%ROWTYPE;
+    BEGIN
+        SELECT * FROM {schema_name}.journal j
+        WHERE j.session_fk_id = session_id AND j.is_undone = false
+        ORDER BY j.journal_id DESC
+        LIMIT 1
+        INTO entry;
+
+        IF (NOT FOUND) THEN
+            RETURN;
+        END IF;
+
+        CASE entry.operation
+            WHEN 'rename-layer' THEN
+                CALL {schema_name}.rename_layer(entry.target_id, entry.old_name);
+            ELSE
+                CALL {schema_name}.unbury(entry.journal_id);
+        END CASE;
+
+        UPDATE {schema_name}.journal
+        SET is_undone = true
+        WHERE journal_id = entry.journal_id;
+
+        RETURN QUERY SELECT entry.operation, entry.target_id, entry.old_name, entry.new_name;
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION {schema_name}.journal_redo(
+    session_id integer
+) RETURNS TABLE (
+    out_operation varchar(20),
+    out_target_id integer,
+    out_old_name varchar(50),
+    out_new_name varchar(50)
+) AS $$
+    DECLARE
+        entry {schema_name}.journal%ROWTYPE;
+    BEGIN
+        SELECT * FROM {schema_name}.journal j
+        WHERE j.session_fk_id = session_id AND j.is_undone = true
+        ORDER BY j.journal_id ASC
+        LIMIT 1
+        INTO entry;
+
+        IF (NOT FOUND) THEN
+            RETURN;
+        END IF;
+
+        CASE entry.operation
+            WHEN 'rm-layer' THEN
+                CALL {schema_name}.bury_layer(entry.journal_id, entry.target_id);
+            WHEN 'rename-layer' THEN
+                CALL {schema_name}.rename_layer(entry.target_id, entry.new_name);
+            WHEN 'delete-session' THEN
+                CALL {schema_name}.bury_session(entry.journal_id, entry.target_id);
+        END CASE;
+
+        UPDATE {schema_name}.journal
+        SET is_undone = false
+        WHERE journal_id = entry.journal_id;
+
+        RETURN QUERY SELECT entry.operation, entry.target_id, entry.old_name, entry.new_name;
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE PROCEDURE {schema_name}.purge_journal(
+    session_id integer
+) AS $$
+    BEGIN
+        DELETE FROM {schema_name}.journal j
+        WHERE session_id IS NULL OR j.session_fk_id = session_id;
+    END
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,447 @@
+CREATE OR REPLACE FUNCTION {schema_name}.main_layer_id(
+    session_id integer
+) RETURNS integer
+AS $$
+    BEGIN
+        RETURN (
+            SELECT MIN(layer_id)
+            FROM {schema_name}.layer
+            WHERE session_fk_id=session_id
+        );
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION {schema_name}.layer_id(
+    session_id integer,
+    in_layer_name varchar(50)
+) RETURNS integer
+AS $$
+    BEGIN
+        RETURN (
+            SELECT layer_id FROM {schema_name}.layer
+            WHERE session_fk_id = session_id AND layer_name = in_layer_name
+        );
+    END
+$$ LANGUAGE plpgsql;
+
+-- CREATE OR REPLACE FUNCTION {schema_name}.layer_object_ids(
+--     layer_id integer
+-- ) RETURNS integer[]
+-- AS $$
+--     BEGIN
+--         RETURN (
+--                 SELECT
+--                     array_agg(object_id::integer ORDER BY object_id)
+--                 FROM {schema_name}.object
+--                 WHERE
+--                     layer_fk_id = layer_id
+--         );
+--     END
+-- $$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION {schema_name}.add_layer(
+    session_id integer,
+    active_layer_id integer,
+    layer_name varchar(50),
+    start_time bigint
+) RETURNS integer
+AS $$
+    DECLARE
+        new_layer_id integer;
+    BEGIN
+        INSERT INTO {schema_name}.layer
+        VALUES(DEFAULT, session_id, layer_name, start_time)
+        RETURNING layer_id INTO new_layer_id;
+
+        INSERT INTO {schema_name}.layer_family
+        VALUES(
+            (SELECT {schema_name}.current_layer_id(active_layer_id, start_time)),
+            new_layer_id
+        );
+
+        RETURN new_layer_id;
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE PROCEDURE {schema_name}.remove_layer(
+    in_layer_id integer
+)
+AS $$
+    BEGIN
+        DELETE FROM {schema_name}.layer
+        WHERE layer_id=in_layer_id;
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE PROCEDURE {schema_name}.rename_layer(
+    in_layer_id integer,
+    new_layer_name varchar(50)
+)
+AS $$
+    BEGIN
+        UPDATE {schema_name}.layer
+        SET layer_name = new_layer_name
+        WHERE layer_id = in_layer_id;
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION {schema_name}.layer_children(
+    session_id integer,
+    in_layer_id integer
+) RETURNS integer[]
+AS $$
+    BEGIN
+        RETURN (
+            SELECT
+                array_agg(layer_id::integer ORDER BY start_time)
+            FROM {schema_name}.layer
+            INNER JOIN {schema_name}.layer_family f
+                ON f.parent_layer_id = in_layer_id
+            WHERE
+                layer_id = f.child_layer_id
+        );
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION {schema_name}.layer_name(
+    in_layer_id integer
+) RETURNS varchar(50)
+AS $$
+    BEGIN
+        RETURN (
+            SELECT
+                layer_name
+            FROM
+                {schema_name}.layer
+            WHERE
+                layer_id=in_layer_id
+        );
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION {schema_name}.layer_start_time(
+    in_layer_id integer
+) RETURNS bigint
+AS $$
+    BEGIN
+        RETURN (
+            SELECT
+                start_time
+            FROM
+                {schema_name}.layer
+            WHERE
+                layer_id=in_layer_id
+        );
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION {schema_name}.current_layer_id(
+    active_layer_id integer,
+    current_vtime bigint
+) RETURNS integer
+AS $$
+    BEGIN
+        RETURN (
+            SELECT
+                layer_id
+            FROM {schema_name}.query_layers_info(
+                active_layer_id,
+                current_vtime,
+                current_vtime
+            )
+            WHERE
+                current_vtime BETWEEN layer_start_time AND layer_stop_time
+            ORDER BY layer_depth ASC
+            LIMIT 1
+        );
+    END
+$$ LANGUAGE plpgsql;
+
+-- The depth is 0 for the layer itself and grows towards the main layer.
+DROP FUNCTION IF EXISTS {schema_name}.layer_ancestors(integer);
+
+CREATE OR REPLACE FUNCTION {schema_name}.layer_ancestors(
+    in_layer_id integer
+) RETURNS TABLE (
+    layer_id integer,
+    start_time bigint,
+    depth integer
+) AS $$
+#variable_conflict use_column
+    BEGIN
+        RETURN QUERY
+            WITH RECURSIVE cte_layer AS (
+                    SELECT
+                        layer_id,
+                        start_time,
+                        0 AS depth
+                    FROM
+                        {schema_name}.layer
+                    WHERE
+                        layer_id = in_layer_id
+                    UNION
+                        SELECT
+                            l.layer_id,
+                            l.start_time,
+                            accum.depth + 1
+                        FROM
+                            {schema_name}.layer l
+                        INNER JOIN {schema_name}.layer_family f
+                            ON f.parent_layer_id = l.layer_id
+                        INNER JOIN cte_layer accum
+                            ON accum.layer_id = f.child_layer_id
+            ) SELECT * FROM cte_layer;
+    END
+$$ LANGUAGE plpgsql;
+
+-- Each ancestor lasts until its child starts.
+DROP FUNCTION IF EXISTS {schema_name}.query_layers_info(integer, bigint, bigint);
+
+CREATE OR REPLACE FUNCTION {schema_name}.query_layers_info(
+    active_layer_id integer,
+    in_start_time bigint,
+    in_stop_time bigint
+) RETURNS TABLE (
+    layer_id integer,
+    layer_start_time bigint,
+    layer_stop_time bigint,
+    layer_depth integer
+) AS $$
+#variable_conflict use_column
+    BEGIN
+        RETURN QUERY
+            SELECT
+                layer_id,
+                GREATEST(start_time, in_start_time) AS start_time,
+                LEAST(lead(start_time) OVER (ORDER BY depth DESC), in_stop_time) AS stop_time,
+                depth
+            FROM (
+                SELECT * FROM {schema_name}.layer_ancestors(active_layer_id)
+            ) as ancestors;
+    END
+$$ LANGUAGE plpgsql;
+CREATE OR REPLACE FUNCTION {schema_name}.layer_parent_id(
+    in_layer_id integer
+) RETURNS integer
+AS $$
+    BEGIN
+        RETURN (
+            SELECT parent_layer_id
+            FROM {schema_name}.layer_family
+            WHERE child_layer_id = in_layer_id
+        );
+    END
+$$ LANGUAGE plpgsql;
+
+-- The parent's locations replaced by the merge of the layer:
+-- the ones from the child's first location of the object, and for the objects
+-- without locations in the child, the ones after the child's start time.
+-- The first location of an object is always kept, the track is recomputed from it.
+CREATE OR REPLACE FUNCTION {schema_name}.merge_replaced_locations(
+    in_layer_id integer
+) RETURNS TABLE (
+    out_location_id bigint,
+    out_t bigint
+) AS $$
+    BEGIN
+        RETURN QUERY
+        SELECT parent_location.location_id, parent_location.t
+        FROM {schema_name}.location parent_location
+        INNER JOIN (
+            SELECT object_fk_id, MIN(t) AS first_time
+            FROM {schema_name}.location
+            WHERE layer_fk_id = {schema_name}.layer_parent_id(in_layer_id)
+            GROUP BY object_fk_id
+        ) parent_objects
+            ON parent_location.object_fk_id = parent_objects.object_fk_id
+        LEFT OUTER JOIN (
+            SELECT object_fk_id, MIN(t) AS branch_time
+            FROM {schema_name}.location
+            WHERE layer_fk_id = in_layer_id
+            GROUP BY object_fk_id
+        ) child_objects
+            ON parent_location.object_fk_id = child_objects.object_fk_id
+        WHERE
+            parent_location.layer_fk_id = {schema_name}.layer_parent_id(in_layer_id)
+            AND (
+                parent_location.t >= child_objects.branch_time
+                OR (
+                    child_objects.branch_time IS NULL
+                    AND parent_location.t > GREATEST(
+                        {schema_name}.layer_start_time(in_layer_id),
+                        parent_objects.first_time
+                    )
+                )
+            );
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION {schema_name}.merge_replaced_locations_count(
+    in_layer_id integer
+) RETURNS bigint
+AS $$
+    BEGIN
+        RETURN (
+            SELECT COUNT(*)
+            FROM {schema_name}.merge_replaced_locations(in_layer_id)
+        );
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE PROCEDURE {schema_name}.merge_layer(
+    in_layer_id integer
+)
+AS $$
+    DECLARE
+        parent_id integer;
+    BEGIN
+        parent_id := {schema_name}.layer_parent_id(in_layer_id);
+
+        IF parent_id IS NULL THEN
+            RAISE 'layer `%` has no parent', {schema_name}.layer_name(in_layer_id);
+        END IF;
+
+        DELETE FROM {schema_name}.location
+        WHERE location_id IN (
+            SELECT out_location_id
+            FROM {schema_name}.merge_replaced_locations(in_layer_id)
+        );
+
+        UPDATE {schema_name}.location
+        SET layer_fk_id = parent_id
+        WHERE layer_fk_id = in_layer_id;
+
+        UPDATE {schema_name}.object
+        SET layer_fk_id = parent_id
+        WHERE layer_fk_id = in_layer_id;
+
+        -- The overrides keep their start time, they still apply from the child's start.
+        INSERT INTO {schema_name}.object_override AS o
+            (layer_fk_id, object_fk_id, start_time, mass, removed_time)
+        SELECT parent_id, object_fk_id, start_time, mass, removed_time
+        FROM {schema_name}.object_override
+        WHERE layer_fk_id = in_layer_id
+        ON CONFLICT (layer_fk_id, object_fk_id, start_time) DO UPDATE
+        SET
+            mass = COALESCE(EXCLUDED.mass, o.mass),
+            removed_time = LEAST(EXCLUDED.removed_time, o.removed_time);
+
+        UPDATE {schema_name}.bookmark
+        SET layer_fk_id = parent_id
+        WHERE layer_fk_id = in_layer_id;
+
+        UPDATE {schema_name}.layer_family
+        SET parent_layer_id = parent_id
+        WHERE parent_layer_id = in_layer_id;
+
+        -- The child layer is removed by the layer_family trigger.
+        DELETE FROM {schema_name}.layer_family
+        WHERE child_layer_id = in_layer_id;
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION {schema_name}.layer_subtree(
+    in_layer_id integer
+) RETURNS TABLE (
+    layer_id integer,
+    parent_layer_id integer
+) AS $$
+#variable_conflict use_column
+    BEGIN
+        RETURN QUERY
+            WITH RECURSIVE cte_layer AS (
+                    SELECT
+                        in_layer_id AS layer_id,
+                        NULL::integer AS parent_layer_id
+                    UNION
+                        SELECT
+                            f.child_layer_id,
+                            f.parent_layer_id
+                        FROM
+                            {schema_name}.layer_family f
+                        INNER JOIN cte_layer accum
+                            ON f.parent_layer_id = accum.layer_id
+            ) SELECT * FROM cte_layer ORDER BY layer_id;
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION {schema_name}.unique_layer_name(
+    session_id integer,
+    base_name varchar(50)
+) RETURNS varchar(50)
+AS $$
+    DECLARE
+        new_name varchar(50) := base_name;
+        suffix_num integer := 0;
+    BEGIN
+        WHILE EXISTS(
+            SELECT 1 FROM {schema_name}.layer
+            WHERE session_fk_id = session_id AND layer_name = new_name
+        ) LOOP
+            suffix_num := suffix_num + 1;
+            new_name := LEFT(base_name, 49 - length(suffix_num::text)) || '-' || suffix_num;
+        END LOOP;
+
+        RETURN new_name;
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION {schema_name}.copy_layer_row(
+    src_layer_id integer,
+    dst_session_id integer,
+    dst_parent_layer_id integer
+) RETURNS integer
+AS $$
+    DECLARE
+        new_layer_id integer;
+    BEGIN
+        INSERT INTO {schema_name}.layer(session_fk_id, layer_name, start_time)
+        SELECT
+            dst_session_id,
+            {schema_name}.unique_layer_name(dst_session_id, layer_name),
+            start_time
+        FROM {schema_name}.layer
+        WHERE layer_id = src_layer_id
+        RETURNING layer_id INTO new_layer_id;
+
+        INSERT INTO {schema_name}.layer_family
+        VALUES(dst_parent_layer_id, new_layer_id);
+
+        RETURN new_layer_id;
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION {schema_name}.session_layers_info(
+    in_session_id integer
+) RETURNS TABLE (
+    out_layer_id integer,
+    out_parent_layer_id integer,
+    out_layer_name varchar(50),
+    out_start_time bigint,
+    out_objects_count bigint,
+    out_last_computed_time bigint
+) AS $$
+    BEGIN
+        RETURN QUERY
+        SELECT
+            l.layer_id,
+            f.parent_layer_id,
+            l.layer_name,
+            l.start_time,
+            (
+                SELECT COUNT(*)
+                FROM {schema_name}.object
+                WHERE object.layer_fk_id = l.layer_id
+            ),
+            (
+                SELECT MAX(t)
+                FROM {schema_name}.location
+                WHERE location.layer_fk_id = l.layer_id
+            )
+        FROM {schema_name}.layer l
+        LEFT OUTER JOIN {schema_name}.layer_family f
+            ON f.child_layer_id = l.layer_id
+        WHERE l.session_fk_id = in_session_id
+        ORDER BY l.layer_id;
+    END
+$$ LANGUAGE plpgsql;
@@ -1,39 +1,3 @@
-CREATE TABLE IF NOT EXISTS {schema_name}.location
-(
-    location_id bigserial PRIMARY KEY,
-    object_fk_id bigserial
-        NOT NULL
-        REFERENCES {schema_name}.object
-        ON DELETE CASCADE,
-    layer_fk_id serial
-        NOT NULL
-        REFERENCES {schema_name}.layer
-        ON DELETE CASCADE,
-    t bigint NOT NULL,
-    x real NOT NULL,
-    y real NOT NULL,
-    z real NOT NULL,
-    vx real NOT NULL,
-    vy real NOT NULL,
-    vz real NOT NULL,
-
-    vcx real NULL, -- vx after collision
-    vcy real NULL, -- vy after collision
-    vcz real NULL  -- vz after collision
-);
-
-CREATE TABLE IF NOT EXISTS {schema_name}.collision_partners
-(
-    location_fk_id bigserial
-        NOT NULL
-        REFERENCES {schema_name}.location
-        ON DELETE CASCADE,
-    partner_fk_id bigserial
-        NOT NULL
-        REFERENCES {schema_name}.location
-        ON DELETE CASCADE
-);
-
 CREATE OR REPLACE PROCEDURE {schema_name}.add_location(
     object_id bigint,
     layer_id integer,
@@ -115,6 +79,7 @@ CREATE OR REPLACE FUNCTION {schema_name}.query_object_layers_info(
     BEGIN
         SELECT compute_step
         FROM {schema_name}.object
+        WHERE object.object_id = query_object_layers_info.object_id
         INTO right_step;
 
         IF step_coeff = 0 THEN
@@ -128,15 +93,15 @@ CREATE OR REPLACE FUNCTION {schema_name}.query_object_layers_info(
         SELECT
             temp_layer_id as layer_id,
             GREATEST(temp_start_time, in_start_time + left_step) AS start_time,
-            LEAST(lead(temp_start_time) OVER (ORDER BY temp_layer_id ASC), in_stop_time + right_step) AS stop_time
+            LEAST(lead(temp_start_time) OVER (ORDER BY temp_depth DESC), in_stop_time + right_step) AS stop_time
         FROM (
             SELECT
-                layer_fk_id as temp_layer_id, MIN(t) as temp_start_time
+                layer_fk_id as temp_layer_id, ancestors.depth as temp_depth, MIN(t) as temp_start_time
             FROM {schema_name}.location
             INNER JOIN {schema_name}.layer_ancestors(active_layer_id) ancestors
                 ON layer_fk_id = ancestors.layer_id
             WHERE object_fk_id = object_id
-            GROUP BY layer_fk_id
+            GROUP BY layer_fk_id, ancestors.depth
         ) as start_times;
     END
 $$ LANGUAGE plpgsql;
@@ -187,6 +152,114 @@ AS $$
         --     GROUP BY location_fk_id
         -- ) c_partners
         --     ON c_partners.location_fk_id = location_id
+        WHERE
+            t <= COALESCE({schema_name}.object_removed_time(active_layer_id, object_fk_id), t)
         ORDER BY object_fk_id, t, location_id DESC;
     END
-$$ LANGUAGE plpgsql;
\ No newline at end of file
+$$ LANGUAGE plpgsql;
+CREATE OR REPLACE FUNCTION {schema_name}.copy_locations(
+    src_object_id bigint,
+    src_layer_id integer,
+    dst_object_id bigint,
+    dst_layer_id integer,
+    with_computed boolean
+) RETURNS bigint
+AS $$
+    DECLARE
+        copied_count bigint;
+    BEGIN
+        -- Without the computed locations only the initial one is copied.
+        INSERT INTO {schema_name}.location(
+            object_fk_id, layer_fk_id, t, x, y, z, vx, vy, vz, vcx, vcy, vcz
+        )
+        SELECT
+            dst_object_id,
+            dst_layer_id,
+            t, x, y, z, vx, vy, vz,
+            CASE WHEN with_computed THEN vcx END,
+            CASE WHEN with_computed THEN vcy END,
+            CASE WHEN with_computed THEN vcz END
+        FROM {schema_name}.location
+        WHERE
+            object_fk_id = src_object_id
+            AND layer_fk_id = src_layer_id
+            AND (with_computed OR t = (
+                SELECT MIN(t)
+                FROM {schema_name}.location
+                WHERE object_fk_id = src_object_id AND layer_fk_id = src_layer_id
+            ))
+        ORDER BY t, location_id;
+
+        GET DIAGNOSTICS copied_count = ROW_COUNT;
+
+        RETURN copied_count;
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION {schema_name}.keyframe_time(
+    active_layer_id integer,
+    requested_time bigint
+) RETURNS bigint
+AS $$
+    BEGIN
+        -- The object's track is continuous around the requested time
+        -- unless the previous location is farther than the compute step.
+        -- The ancestors' locations are skipped: their gaps are recomputed only in their own layer.
+        RETURN (
+            SELECT COALESCE(MIN(prev_t), requested_time)
+            FROM (
+                SELECT
+                    MAX(t) FILTER (WHERE t <= requested_time) AS prev_t,
+                    MAX(t) AS max_t,
+                    o.compute_step
+                FROM {schema_name}.location
+                INNER JOIN {schema_name}.object o
+                    ON object_fk_id = o.object_id
+                INNER JOIN {schema_name}.layer_ancestors(active_layer_id) ancestors
+                    ON o.layer_fk_id = ancestors.layer_id
+                WHERE location.layer_fk_id = active_layer_id
+                GROUP BY object_fk_id, o.compute_step
+            ) AS objects_times
+            WHERE max_t > requested_time AND requested_time - prev_t > compute_step
+        );
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION {schema_name}.compact_locations(
+    in_session_id integer,
+    keyframe_step bigint
+) RETURNS bigint
+AS $$
+    DECLARE
+        removed_count bigint;
+    BEGIN
+        -- The initial locations, the collision points and the latest location
+        -- of each keyframe interval are kept.
+        DELETE FROM {schema_name}.location
+        WHERE location_id IN (
+            SELECT location_id
+            FROM (
+                SELECT
+                    location_id,
+                    vcx,
+                    ROW_NUMBER() OVER (
+                        PARTITION BY object_fk_id, layer_fk_id
+                        ORDER BY t, location_id
+                    ) AS initial_rank,
+                    ROW_NUMBER() OVER (
+                        PARTITION BY object_fk_id, layer_fk_id, t / keyframe_step
+                        ORDER BY t DESC, location_id DESC
+                    ) AS keyframe_rank
+                FROM {schema_name}.location
+                INNER JOIN {schema_name}.layer
+                    ON layer_fk_id = layer.layer_id
+                WHERE layer.session_fk_id = in_session_id
+            ) AS ranked
+            WHERE initial_rank > 1 AND keyframe_rank > 1 AND vcx IS NULL
+        );
+
+        GET DIAGNOSTICS removed_count = ROW_COUNT;
+
+        RETURN removed_count;
+    END
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,159 @@
+-- The changes are sent to the `{schema_name}_changes` channel, so the applications
+-- sharing a session can refresh it. The payload is `<kind>:<session_id>[:<layer_id>]`.
+-- The triggers are statement-level, so a batch of rows produces a single notification
+-- per session (or per layer).
+
+CREATE OR REPLACE FUNCTION {schema_name}.notify_layers_changed()
+RETURNS trigger
+AS $$
+    BEGIN
+        PERFORM pg_notify('{schema_name}_changes', 'layers:' || session_fk_id)
+        FROM (SELECT DISTINCT session_fk_id FROM changed_layers) changed_sessions;
+
+        RETURN NULL;
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION {schema_name}.notify_locations_added()
+RETURNS trigger
+AS $$
+    BEGIN
+        PERFORM pg_notify(
+            '{schema_name}_changes',
+            'locations:' || session_fk_id || ':' || layer_id
+        )
+        FROM (
+            SELECT DISTINCT l.session_fk_id, l.layer_id
+            FROM added_locations
+            INNER JOIN {schema_name}.layer l
+                ON layer_fk_id = l.layer_id
+        ) changed_layers;
+
+        RETURN NULL;
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION {schema_name}.notify_bookmarks_changed()
+RETURNS trigger
+AS $$
+    BEGIN
+        PERFORM pg_notify('{schema_name}_changes', 'bookmarks:' || session_fk_id)
+        FROM (SELECT DISTINCT session_fk_id FROM changed_bookmarks) changed_sessions;
+
+        RETURN NULL;
+    END
+$$ LANGUAGE plpgsql;
+
+DO
+$$
+BEGIN
+	IF NOT EXISTS(
+		SELECT * FROM information_schema.triggers
+		WHERE event_object_schema = '{schema_name}'
+		AND event_object_table = 'layer'
+		AND trigger_name = 'trigger_notify_layers_insert'
+	) THEN
+		CREATE TRIGGER trigger_notify_layers_insert
+		AFTER INSERT
+		    ON {schema_name}.layer
+		    REFERENCING NEW TABLE AS changed_layers
+            FOR EACH STATEMENT
+		EXECUTE FUNCTION {schema_name}.notify_layers_changed();
+	END IF;
+END
+$$;
+
+DO
+$$
+BEGIN
+	IF NOT EXISTS(
+		SELECT * FROM information_schema.triggers
+		WHERE event_object_schema = '{schema_name}'
+		AND event_object_table = 'layer'
+		AND trigger_name = 'trigger_notify_layers_update'
+	) THEN
+		CREATE TRIGGER trigger_notify_layers_update
+		AFTER UPDATE
+		    ON {schema_name}.layer
+		    REFERENCING NEW TABLE AS changed_layers
+            FOR EACH STATEMENT
+		EXECUTE FUNCTION {schema_name}.notify_layers_changed();
+	END IF;
+END
+$$;
+
+DO
+$$
+BEGIN
+	IF NOT EXISTS(
+		SELECT * FROM information_schema.triggers
+		WHERE event_object_schema = '{schema_name}'
+		AND event_object_table = 'layer'
+		AND trigger_name = 'trigger_notify_layers_delete'
+	) THEN
+		CREATE TRIGGER trigger_notify_layers_delete
+		AFTER DELETE
+		    ON {schema_name}.layer
+		    REFERENCING OLD TABLE AS changed_layers
+            FOR EACH STATEMENT
+		EXECUTE FUNCTION {schema_name}.notify_layers_changed();
+	END IF;
+END
+$$;
+
+DO
+$$
+BEGIN
+	IF NOT EXISTS(
+		SELECT * FROM information_schema.triggers
+		WHERE event_object_schema = '{schema_name}'
+		AND event_object_table = 'location'
+		AND trigger_name = 'trigger_notify_locations_insert'
+	) THEN
+		CREATE TRIGGER trigger_notify_locations_insert
+		AFTER INSERT
+		    ON {schema_name}.location
+		    REFERENCING NEW TABLE AS added_locations
+            FOR EACH STATEMENT
+		EXECUTE FUNCTION {schema_name}.notify_locations_added();
+	END IF;
+END
+$$;
+
+DO
+$$
+BEGIN
+	IF NOT EXISTS(
+		SELECT * FROM information_schema.triggers
+		WHERE event_object_schema = '{schema_name}'
+		AND event_object_table = 'bookmark'
+		AND trigger_name = 'trigger_notify_bookmarks_insert'
+	) THEN
+		CREATE TRIGGER trigger_notify_bookmarks_insert
+		AFTER INSERT
+		    ON {schema_name}.bookmark
+		    REFERENCING NEW TABLE AS changed_bookmarks
+            FOR EACH STATEMENT
+		EXECUTE FUNCTION {schema_name}.notify_bookmarks_changed();
+	END IF;
+END
+$$;
+
+DO
+$$
+BEGIN
+	IF NOT EXISTS(
+		SELECT * FROM information_schema.triggers
+		WHERE event_object_schema = '{schema_name}'
+		AND event_object_table = 'bookmark'
+		AND trigger_name = 'trigger_notify_bookmarks_delete'
+	) THEN
+		CREATE TRIGGER trigger_notify_bookmarks_delete
+		AFTER DELETE
+		    ON {schema_name}.bookmark
+		    REFERENCING OLD TABLE AS changed_bookmarks
+            FOR EACH STATEMENT
+		EXECUTE FUNCTION {schema_name}.notify_bookmarks_changed();
+	END IF;
+END
+$$;
@@ -1,23 +1,3 @@
-CREATE TABLE IF NOT EXISTS {schema_name}.object
-(
-    object_id bigserial PRIMARY KEY,
-    session_fk_id serial
-        NOT NULL
-        REFERENCES {schema_name}.session(session_id)
-        ON DELETE CASCADE,
-    layer_fk_id serial
-        NOT NULL
-        REFERENCES {schema_name}.layer(layer_id)
-        ON DELETE CASCADE,
-    object_name varchar(50) NOT NULL,
-    radius real NOT NULL,
-    color integer NOT NULL,
-    mass real NOT NULL,
-    compute_step bigint NOT NULL,
-
-    UNIQUE (session_fk_id, object_name)
-);
-
 CREATE OR REPLACE FUNCTION {schema_name}.add_object(
     session_id integer,
     layer_id integer,
@@ -87,6 +67,8 @@ AS $$
     END
 $$ LANGUAGE plpgsql;
 
+DROP FUNCTION IF EXISTS {schema_name}.current_objects_delta(integer, bigint[]);
+
 CREATE OR REPLACE FUNCTION {schema_name}.current_objects_delta(
     active_layer_id integer,
     known_objects_ids bigint[]
@@ -97,7 +79,8 @@ CREATE OR REPLACE FUNCTION {schema_name}.current_objects_delta(
     out_radius real,
     out_color integer,
     out_mass real,
-    out_compute_step bigint
+    out_compute_step bigint,
+    out_removed_time bigint
 ) AS $$
     BEGIN
         RETURN QUERY
@@ -114,7 +97,8 @@ CREATE OR REPLACE FUNCTION {schema_name}.current_objects_delta(
             radius,
             color,
             mass,
-            compute_step
+            compute_step,
+            {schema_name}.object_removed_time(active_layer_id, object_id)
         FROM {schema_name}.object
         WHERE object_id = ANY(
             SELECT
@@ -153,4 +137,55 @@ AS $$
             ) as str_name_nums
         );
     END
-$$ LANGUAGE plpgsql;
\ No newline at end of file
+$$ LANGUAGE plpgsql;
+CREATE OR REPLACE FUNCTION {schema_name}.unique_object_name(
+    session_id integer,
+    base_name varchar(50)
+) RETURNS varchar(50)
+AS $$
+    DECLARE
+        new_name varchar(50) := base_name;
+        suffix_num integer := 0;
+    BEGIN
+        WHILE {schema_name}.is_object_exists(session_id, new_name) LOOP
+            suffix_num := suffix_num + 1;
+            new_name := LEFT(base_name, 49 - length(suffix_num::text)) || '-' || suffix_num;
+        END LOOP;
+
+        RETURN new_name;
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION {schema_name}.copy_object(
+    src_object_id bigint,
+    dst_session_id integer,
+    dst_layer_id integer
+) RETURNS bigint
+AS $$
+    DECLARE
+        new_object_id bigint;
+    BEGIN
+        INSERT INTO {schema_name}.object(
+            session_fk_id,
+            layer_fk_id,
+            object_name,
+            radius,
+            color,
+            mass,
+            compute_step
+        )
+        SELECT
+            dst_session_id,
+            dst_layer_id,
+            {schema_name}.unique_object_name(dst_session_id, object_name),
+            radius,
+            color,
+            mass,
+            compute_step
+        FROM {schema_name}.object
+        WHERE object_id = src_object_id
+        RETURNING object_id INTO new_object_id;
+
+        RETURN new_object_id;
+    END
+$$ LANGUAGE plpgsql;
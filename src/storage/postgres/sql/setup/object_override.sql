@@ -0,0 +1,98 @@
+-- The override applies from `in_start_time`, the layer's start time by default.
+CREATE OR REPLACE PROCEDURE {schema_name}.set_object_override(
+    layer_id integer,
+    object_id bigint,
+    in_start_time bigint,
+    new_mass real,
+    new_removed_time bigint
+) AS $$
+    BEGIN
+        INSERT INTO {schema_name}.object_override AS o
+            (layer_fk_id, object_fk_id, start_time, mass, removed_time)
+        VALUES(
+            layer_id,
+            object_id,
+            COALESCE(in_start_time, {schema_name}.layer_start_time(layer_id)),
+            new_mass,
+            new_removed_time
+        )
+        ON CONFLICT (layer_fk_id, object_fk_id, start_time) DO UPDATE
+        SET
+            mass = COALESCE(EXCLUDED.mass, o.mass),
+            removed_time = COALESCE(EXCLUDED.removed_time, o.removed_time);
+    END
+$$ LANGUAGE plpgsql;
+
+-- The mass overrides of the objects visible in the layer.
+-- An override is applied from its start time until the next one.
+-- The start time is the one of the layer the override was created in,
+-- it is kept when the layer is merged into its parent.
+CREATE OR REPLACE FUNCTION {schema_name}.object_mass_overrides(
+    active_layer_id integer
+) RETURNS TABLE (
+    out_object_id bigint,
+    out_start_time bigint,
+    out_mass real
+) AS $$
+    BEGIN
+        RETURN QUERY
+        SELECT o.object_fk_id, o.start_time, o.mass
+        FROM {schema_name}.object_override o
+        INNER JOIN {schema_name}.layer_ancestors(active_layer_id) ancestors
+            ON o.layer_fk_id = ancestors.layer_id
+        WHERE o.mass IS NOT NULL
+        ORDER BY o.object_fk_id, o.start_time, ancestors.depth DESC;
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION {schema_name}.object_removed_time(
+    active_layer_id integer,
+    obj_id bigint
+) RETURNS bigint
+AS $$
+    BEGIN
+        RETURN (
+            SELECT MIN(removed_time)
+            FROM {schema_name}.object_override
+            INNER JOIN {schema_name}.layer_ancestors(active_layer_id) ancestors
+                ON layer_fk_id = ancestors.layer_id
+            WHERE object_fk_id = obj_id
+        );
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE PROCEDURE {schema_name}.copy_object_override(
+    src_layer_id integer,
+    src_object_id bigint,
+    dst_layer_id integer,
+    dst_object_id bigint
+) AS $$
+    BEGIN
+        INSERT INTO {schema_name}.object_override
+            (layer_fk_id, object_fk_id, start_time, mass, removed_time)
+        SELECT dst_layer_id, dst_object_id, start_time, mass, removed_time
+        FROM {schema_name}.object_override
+        WHERE layer_fk_id = src_layer_id AND object_fk_id = src_object_id;
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION {schema_name}.overridden_objects(
+    active_layer_id integer
+) RETURNS TABLE (
+    out_layer_id integer,
+    out_object_id bigint
+) AS $$
+    BEGIN
+        -- The objects are overridden in the descendants of the layers introducing them.
+        RETURN QUERY
+        SELECT DISTINCT ov.layer_fk_id, ov.object_fk_id
+        FROM {schema_name}.object o
+        INNER JOIN {schema_name}.layer_ancestors(active_layer_id) ancestors
+            ON o.layer_fk_id = ancestors.layer_id
+        CROSS JOIN LATERAL {schema_name}.layer_subtree(o.layer_fk_id) subtree
+        INNER JOIN {schema_name}.object_override ov
+            ON ov.object_fk_id = o.object_id
+            AND ov.layer_fk_id = subtree.layer_id
+        ORDER BY ov.layer_fk_id, ov.object_fk_id;
+    END
+$$ LANGUAGE plpgsql;
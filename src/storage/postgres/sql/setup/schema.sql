@@ -0,0 +1,8 @@
+CREATE SCHEMA IF NOT EXISTS {schema_name};
+
+CREATE TABLE IF NOT EXISTS {schema_name}.schema_version
+(
+    version integer PRIMARY KEY,
+    migration_name varchar(100) NOT NULL,
+    applied_at timestamptz NOT NULL DEFAULT now()
+);
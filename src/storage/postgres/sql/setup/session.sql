@@ -1,12 +1,4 @@
-CREATE TABLE IF NOT EXISTS {schema_name}.session
-(
-	session_id serial PRIMARY KEY,
-	session_name varchar(50) UNIQUE,
-	last_access timestamptz NOT NULL,
-	is_locked boolean NOT NULL
-);
-
-CREATE OR REPLACE FUNCTION is_session_hanged(
+CREATE OR REPLACE FUNCTION {schema_name}.is_session_hanged(
     session_last_access timestamptz
 ) RETURNS boolean
 AS $$
@@ -115,4 +107,44 @@ AS $$
             RAISE 'session `%` is either locked or not exists', name;
         END IF;
     END
-$$ LANGUAGE plpgsql;
\ No newline at end of file
+$$ LANGUAGE plpgsql;
+CREATE OR REPLACE FUNCTION {schema_name}.attached_session_id(
+    name varchar(50),
+    OUT found_session_id integer,
+    OUT found_main_layer_id integer
+)
+AS $$
+    BEGIN
+        SELECT session_id
+        FROM {schema_name}.session
+        WHERE session_name = name
+        INTO found_session_id;
+
+        IF (NOT FOUND) THEN
+            RAISE 'session `%` not exists', name;
+        END IF;
+
+        found_main_layer_id := {schema_name}.main_layer_id(found_session_id);
+    END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION {schema_name}.unlocked_session_id(
+    name varchar(50),
+    current_session_id integer
+) RETURNS integer
+AS $$
+    DECLARE
+        found_session_id integer;
+    BEGIN
+        SELECT session_id
+        FROM {schema_name}.session
+        WHERE session_name = name AND (is_locked = false OR session_id = current_session_id)
+        INTO found_session_id;
+
+        IF (NOT FOUND) THEN
+            RAISE 'session `%` is either locked or not exists', name;
+        END IF;
+
+        RETURN found_session_id;
+    END
+$$ LANGUAGE plpgsql;
@@ -4,7 +4,7 @@ AS $$
 		UPDATE {schema_name}.session 
 		SET is_locked=false
 		WHERE is_locked=true 
-		AND is_session_hanged(last_access);
+		AND {schema_name}.is_session_hanged(last_access);
 	END 
 $$ LANGUAGE plpgsql;
 
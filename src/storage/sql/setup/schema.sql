@@ -1 +0,0 @@
-CREATE SCHEMA IF NOT EXISTS {schema_name};
\ No newline at end of file
@@ -0,0 +1,66 @@
+use super::SqliteTransaction;
+use crate::{
+    r#type::{
+        BookmarkInfo, BookmarkName, IntoRustDuration, IntoStorageDuration, LayerId, RawTime,
+        SessionId,
+    },
+    storage::BookmarkApi,
+    Result,
+};
+use rusqlite::params;
+
+impl BookmarkApi for SqliteTransaction {
+    fn add(
+        &mut self,
+        session_id: SessionId,
+        layer_id: LayerId,
+        name: &BookmarkName,
+        time: chrono::Duration,
+    ) -> Result<()> {
+        self.connection.execute(
+            "INSERT INTO bookmark(session_fk_id, layer_fk_id, bookmark_name, t)
+            VALUES(?1, ?2, ?3, ?4)",
+            params![session_id, layer_id, name, time.into_storage_duration()],
+        )?;
+
+        Ok(())
+    }
+
+    fn remove(&mut self, session_id: SessionId, name: &BookmarkName) -> Result<()> {
+        let removed = self.connection.execute(
+            "DELETE FROM bookmark WHERE session_fk_id = ?1 AND bookmark_name = ?2",
+            params![session_id, name],
+        )?;
+
+        if removed == 0 {
+            return Err(Self::backend_error(format!("bookmark `{}` not found", name)));
+        }
+
+        Ok(())
+    }
+
+    fn get_list(&mut self, session_id: SessionId) -> Result<Vec<BookmarkInfo>> {
+        let mut statement = self.connection.prepare(
+            "SELECT bookmark_name, layer_name, t
+            FROM bookmark
+            INNER JOIN layer
+                ON layer_fk_id = layer.layer_id
+            WHERE bookmark.session_fk_id = ?1
+            ORDER BY t, bookmark_name",
+        )?;
+
+        let infos = statement
+            .query_map(params![session_id], |row| {
+                let time: RawTime = row.get(2)?;
+
+                Ok(BookmarkInfo {
+                    name: row.get(0)?,
+                    layer_name: row.get(1)?,
+                    time: time.into_rust_duration(),
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(infos)
+    }
+}
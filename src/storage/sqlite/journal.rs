@@ -0,0 +1,445 @@
+use super::SqliteTransaction;
+use crate::{
+    r#type::{JournalOperation, LayerId, LayerName, SessionId, SessionName},
+    storage::{JournalApi, LayerApi, SessionApi},
+    Result,
+};
+use itertools::Itertools;
+use rusqlite::{params, OptionalExtension, Row};
+
+type JournalId = i32;
+
+const TOMB_TABLES: &[&str] = &[
+    "tomb_session",
+    "tomb_layer",
+    "tomb_layer_family",
+    "tomb_object",
+    "tomb_location",
+    "tomb_collision_partners",
+    "tomb_object_override",
+    "tomb_bookmark",
+];
+
+impl SqliteTransaction {
+    fn journal_operation(row: &Row) -> rusqlite::Result<(JournalId, JournalOperation)> {
+        let journal_id = row.get(0)?;
+        let operation: String = row.get(1)?;
+        let target_id = row.get(2)?;
+        let old_name = row.get(3)?;
+
+        let operation = match operation.as_str() {
+            "rm-layer" => JournalOperation::RemoveLayer(target_id, old_name),
+            "rename-layer" => JournalOperation::RenameLayer(target_id, old_name, row.get(4)?),
+            "delete-session" => JournalOperation::DeleteSession(target_id, old_name),
+            _ => unreachable!("unknown journal operation \"{}\"", operation),
+        };
+
+        Ok((journal_id, operation))
+    }
+
+    fn journal_record(
+        &self,
+        session_id: SessionId,
+        operation: &str,
+        target_id: i32,
+        old_name: &str,
+        new_name: Option<&str>,
+    ) -> Result<JournalId> {
+        // A new operation makes the undone ones unreachable for redo.
+        self.connection.execute(
+            "DELETE FROM journal WHERE session_fk_id = ?1 AND is_undone = 1",
+            params![session_id],
+        )?;
+
+        self.connection.execute(
+            "INSERT INTO journal(
+                session_fk_id,
+                operation,
+                target_id,
+                old_name,
+                new_name,
+                record_time
+            ) VALUES(?1, ?2, ?3, ?4, ?5, ?6)",
+            params![session_id, operation, target_id, old_name, new_name, Self::now()],
+        )?;
+
+        Ok(self.connection.last_insert_rowid() as JournalId)
+    }
+
+    /// Moves the rows of the layers and everything which depends on them into the tombstones.
+    /// The rows themselves are left for the caller to delete.
+    fn bury_layers(&self, journal_id: JournalId, layer_ids: &[LayerId]) -> Result<()> {
+        let layer_ids = layer_ids.iter().join(",");
+
+        let bury_queries = [
+            format!(
+                "INSERT INTO tomb_layer(
+                    journal_fk_id, layer_id, session_fk_id, layer_name, start_time
+                )
+                SELECT ?1, layer_id, session_fk_id, layer_name, start_time
+                FROM layer
+                WHERE layer_id IN ({})",
+                layer_ids
+            ),
+            format!(
+                "INSERT INTO tomb_layer_family(journal_fk_id, parent_layer_id, child_layer_id)
+                SELECT ?1, parent_layer_id, child_layer_id
+                FROM layer_family
+                WHERE child_layer_id IN ({})",
+                layer_ids
+            ),
+            format!(
+                "INSERT INTO tomb_object(
+                    journal_fk_id,
+                    object_id,
+                    session_fk_id,
+                    layer_fk_id,
+                    object_name,
+                    radius,
+                    color,
+                    mass,
+                    compute_step
+                )
+                SELECT
+                    ?1,
+                    object_id,
+                    session_fk_id,
+                    layer_fk_id,
+                    object_name,
+                    radius,
+                    color,
+                    mass,
+                    compute_step
+                FROM object
+                WHERE layer_fk_id IN ({})",
+                layer_ids
+            ),
+            format!(
+                "INSERT INTO tomb_location(
+                    journal_fk_id,
+                    location_id,
+                    object_fk_id,
+                    layer_fk_id,
+                    t, x, y, z, vx, vy, vz, vcx, vcy, vcz
+                )
+                SELECT
+                    ?1,
+                    location_id,
+                    object_fk_id,
+                    layer_fk_id,
+                    t, x, y, z, vx, vy, vz, vcx, vcy, vcz
+                FROM location
+                WHERE
+                    layer_fk_id IN ({})
+                    OR object_fk_id IN (
+                        SELECT object_id
+                        FROM tomb_object
+                        WHERE journal_fk_id = ?1
+                    )",
+                layer_ids
+            ),
+            "INSERT INTO tomb_collision_partners(journal_fk_id, location_fk_id, partner_fk_id)
+            SELECT ?1, location_fk_id, partner_fk_id
+            FROM collision_partners
+            WHERE
+                location_fk_id IN (
+                    SELECT location_id
+                    FROM tomb_location
+                    WHERE journal_fk_id = ?1
+                )
+                OR partner_fk_id IN (
+                    SELECT location_id
+                    FROM tomb_location
+                    WHERE journal_fk_id = ?1
+                )"
+            .to_string(),
+            format!(
+                "INSERT INTO tomb_object_override(
+                    journal_fk_id, layer_fk_id, object_fk_id, start_time, mass, removed_time
+                )
+                SELECT ?1, layer_fk_id, object_fk_id, start_time, mass, removed_time
+                FROM object_override
+                WHERE
+                    layer_fk_id IN ({})
+                    OR object_fk_id IN (
+                        SELECT object_id
+                        FROM tomb_object
+                        WHERE journal_fk_id = ?1
+                    )",
+                layer_ids
+            ),
+            format!(
+                "INSERT INTO tomb_bookmark(
+                    journal_fk_id, bookmark_id, session_fk_id, layer_fk_id, bookmark_name, t
+                )
+                SELECT ?1, bookmark_id, session_fk_id, layer_fk_id, bookmark_name, t
+                FROM bookmark
+                WHERE layer_fk_id IN ({})",
+                layer_ids
+            ),
+        ];
+
+        for query in bury_queries.iter() {
+            self.connection.execute(query, params![journal_id])?;
+        }
+
+        Ok(())
+    }
+
+    fn bury_layer(&self, journal_id: JournalId, layer_id: LayerId) -> Result<()> {
+        let subtree: Vec<_> = self
+            .layer_subtree(layer_id)?
+            .into_iter()
+            .map(|(layer_id, _)| layer_id)
+            .collect();
+
+        self.bury_layers(journal_id, &subtree)?;
+
+        // There is no trigger removing the descendants, so the whole subtree is deleted.
+        self.connection.execute(
+            &format!(
+                "DELETE FROM layer WHERE layer_id IN ({})",
+                subtree.iter().join(",")
+            ),
+            params![],
+        )?;
+
+        Ok(())
+    }
+
+    fn bury_session(&mut self, journal_id: JournalId, session_id: SessionId) -> Result<()> {
+        let buried = self.connection.execute(
+            "INSERT INTO tomb_session(
+                journal_fk_id, session_id, session_name, last_access, is_locked
+            )
+            SELECT ?1, session_id, session_name, last_access, is_locked
+            FROM session
+            WHERE session_id = ?2 AND is_locked = 0",
+            params![journal_id, session_id],
+        )?;
+
+        if buried == 0 {
+            let name = SessionApi::get_name(self, session_id)?;
+
+            return Err(Self::locked_or_not_exists(&name));
+        }
+
+        let layer_ids = {
+            let mut statement = self
+                .connection
+                .prepare("SELECT layer_id FROM layer WHERE session_fk_id = ?1")?;
+
+            let layer_ids = statement
+                .query_map(params![session_id], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<LayerId>>>()?;
+
+            layer_ids
+        };
+
+        self.bury_layers(journal_id, &layer_ids)?;
+
+        self.connection.execute(
+            "DELETE FROM session WHERE session_id = ?1",
+            params![session_id],
+        )?;
+
+        self.maintain_sessions()
+    }
+
+    fn unbury(&self, journal_id: JournalId) -> Result<()> {
+        self.connection.execute_batch(&format!(
+            "INSERT INTO session
+            SELECT session_id, session_name, last_access, is_locked
+            FROM tomb_session
+            WHERE journal_fk_id = {jid};
+
+            INSERT INTO layer
+            SELECT layer_id, session_fk_id, layer_name, start_time
+            FROM tomb_layer
+            WHERE journal_fk_id = {jid};
+
+            INSERT INTO layer_family
+            SELECT parent_layer_id, child_layer_id
+            FROM tomb_layer_family
+            WHERE journal_fk_id = {jid};
+
+            INSERT INTO object
+            SELECT
+                object_id,
+                session_fk_id,
+                layer_fk_id,
+                object_name,
+                radius,
+                color,
+                mass,
+                compute_step
+            FROM tomb_object
+            WHERE journal_fk_id = {jid};
+
+            INSERT INTO location
+            SELECT
+                location_id,
+                object_fk_id,
+                layer_fk_id,
+                t, x, y, z, vx, vy, vz, vcx, vcy, vcz
+            FROM tomb_location
+            WHERE journal_fk_id = {jid};
+
+            INSERT INTO collision_partners
+            SELECT location_fk_id, partner_fk_id
+            FROM tomb_collision_partners
+            WHERE journal_fk_id = {jid};
+
+            INSERT INTO object_override
+            SELECT layer_fk_id, object_fk_id, start_time, mass, removed_time
+            FROM tomb_object_override
+            WHERE journal_fk_id = {jid};
+
+            INSERT INTO bookmark
+            SELECT bookmark_id, session_fk_id, layer_fk_id, bookmark_name, t
+            FROM tomb_bookmark
+            WHERE journal_fk_id = {jid};",
+            jid = journal_id
+        ))?;
+
+        for table in TOMB_TABLES {
+            self.connection.execute(
+                &format!("DELETE FROM {} WHERE journal_fk_id = ?1", table),
+                params![journal_id],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn last_journal_entry(
+        &self,
+        session_id: SessionId,
+        is_undone: bool,
+    ) -> Result<Option<(JournalId, JournalOperation)>> {
+        // The latest done entry is undone first, the earliest undone one is redone first.
+        let entry = self
+            .connection
+            .query_row(
+                "SELECT journal_id, operation, target_id, old_name, new_name
+                FROM journal
+                WHERE session_fk_id = ?1 AND is_undone = ?2
+                ORDER BY
+                    CASE WHEN ?2 THEN journal_id END ASC,
+                    CASE WHEN NOT ?2 THEN journal_id END DESC
+                LIMIT 1",
+                params![session_id, is_undone],
+                Self::journal_operation,
+            )
+            .optional()?;
+
+        Ok(entry)
+    }
+
+    fn set_undone(&self, journal_id: JournalId, is_undone: bool) -> Result<()> {
+        self.connection.execute(
+            "UPDATE journal SET is_undone = ?2 WHERE journal_id = ?1",
+            params![journal_id, is_undone],
+        )?;
+
+        Ok(())
+    }
+}
+
+impl JournalApi for SqliteTransaction {
+    fn remove_layer(&mut self, session_id: SessionId, layer_id: LayerId) -> Result<()> {
+        let layer_name = LayerApi::get_name(self, layer_id)?;
+        let journal_id = self.journal_record(session_id, "rm-layer", layer_id, &layer_name, None)?;
+
+        self.bury_layer(journal_id, layer_id)
+    }
+
+    fn rename_layer(
+        &mut self,
+        session_id: SessionId,
+        layer_id: LayerId,
+        new_layer_name: &LayerName,
+    ) -> Result<()> {
+        let layer_name = LayerApi::get_name(self, layer_id)?;
+
+        self.journal_record(
+            session_id,
+            "rename-layer",
+            layer_id,
+            &layer_name,
+            Some(new_layer_name.as_str()),
+        )?;
+
+        LayerApi::rename_layer(self, layer_id, new_layer_name)
+    }
+
+    fn delete_session(&mut self, session_id: SessionId, name: &SessionName) -> Result<()> {
+        let deleted_session_id: SessionId = self
+            .connection
+            .query_row(
+                "SELECT session_id FROM session WHERE session_name = ?1 AND is_locked = 0",
+                params![name],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| Self::locked_or_not_exists(name))?;
+
+        let journal_id = self.journal_record(
+            session_id,
+            "delete-session",
+            deleted_session_id,
+            name,
+            None,
+        )?;
+
+        self.bury_session(journal_id, deleted_session_id)
+    }
+
+    fn undo(&mut self, session_id: SessionId) -> Result<Option<JournalOperation>> {
+        let (journal_id, operation) = match self.last_journal_entry(session_id, false)? {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        match &operation {
+            JournalOperation::RenameLayer(layer_id, old_name, _) => {
+                LayerApi::rename_layer(self, *layer_id, old_name)?
+            }
+            _ => self.unbury(journal_id)?,
+        }
+
+        self.set_undone(journal_id, true)?;
+
+        Ok(Some(operation))
+    }
+
+    fn redo(&mut self, session_id: SessionId) -> Result<Option<JournalOperation>> {
+        let (journal_id, operation) = match self.last_journal_entry(session_id, true)? {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+
+        match &operation {
+            JournalOperation::RemoveLayer(layer_id, _) => self.bury_layer(journal_id, *layer_id)?,
+            JournalOperation::RenameLayer(layer_id, _, new_name) => {
+                LayerApi::rename_layer(self, *layer_id, new_name)?
+            }
+            JournalOperation::DeleteSession(deleted_session_id, _) => {
+                self.bury_session(journal_id, *deleted_session_id)?
+            }
+        }
+
+        self.set_undone(journal_id, false)?;
+
+        Ok(Some(operation))
+    }
+
+    fn purge(&mut self, session_id: Option<SessionId>) -> Result<()> {
+        self.connection.execute(
+            "DELETE FROM journal WHERE ?1 IS NULL OR session_fk_id = ?1",
+            params![session_id],
+        )?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,568 @@
+use super::SqliteTransaction;
+use crate::{
+    make_error,
+    r#type::{
+        IntoRustDuration, IntoStorageDuration, LayerCopyInfo, LayerId, LayerInfo, LayerMergeInfo,
+        LayerName, ObjectId, ObjectName, RawTime, SessionId,
+    },
+    storage::LayerApi,
+    Result,
+};
+use rusqlite::{params, OptionalExtension};
+use std::collections::{BTreeSet, HashMap};
+
+/// Prepends the `replaced_locations(location_id, t)` CTE to the query:
+/// the parent's locations replaced by the merge of the `?1` layer.
+///
+/// These are the ones from the child's first location of the object, and for the objects
+/// without locations in the child, the ones after the child's start time.
+/// The first location of an object is always kept, the track is recomputed from it.
+macro_rules! with_replaced_locations {
+    ($query:literal) => {
+        concat!(
+            "WITH merge_layers(parent_id, start_time) AS (
+                SELECT parent_layer_id, start_time
+                FROM layer_family
+                INNER JOIN layer
+                    ON child_layer_id = layer_id
+                WHERE child_layer_id = ?1
+            ),
+            replaced_locations(location_id, t) AS (
+                SELECT parent_location.location_id, parent_location.t
+                FROM location parent_location
+                INNER JOIN merge_layers
+                    ON parent_location.layer_fk_id = merge_layers.parent_id
+                INNER JOIN (
+                    SELECT object_fk_id, MIN(t) AS first_time
+                    FROM location
+                    WHERE layer_fk_id = (SELECT parent_id FROM merge_layers)
+                    GROUP BY object_fk_id
+                ) parent_objects
+                    ON parent_location.object_fk_id = parent_objects.object_fk_id
+                LEFT OUTER JOIN (
+                    SELECT object_fk_id, MIN(t) AS branch_time
+                    FROM location
+                    WHERE layer_fk_id = ?1
+                    GROUP BY object_fk_id
+                ) child_objects
+                    ON parent_location.object_fk_id = child_objects.object_fk_id
+                WHERE
+                    parent_location.t >= child_objects.branch_time
+                    OR (
+                        child_objects.branch_time IS NULL
+                        AND parent_location.t > MAX(
+                            merge_layers.start_time,
+                            parent_objects.first_time
+                        )
+                    )
+            ) ",
+            $query
+        )
+    };
+}
+
+impl SqliteTransaction {
+    fn query_names(&self, query: &str, layer_id: LayerId) -> Result<Vec<String>> {
+        let mut statement = self.connection.prepare(query)?;
+
+        let names = statement
+            .query_map(params![layer_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(names)
+    }
+
+    fn copy_layer_row(
+        &self,
+        src_layer_id: LayerId,
+        dst_session_id: SessionId,
+        dst_parent_id: LayerId,
+    ) -> Result<LayerId> {
+        let (layer_name, start_time): (LayerName, RawTime) = self.connection.query_row(
+            "SELECT layer_name, start_time FROM layer WHERE layer_id = ?1",
+            params![src_layer_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        let layer_name = Self::unique_name(&layer_name, |name| {
+            let is_taken = self.connection.query_row(
+                "SELECT EXISTS(
+                    SELECT 1 FROM layer WHERE session_fk_id = ?1 AND layer_name = ?2
+                )",
+                params![dst_session_id, name],
+                |row| row.get(0),
+            )?;
+
+            Ok(is_taken)
+        })?;
+
+        self.connection.execute(
+            "INSERT INTO layer(session_fk_id, layer_name, start_time) VALUES(?1, ?2, ?3)",
+            params![dst_session_id, layer_name, start_time],
+        )?;
+
+        let new_layer_id = self.connection.last_insert_rowid() as LayerId;
+
+        self.connection.execute(
+            "INSERT INTO layer_family VALUES(?1, ?2)",
+            params![dst_parent_id, new_layer_id],
+        )?;
+
+        Ok(new_layer_id)
+    }
+}
+
+impl LayerApi for SqliteTransaction {
+    fn get_name(&mut self, layer_id: LayerId) -> Result<LayerName> {
+        let name = self.connection.query_row(
+            "SELECT layer_name FROM layer WHERE layer_id = ?1",
+            params![layer_id],
+            |row| row.get(0),
+        )?;
+
+        Ok(name)
+    }
+
+    fn get_start_time(&mut self, layer_id: LayerId) -> Result<chrono::Duration> {
+        let start_time: RawTime = self.connection.query_row(
+            "SELECT start_time FROM layer WHERE layer_id = ?1",
+            params![layer_id],
+            |row| row.get(0),
+        )?;
+
+        Ok(start_time.into_rust_duration())
+    }
+
+    fn rename_layer(&mut self, layer_id: LayerId, new_layer_name: &LayerName) -> Result<()> {
+        self.connection.execute(
+            "UPDATE layer SET layer_name = ?2 WHERE layer_id = ?1",
+            params![layer_id, new_layer_name],
+        )?;
+
+        Ok(())
+    }
+
+    fn get_layer_id(&mut self, session_id: SessionId, layer_name: &LayerName) -> Result<LayerId> {
+        self.connection
+            .query_row(
+                "SELECT layer_id FROM layer WHERE session_fk_id = ?1 AND layer_name = ?2",
+                params![session_id, layer_name],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| make_error![Error::Layer::LayerNotFound(layer_name.clone())])
+    }
+
+    fn get_main_layer(&mut self, session_id: SessionId) -> Result<LayerId> {
+        self.main_layer_id(session_id)
+    }
+
+    fn get_layer_children(
+        &mut self,
+        _session_id: SessionId,
+        layer_id: LayerId,
+    ) -> Result<Vec<LayerId>> {
+        let mut statement = self.connection.prepare(
+            "SELECT layer_id
+            FROM layer
+            INNER JOIN layer_family
+                ON child_layer_id = layer_id
+            WHERE parent_layer_id = ?1
+            ORDER BY start_time",
+        )?;
+
+        let children = statement
+            .query_map(params![layer_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(children)
+    }
+
+    fn get_layers_info(&mut self, session_id: SessionId) -> Result<Vec<LayerInfo>> {
+        let mut statement = self.connection.prepare(
+            "SELECT
+                l.layer_id,
+                f.parent_layer_id,
+                l.layer_name,
+                l.start_time,
+                (
+                    SELECT COUNT(*)
+                    FROM object
+                    WHERE object.layer_fk_id = l.layer_id
+                ),
+                (
+                    SELECT MAX(t)
+                    FROM location
+                    WHERE location.layer_fk_id = l.layer_id
+                )
+            FROM layer l
+            LEFT OUTER JOIN layer_family f
+                ON f.child_layer_id = l.layer_id
+            WHERE l.session_fk_id = ?1
+            ORDER BY l.layer_id",
+        )?;
+
+        let infos = statement
+            .query_map(params![session_id], |row| {
+                let start_time: RawTime = row.get(3)?;
+                let last_computed_time: Option<RawTime> = row.get(5)?;
+
+                Ok(LayerInfo {
+                    id: row.get(0)?,
+                    parent_id: row.get(1)?,
+                    name: row.get(2)?,
+                    start_time: start_time.into_rust_duration(),
+                    objects_count: row.get(4)?,
+                    last_computed_time: last_computed_time.map(|t| t.into_rust_duration()),
+                    is_active: false,
+                    is_current: false,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(infos)
+    }
+
+    fn get_current_layer_id(
+        &mut self,
+        active_layer_id: LayerId,
+        vtime: chrono::Duration,
+    ) -> Result<LayerId> {
+        let vtime = vtime.into_storage_duration();
+        let ancestors = self.ancestors_start_times(active_layer_id)?;
+
+        // Each ancestor lasts until its child (the next one) starts.
+        // The deepest layer wins at the boundary.
+        let next_start_times = ancestors
+            .iter()
+            .skip(1)
+            .map(|&(_, start_time)| Some(start_time))
+            .chain(std::iter::once(None));
+
+        ancestors
+            .iter()
+            .zip(next_start_times)
+            .filter(|((_, start_time), next_start_time)| {
+                *start_time <= vtime && next_start_time.map_or(true, |next| vtime <= next)
+            })
+            .map(|((layer_id, _), _)| *layer_id)
+            .last()
+            .ok_or_else(|| {
+                Self::backend_error(format!(
+                    "no layer of the layer #{} contains the time {}",
+                    active_layer_id, vtime
+                ))
+            })
+    }
+
+    fn add_layer(
+        &mut self,
+        session_id: SessionId,
+        active_layer_id: LayerId,
+        new_layer_name: &LayerName,
+        new_layer_start_time: chrono::Duration,
+    ) -> Result<LayerId> {
+        let parent_id = self.get_current_layer_id(active_layer_id, new_layer_start_time)?;
+
+        self.connection.execute(
+            "INSERT INTO layer(session_fk_id, layer_name, start_time) VALUES(?1, ?2, ?3)",
+            params![
+                session_id,
+                new_layer_name,
+                new_layer_start_time.into_storage_duration()
+            ],
+        )?;
+
+        let new_layer_id = self.connection.last_insert_rowid() as LayerId;
+
+        self.connection.execute(
+            "INSERT INTO layer_family VALUES(?1, ?2)",
+            params![parent_id, new_layer_id],
+        )?;
+
+        Ok(new_layer_id)
+    }
+
+    fn layer_ancestors(&mut self, layer_id: LayerId) -> Result<Vec<LayerId>> {
+        let ancestors = self.ancestors_start_times(layer_id)?;
+
+        Ok(ancestors.into_iter().map(|(layer_id, _)| layer_id).collect())
+    }
+
+    fn get_parent_id(&mut self, layer_id: LayerId) -> Result<Option<LayerId>> {
+        let parent_id = self
+            .connection
+            .query_row(
+                "SELECT parent_layer_id FROM layer_family WHERE child_layer_id = ?1",
+                params![layer_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        Ok(parent_id)
+    }
+
+    fn get_merge_info(&mut self, layer_id: LayerId) -> Result<LayerMergeInfo> {
+        let name = self.get_name(layer_id)?;
+
+        let parent_id = self
+            .get_parent_id(layer_id)?
+            .ok_or_else(|| make_error![Error::Layer::NoParentLayer(name)])?;
+
+        let parent_name = self.get_name(parent_id)?;
+        let start_time = self.get_start_time(layer_id)?;
+
+        let replaced_locations = self.connection.query_row(
+            with_replaced_locations!("SELECT COUNT(*) FROM replaced_locations"),
+            params![layer_id],
+            |row| row.get(0),
+        )?;
+
+        let moved_locations = self.connection.query_row(
+            "SELECT COUNT(*) FROM location WHERE layer_fk_id = ?1",
+            params![layer_id],
+            |row| row.get(0),
+        )?;
+
+        let moved_objects = self.query_names(
+            "SELECT object_name
+            FROM object
+            WHERE layer_fk_id = ?1
+            ORDER BY object_name",
+            layer_id,
+        )?;
+
+        let overridden_objects = self.query_names(
+            "SELECT DISTINCT object_name
+            FROM object_override
+            INNER JOIN object
+                ON object_fk_id = object_id
+            WHERE object_override.layer_fk_id = ?1
+            ORDER BY object_name",
+            layer_id,
+        )?;
+
+        let reparented_layers = self.query_names(
+            "SELECT layer_name
+            FROM layer
+            INNER JOIN layer_family
+                ON child_layer_id = layer_id
+            WHERE parent_layer_id = ?1
+            ORDER BY layer_name",
+            layer_id,
+        )?;
+
+        let conflicting_layers = self.query_names(
+            with_replaced_locations!(
+                "SELECT sibling.layer_name
+                FROM layer sibling
+                INNER JOIN layer_family family
+                    ON family.child_layer_id = sibling.layer_id
+                WHERE
+                    family.parent_layer_id = (SELECT parent_id FROM merge_layers)
+                    AND sibling.layer_id <> ?1
+                    AND sibling.start_time > (SELECT MIN(t) FROM replaced_locations)
+                ORDER BY sibling.layer_name"
+            ),
+            layer_id,
+        )?;
+
+        Ok(LayerMergeInfo {
+            parent_name,
+            start_time,
+            replaced_locations,
+            moved_locations,
+            moved_objects,
+            overridden_objects,
+            reparented_layers,
+            conflicting_layers,
+        })
+    }
+
+    fn merge_layer(&mut self, layer_id: LayerId) -> Result<()> {
+        let parent_id = match self.get_parent_id(layer_id)? {
+            Some(parent_id) => parent_id,
+            None => return Err(make_error![Error::Layer::NoParentLayer(self.get_name(layer_id)?)]),
+        };
+
+        self.connection.execute(
+            with_replaced_locations!(
+                "DELETE FROM location
+                WHERE location_id IN (SELECT location_id FROM replaced_locations)"
+            ),
+            params![layer_id],
+        )?;
+
+        for table in &["location", "object", "bookmark"] {
+            self.connection.execute(
+                &format!("UPDATE {} SET layer_fk_id = ?2 WHERE layer_fk_id = ?1", table),
+                params![layer_id, parent_id],
+            )?;
+        }
+
+        // The overrides keep their start time, they still apply from the child's start.
+        // SQLite's MIN is NULL if any argument is NULL unlike Postgres' LEAST.
+        self.connection.execute(
+            "INSERT INTO object_override
+            SELECT ?2, object_fk_id, start_time, mass, removed_time
+            FROM object_override
+            WHERE layer_fk_id = ?1
+            ON CONFLICT (layer_fk_id, object_fk_id, start_time) DO UPDATE
+            SET
+                mass = COALESCE(excluded.mass, object_override.mass),
+                removed_time = COALESCE(
+                    MIN(excluded.removed_time, object_override.removed_time),
+                    excluded.removed_time,
+                    object_override.removed_time
+                )",
+            params![layer_id, parent_id],
+        )?;
+
+        self.connection.execute(
+            "UPDATE layer_family SET parent_layer_id = ?2 WHERE parent_layer_id = ?1",
+            params![layer_id, parent_id],
+        )?;
+
+        // The layer's family row and overrides are removed by the cascade.
+        self.connection.execute(
+            "DELETE FROM layer WHERE layer_id = ?1",
+            params![layer_id],
+        )?;
+
+        Ok(())
+    }
+
+    fn get_session_id(&mut self, layer_id: LayerId) -> Result<SessionId> {
+        let session_id = self.connection.query_row(
+            "SELECT session_fk_id FROM layer WHERE layer_id = ?1",
+            params![layer_id],
+            |row| row.get(0),
+        )?;
+
+        Ok(session_id)
+    }
+
+    fn copy_layer(
+        &mut self,
+        src_layer_id: LayerId,
+        dst_session_id: SessionId,
+        dst_parent_id: LayerId,
+        with_descendants: bool,
+        with_computed: bool,
+    ) -> Result<LayerCopyInfo> {
+        let is_same_session = self.get_session_id(src_layer_id)? == dst_session_id;
+
+        let subtree = if with_descendants {
+            self.layer_subtree(src_layer_id)?
+        } else {
+            vec![(src_layer_id, None)]
+        };
+
+        let mut layers_map = HashMap::new();
+        let mut objects_map: HashMap<ObjectId, ObjectId> = HashMap::new();
+        let mut skipped_objects = BTreeSet::new();
+
+        let mut info = LayerCopyInfo {
+            layers: vec![],
+            renamed_objects: vec![],
+            copied_objects: 0,
+            copied_locations: 0,
+            skipped_objects: vec![],
+        };
+
+        // The subtree is ordered by id, so parents are always copied before their children.
+        for (src_id, src_parent_id) in subtree {
+            let parent_id = match src_parent_id {
+                Some(src_parent_id) => layers_map[&src_parent_id],
+                None => dst_parent_id,
+            };
+
+            let new_layer_id = self.copy_layer_row(src_id, dst_session_id, parent_id)?;
+
+            layers_map.insert(src_id, new_layer_id);
+            info.layers.push((self.get_name(src_id)?, self.get_name(new_layer_id)?));
+
+            let objects: Vec<(ObjectId, ObjectName)> = {
+                let mut statement = self.connection.prepare(
+                    "SELECT object_id, object_name
+                    FROM object
+                    WHERE layer_fk_id = ?1
+                    ORDER BY object_id",
+                )?;
+
+                let objects = statement
+                    .query_map(params![src_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .collect::<rusqlite::Result<_>>()?;
+
+                objects
+            };
+
+            for (object_id, object_name) in objects {
+                let (new_object_id, new_object_name) =
+                    self.copy_object(object_id, dst_session_id, new_layer_id)?;
+
+                if new_object_name != object_name {
+                    info.renamed_objects.push((object_name, new_object_name));
+                }
+
+                objects_map.insert(object_id, new_object_id);
+                info.copied_objects += 1;
+            }
+
+            // Tracks and overrides of both own and inherited objects.
+            let tracks: Vec<(ObjectId, ObjectName)> = {
+                let mut statement = self.connection.prepare(
+                    "SELECT object_id, object_name
+                    FROM object
+                    WHERE object_id IN (
+                        SELECT object_fk_id
+                        FROM location
+                        WHERE layer_fk_id = ?1
+                        UNION
+                        SELECT object_fk_id
+                        FROM object_override
+                        WHERE layer_fk_id = ?1
+                    )
+                    ORDER BY object_id",
+                )?;
+
+                let tracks = statement
+                    .query_map(params![src_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .collect::<rusqlite::Result<_>>()?;
+
+                tracks
+            };
+
+            for (object_id, object_name) in tracks {
+                let dst_object_id = match objects_map.get(&object_id) {
+                    Some(dst_object_id) => *dst_object_id,
+                    None if is_same_session => object_id,
+                    None => {
+                        skipped_objects.insert(object_name);
+                        continue;
+                    }
+                };
+
+                info.copied_locations += self.copy_locations(
+                    object_id,
+                    src_id,
+                    dst_object_id,
+                    new_layer_id,
+                    with_computed,
+                )?;
+
+                self.connection.execute(
+                    "INSERT INTO object_override
+                    SELECT ?3, ?4, start_time, mass, removed_time
+                    FROM object_override
+                    WHERE layer_fk_id = ?1 AND object_fk_id = ?2",
+                    params![src_id, object_id, new_layer_id, dst_object_id],
+                )?;
+            }
+        }
+
+        info.skipped_objects = skipped_objects.into_iter().collect();
+
+        Ok(info)
+    }
+}
@@ -0,0 +1,359 @@
+use super::SqliteTransaction;
+use crate::{
+    engine::context::{db_util::LocationInfo, TimeRange},
+    object::GenCoord,
+    r#type::{
+        Coord, IntoRustDuration, IntoStorageDuration, LayerId, ObjectId, RawTime, SessionId,
+        Vector,
+    },
+    storage::LocationApi,
+    Result,
+};
+use rusqlite::params;
+
+impl SqliteTransaction {
+    /// Copies the object's locations of the source layer into the destination layer.
+    /// Without the computed locations only the initial one is copied.
+    pub(super) fn copy_locations(
+        &mut self,
+        src_object_id: ObjectId,
+        src_layer_id: LayerId,
+        dst_object_id: ObjectId,
+        dst_layer_id: LayerId,
+        with_computed: bool,
+    ) -> Result<i64> {
+        let copied_count = self.connection.execute(
+            "INSERT INTO location(
+                object_fk_id, layer_fk_id, t, x, y, z, vx, vy, vz, vcx, vcy, vcz
+            )
+            SELECT
+                ?3,
+                ?4,
+                t, x, y, z, vx, vy, vz,
+                CASE WHEN ?5 THEN vcx END,
+                CASE WHEN ?5 THEN vcy END,
+                CASE WHEN ?5 THEN vcz END
+            FROM location
+            WHERE
+                object_fk_id = ?1
+                AND layer_fk_id = ?2
+                AND (?5 OR t = (
+                    SELECT MIN(t)
+                    FROM location
+                    WHERE object_fk_id = ?1 AND layer_fk_id = ?2
+                ))
+            ORDER BY t, location_id",
+            params![src_object_id, src_layer_id, dst_object_id, dst_layer_id, with_computed],
+        )?;
+
+        Ok(copied_count as i64)
+    }
+
+    /// The SQLite version of the Postgres `range_locations` function.
+    ///
+    /// Each ancestor layer contributes the object's locations from the time the object
+    /// was first computed in the layer until it is first computed in the next one.
+    /// Only the latest location is kept for each time.
+    fn range_locations(
+        &self,
+        layer_id: LayerId,
+        start_time: RawTime,
+        stop_time: RawTime,
+        step_coeff: i16,
+    ) -> Result<Vec<LocationInfo>> {
+        let mut statement = self.connection.prepare_cached(with_ancestors!(
+            ", start_times AS (
+                SELECT object_fk_id, layer_fk_id, ancestors.depth, MIN(t) AS min_t
+                FROM location
+                INNER JOIN ancestors
+                    ON layer_fk_id = ancestors.layer_id
+                GROUP BY object_fk_id, layer_fk_id, ancestors.depth
+            ), steps AS (
+                SELECT
+                    object_id,
+                    CASE WHEN ?4 = 0 THEN -compute_step ELSE compute_step * ?4 END AS left_step,
+                    CASE WHEN ?4 = 0 THEN compute_step ELSE compute_step * ?4 END AS right_step
+                FROM object
+            ), layers_info AS (
+                SELECT
+                    s.object_fk_id,
+                    s.layer_fk_id,
+                    MAX(s.min_t, ?2 + steps.left_step) AS layer_start_time,
+                    MIN(
+                        COALESCE(
+                            LEAD(s.min_t) OVER (
+                                PARTITION BY s.object_fk_id
+                                ORDER BY s.depth DESC
+                            ),
+                            ?3 + steps.right_step
+                        ),
+                        ?3 + steps.right_step
+                    ) AS layer_stop_time
+                FROM start_times s
+                INNER JOIN steps
+                    ON s.object_fk_id = steps.object_id
+            ), ranked AS (
+                SELECT
+                    l.location_id,
+                    l.object_fk_id,
+                    l.t, l.x, l.y, l.z, l.vx, l.vy, l.vz, l.vcx, l.vcy, l.vcz,
+                    ROW_NUMBER() OVER (
+                        PARTITION BY l.object_fk_id, l.t
+                        ORDER BY l.location_id DESC
+                    ) AS location_rank
+                FROM location l
+                INNER JOIN layers_info i
+                    ON l.object_fk_id = i.object_fk_id
+                    AND l.layer_fk_id = i.layer_fk_id
+                    AND l.t BETWEEN i.layer_start_time AND i.layer_stop_time
+                WHERE l.t <= COALESCE(
+                    (
+                        SELECT MIN(removed_time)
+                        FROM object_override
+                        WHERE
+                            object_fk_id = l.object_fk_id
+                            AND layer_fk_id IN (SELECT layer_id FROM ancestors)
+                    ),
+                    l.t
+                )
+            )
+            SELECT
+                location_id,
+                object_fk_id,
+                t, x, y, z, vx, vy, vz, vcx, vcy, vcz
+            FROM ranked
+            WHERE location_rank = 1
+            ORDER BY object_fk_id, t"
+        ))?;
+
+        let locations = statement
+            .query_map(
+                params![layer_id, start_time, stop_time, step_coeff],
+                |row| {
+                    let t: RawTime = row.get(2)?;
+                    let coord = |idx: usize| row.get::<_, f64>(idx).map(|c| c as Coord);
+                    let opt_coord = |idx: usize| {
+                        row.get::<_, Option<f64>>(idx)
+                            .map(|c| c.map(|c| c as Coord))
+                    };
+
+                    Ok(LocationInfo {
+                        layer_id: LayerId::default(),
+                        location_id: row.get(0)?,
+                        object_id: row.get(1)?,
+                        t: t.into_rust_duration(),
+                        x: coord(3)?,
+                        y: coord(4)?,
+                        z: coord(5)?,
+                        vx: coord(6)?,
+                        vy: coord(7)?,
+                        vz: coord(8)?,
+                        vcx: opt_coord(9)?,
+                        vcy: opt_coord(10)?,
+                        vcz: opt_coord(11)?,
+                    })
+                },
+            )?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(locations)
+    }
+}
+
+impl LocationApi for SqliteTransaction {
+    fn add(&mut self, object_id: ObjectId, layer_id: LayerId, coord: GenCoord) -> Result<()> {
+        let location = coord.location();
+        let velocity = coord.velocity();
+
+        self.connection.execute(
+            "INSERT INTO location(object_fk_id, layer_fk_id, t, x, y, z, vx, vy, vz)
+            VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                object_id,
+                layer_id,
+                coord.time().into_storage_duration(),
+                location[0] as f64,
+                location[1] as f64,
+                location[2] as f64,
+                velocity[0] as f64,
+                velocity[1] as f64,
+                velocity[2] as f64,
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    fn add_locations(&mut self, locations: &[LocationInfo]) -> Result<()> {
+        let mut statement = self.connection.prepare_cached(
+            "INSERT INTO location(
+                object_fk_id,
+                layer_fk_id,
+                t,
+                x,
+                y,
+                z,
+                vx,
+                vy,
+                vz,
+                vcx,
+                vcy,
+                vcz
+            ) VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        )?;
+
+        for location_info in locations {
+            statement.execute(params![
+                location_info.object_id,
+                location_info.layer_id,
+                location_info.t.into_storage_duration(),
+                location_info.x as f64,
+                location_info.y as f64,
+                location_info.z as f64,
+                location_info.vx as f64,
+                location_info.vy as f64,
+                location_info.vz as f64,
+                location_info.vcx.map(|c| c as f64),
+                location_info.vcy.map(|c| c as f64),
+                location_info.vcz.map(|c| c as f64),
+            ])?;
+        }
+
+        Ok(())
+    }
+
+    fn get_min_valid_start_time(
+        &mut self,
+        layer_id: LayerId,
+        requested_time: chrono::Duration,
+    ) -> Result<chrono::Duration> {
+        let time: RawTime = self.connection.query_row(
+            with_ancestors!(
+                "SELECT COALESCE(MIN(max_obj_time), ?2)
+                FROM (
+                    SELECT MAX(t) AS max_obj_time
+                    FROM location
+                    WHERE
+                        layer_fk_id = ?1
+                        AND object_fk_id IN (
+                            SELECT object_id
+                            FROM object
+                            WHERE layer_fk_id IN (SELECT layer_id FROM ancestors)
+                        )
+                    GROUP BY object_fk_id
+                )"
+            ),
+            params![layer_id, requested_time.into_storage_duration()],
+            |row| row.get(0),
+        )?;
+
+        Ok(time.into_rust_duration())
+    }
+
+    fn get_keyframe_time(
+        &mut self,
+        layer_id: LayerId,
+        requested_time: chrono::Duration,
+    ) -> Result<chrono::Duration> {
+        // The object's track is continuous around the requested time
+        // unless the previous location is farther than the compute step.
+        // The ancestors' locations are skipped: their gaps are recomputed only in their own layer.
+        let time: RawTime = self.connection.query_row(
+            with_ancestors!(
+                "SELECT COALESCE(MIN(prev_t), ?2)
+                FROM (
+                    SELECT
+                        MAX(CASE WHEN t <= ?2 THEN t END) AS prev_t,
+                        MAX(t) AS max_t,
+                        o.compute_step
+                    FROM location
+                    INNER JOIN object o
+                        ON object_fk_id = o.object_id
+                    WHERE
+                        location.layer_fk_id = ?1
+                        AND o.layer_fk_id IN (SELECT layer_id FROM ancestors)
+                    GROUP BY object_fk_id, o.compute_step
+                )
+                WHERE max_t > ?2 AND ?2 - prev_t > compute_step"
+            ),
+            params![layer_id, requested_time.into_storage_duration()],
+            |row| row.get(0),
+        )?;
+
+        Ok(time.into_rust_duration())
+    }
+
+    fn compact(&mut self, session_id: SessionId, keyframe_step: chrono::Duration) -> Result<i64> {
+        // The initial locations, the collision points and the latest location
+        // of each keyframe interval are kept.
+        let removed_count = self.connection.execute(
+            "DELETE FROM location
+            WHERE location_id IN (
+                SELECT location_id
+                FROM (
+                    SELECT
+                        location_id,
+                        vcx,
+                        ROW_NUMBER() OVER (
+                            PARTITION BY object_fk_id, layer_fk_id
+                            ORDER BY t, location_id
+                        ) AS initial_rank,
+                        ROW_NUMBER() OVER (
+                            PARTITION BY object_fk_id, layer_fk_id, t / ?2
+                            ORDER BY t DESC, location_id DESC
+                        ) AS keyframe_rank
+                    FROM location
+                    INNER JOIN layer
+                        ON layer_fk_id = layer.layer_id
+                    WHERE layer.session_fk_id = ?1
+                )
+                WHERE initial_rank > 1 AND keyframe_rank > 1 AND vcx IS NULL
+            )",
+            params![session_id, keyframe_step.into_storage_duration()],
+        )?;
+
+        Ok(removed_count as i64)
+    }
+
+    fn get_range_locations(
+        &mut self,
+        layer_id: LayerId,
+        time_range: &TimeRange,
+        step_coeff: i16,
+    ) -> Result<Vec<LocationInfo>> {
+        self.range_locations(
+            layer_id,
+            time_range.start().into_storage_duration(),
+            time_range.end().into_storage_duration(),
+            step_coeff,
+        )
+    }
+
+    fn get_object_locations_around(
+        &mut self,
+        layer_id: LayerId,
+        object_id: ObjectId,
+        requested_time: chrono::Duration,
+    ) -> Result<Vec<(GenCoord, Option<Vector>)>> {
+        let requested_time = requested_time.into_storage_duration();
+
+        let locations = self
+            .range_locations(layer_id, requested_time, requested_time, 0)?
+            .into_iter()
+            .filter(|info| info.object_id == object_id)
+            .map(|info| {
+                let location = Vector::new(info.x, info.y, info.z);
+                let velocity = Vector::new(info.vx, info.vy, info.vz);
+
+                let collision_velocity = match (info.vcx, info.vcy, info.vcz) {
+                    (Some(vcx), Some(vcy), Some(vcz)) => Some(Vector::new(vcx, vcy, vcz)),
+                    _ => None,
+                };
+
+                (GenCoord::new(info.t, location, velocity), collision_velocity)
+            })
+            .collect();
+
+        Ok(locations)
+    }
+}
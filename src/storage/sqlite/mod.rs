@@ -0,0 +1,206 @@
+use {
+    super::{
+        BookmarkApi, IsolationLevel, JournalApi, LayerApi, LocationApi, ObjectApi, SessionApi,
+        StorageBackend, StorageTransaction,
+    },
+    crate::{
+        make_error,
+        r#type::{LayerId, RawTime},
+        Result,
+    },
+    rusqlite::{params, Connection},
+    std::path::PathBuf,
+};
+
+/// Prefixes the query with the common table `ancestors(layer_id, depth)`
+/// which contains the layer `?1` and all of its ancestors.
+/// The depth is 0 for the layer itself and grows towards the main layer.
+macro_rules! with_ancestors {
+    ($query:literal) => {
+        concat!(
+            "WITH RECURSIVE ancestors(layer_id, depth) AS (
+                SELECT ?1, 0
+                UNION
+                SELECT parent_layer_id, ancestors.depth + 1
+                FROM layer_family
+                INNER JOIN ancestors
+                    ON child_layer_id = ancestors.layer_id
+            ) ",
+            $query
+        )
+    };
+}
+
+mod bookmark;
+mod journal;
+mod layer;
+mod location;
+mod object;
+mod session;
+
+const BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+pub struct SqliteBackend {
+    path: PathBuf,
+    session_max_hang_time: chrono::Duration,
+}
+
+impl SqliteBackend {
+    pub fn setup(path: PathBuf, session_max_hang_time: chrono::Duration) -> Result<Self> {
+        let backend = Self {
+            path,
+            session_max_hang_time,
+        };
+
+        let connection = backend.connect()?;
+        connection.execute_batch("PRAGMA journal_mode = WAL;")?;
+        connection
+            .execute_batch(include_str!("sql/schema.sql"))
+            .map_err(|err| make_error![Error::Storage::Backend(format!(
+                "unable to setup schema: {}",
+                err
+            ))])?;
+
+        Ok(backend)
+    }
+
+    fn connect(&self) -> Result<Connection> {
+        let connection = Connection::open(&self.path)?;
+        connection.busy_timeout(BUSY_TIMEOUT)?;
+        connection.execute_batch("PRAGMA foreign_keys = ON;")?;
+
+        Ok(connection)
+    }
+}
+
+impl StorageBackend for SqliteBackend {
+    /// SQLite transactions are always serializable, so the isolation level is ignored.
+    /// Each transaction takes the write lock at once, so concurrent transactions wait
+    /// for each other instead of failing on the lock upgrade.
+    fn transaction(&self, _: IsolationLevel) -> Result<Box<dyn StorageTransaction>> {
+        let connection = self.connect()?;
+        connection.execute_batch("BEGIN IMMEDIATE")?;
+
+        Ok(Box::new(SqliteTransaction {
+            connection,
+            session_max_hang_time: self.session_max_hang_time,
+            is_committed: false,
+        }))
+    }
+}
+
+/// The transaction owns its own connection to the database file.
+///
+/// The transaction implements all the storage APIs, see the submodules.
+/// The logic of the PL/pgSQL functions of the Postgres backend lives here in Rust.
+pub struct SqliteTransaction {
+    connection: Connection,
+    session_max_hang_time: chrono::Duration,
+    is_committed: bool,
+}
+
+impl SqliteTransaction {
+    /// Returns the layer itself and all of its ancestors with their start times
+    /// ordered from the main layer down to the layer.
+    fn ancestors_start_times(&self, layer_id: LayerId) -> Result<Vec<(LayerId, RawTime)>> {
+        let mut statement = self.connection.prepare_cached(with_ancestors!(
+            "SELECT layer.layer_id, start_time
+            FROM layer
+            INNER JOIN ancestors
+                ON layer.layer_id = ancestors.layer_id
+            ORDER BY ancestors.depth DESC"
+        ))?;
+
+        let ancestors = statement
+            .query_map(params![layer_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(ancestors)
+    }
+
+    /// Returns the layer with all of its descendants as (layer, parent) pairs ordered by id.
+    /// The parent of the layer itself is `None`.
+    fn layer_subtree(&self, layer_id: LayerId) -> Result<Vec<(LayerId, Option<LayerId>)>> {
+        let mut statement = self.connection.prepare_cached(
+            "WITH RECURSIVE subtree(layer_id, parent_layer_id) AS (
+                SELECT ?1, NULL
+                UNION
+                SELECT child_layer_id, layer_family.parent_layer_id
+                FROM layer_family
+                INNER JOIN subtree
+                    ON layer_family.parent_layer_id = subtree.layer_id
+            ) SELECT layer_id, parent_layer_id FROM subtree ORDER BY layer_id",
+        )?;
+
+        let subtree = statement
+            .query_map(params![layer_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(subtree)
+    }
+
+    /// The same name if it is free, otherwise the name with the first free `-N` suffix.
+    fn unique_name(
+        base_name: &str,
+        mut is_taken: impl FnMut(&str) -> Result<bool>,
+    ) -> Result<String> {
+        let mut new_name = base_name.to_string();
+        let mut suffix_num = 0;
+
+        while is_taken(&new_name)? {
+            suffix_num += 1;
+
+            let suffix = format!("-{}", suffix_num);
+            let base_len = 50usize.saturating_sub(suffix.len());
+
+            new_name = base_name.chars().take(base_len).collect::<String>() + &suffix;
+        }
+
+        Ok(new_name)
+    }
+
+    fn backend_error(desc: String) -> crate::Error {
+        make_error![Error::Storage::Backend(desc)]
+    }
+}
+
+impl StorageTransaction for SqliteTransaction {
+    fn session(&mut self) -> &mut dyn SessionApi {
+        self
+    }
+
+    fn layer(&mut self) -> &mut dyn LayerApi {
+        self
+    }
+
+    fn object(&mut self) -> &mut dyn ObjectApi {
+        self
+    }
+
+    fn location(&mut self) -> &mut dyn LocationApi {
+        self
+    }
+
+    fn bookmark(&mut self) -> &mut dyn BookmarkApi {
+        self
+    }
+
+    fn journal(&mut self) -> &mut dyn JournalApi {
+        self
+    }
+
+    fn commit(mut self: Box<Self>) -> Result<()> {
+        self.connection.execute_batch("COMMIT")?;
+        self.is_committed = true;
+
+        Ok(())
+    }
+}
+
+impl Drop for SqliteTransaction {
+    fn drop(&mut self) {
+        if !self.is_committed {
+            let _ = self.connection.execute_batch("ROLLBACK");
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use super::SqliteTransaction;
+use crate::{
+    engine::context::db_util::{MassOverrideInfo, ObjectInfo},
+    graphics, object,
+    r#type::{
+        IntoRustDuration, IntoStorageDuration, LayerId, Mass, ObjectId, ObjectName, RawTime,
+        SessionId,
+    },
+    storage::ObjectApi,
+    Result,
+};
+use rusqlite::params;
+use std::collections::HashSet;
+
+impl SqliteTransaction {
+    /// Copies the object into the layer of the session under a free name.
+    pub(super) fn copy_object(
+        &mut self,
+        src_object_id: ObjectId,
+        dst_session_id: SessionId,
+        dst_layer_id: LayerId,
+    ) -> Result<(ObjectId, ObjectName)> {
+        let object_name: ObjectName = self.connection.query_row(
+            "SELECT object_name FROM object WHERE object_id = ?1",
+            params![src_object_id],
+            |row| row.get(0),
+        )?;
+
+        let new_object_name = Self::unique_name(&object_name, |name| {
+            self.is_object_exists(dst_session_id, &name.to_string())
+        })?;
+
+        self.connection.execute(
+            "INSERT INTO object(
+                session_fk_id,
+                layer_fk_id,
+                object_name,
+                radius,
+                color,
+                mass,
+                compute_step
+            )
+            SELECT ?2, ?3, ?4, radius, color, mass, compute_step
+            FROM object
+            WHERE object_id = ?1",
+            params![src_object_id, dst_session_id, dst_layer_id, new_object_name],
+        )?;
+
+        Ok((self.connection.last_insert_rowid(), new_object_name))
+    }
+}
+
+impl ObjectApi for SqliteTransaction {
+    fn add(
+        &mut self,
+        session_id: SessionId,
+        layer_id: LayerId,
+        object: object::Object,
+    ) -> Result<ObjectId> {
+        self.connection.execute(
+            "INSERT INTO object(
+                session_fk_id,
+                layer_fk_id,
+                object_name,
+                radius,
+                color,
+                mass,
+                compute_step
+            ) VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                session_id,
+                layer_id,
+                object.name(),
+                object.radius() as f64,
+                graphics::pack_color(object.color()),
+                object.mass() as f64,
+                object.compute_step().into_storage_duration(),
+            ],
+        )?;
+
+        Ok(self.connection.last_insert_rowid())
+    }
+
+    fn set_override(
+        &mut self,
+        layer_id: LayerId,
+        object_id: ObjectId,
+        start_time: Option<chrono::Duration>,
+        mass: Option<Mass>,
+        removed_time: Option<chrono::Duration>,
+    ) -> Result<()> {
+        self.connection.execute(
+            "INSERT INTO object_override
+            SELECT ?1, ?2, COALESCE(?3, start_time), ?4, ?5
+            FROM layer
+            WHERE layer_id = ?1
+            ON CONFLICT (layer_fk_id, object_fk_id, start_time) DO UPDATE
+            SET
+                mass = COALESCE(excluded.mass, object_override.mass),
+                removed_time = COALESCE(excluded.removed_time, object_override.removed_time)",
+            params![
+                layer_id,
+                object_id,
+                start_time.map(|time| time.into_storage_duration()),
+                mass.map(|mass| mass as f64),
+                removed_time.map(|time| time.into_storage_duration()),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    fn get_overridden(&mut self, active_layer_id: LayerId) -> Result<Vec<(LayerId, ObjectId)>> {
+        // The objects are overridden in the descendants of the layers introducing them.
+        let mut statement = self.connection.prepare(with_ancestors!(
+            ", object_subtree(object_id, layer_id) AS (
+                SELECT object_id, layer_fk_id
+                FROM object
+                WHERE layer_fk_id IN (SELECT layer_id FROM ancestors)
+                UNION
+                SELECT object_subtree.object_id, child_layer_id
+                FROM layer_family
+                INNER JOIN object_subtree
+                    ON parent_layer_id = object_subtree.layer_id
+            )
+            SELECT DISTINCT layer_fk_id, object_fk_id
+            FROM object_override
+            INNER JOIN object_subtree
+                ON object_fk_id = object_subtree.object_id
+                AND layer_fk_id = object_subtree.layer_id
+            ORDER BY layer_fk_id, object_fk_id"
+        ))?;
+
+        let overridden = statement
+            .query_map(params![active_layer_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(overridden)
+    }
+
+    fn is_object_exists(
+        &mut self,
+        session_id: SessionId,
+        object_name: &ObjectName,
+    ) -> Result<bool> {
+        let is_exists = self.connection.query_row(
+            "SELECT EXISTS(
+                SELECT 1 FROM object WHERE session_fk_id = ?1 AND object_name = ?2
+            )",
+            params![session_id, object_name],
+            |row| row.get(0),
+        )?;
+
+        Ok(is_exists)
+    }
+
+    fn get_max_object_default_name_num(&mut self, session_id: SessionId) -> Result<i64> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT object_name FROM object WHERE session_fk_id = ?1")?;
+
+        let names = statement
+            .query_map(params![session_id], |row| row.get::<_, ObjectName>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let max_num = names
+            .iter()
+            .filter_map(|name| name.strip_prefix("object-"))
+            .map(|num| {
+                if !num.is_empty() && num.chars().all(|c| c.is_ascii_digit()) {
+                    num.parse().unwrap_or(-1)
+                } else {
+                    -1
+                }
+            })
+            .max()
+            .unwrap_or(-1);
+
+        Ok(max_num)
+    }
+
+    fn get_objects_delta(
+        &mut self,
+        active_layer_id: LayerId,
+        known_objects_ids: &[ObjectId],
+    ) -> Result<Vec<ObjectInfo>> {
+        let known_objects_ids: HashSet<_> = known_objects_ids.iter().collect();
+
+        let mut statement = self.connection.prepare(with_ancestors!(
+            "SELECT
+                object_id,
+                layer_fk_id,
+                object_name,
+                radius,
+                color,
+                mass,
+                compute_step,
+                (
+                    SELECT MIN(o.removed_time)
+                    FROM object_override o
+                    WHERE
+                        o.object_fk_id = object_id
+                        AND o.layer_fk_id IN (SELECT layer_id FROM ancestors)
+                )
+            FROM object
+            WHERE layer_fk_id IN (SELECT layer_id FROM ancestors)
+            ORDER BY object_id"
+        ))?;
+
+        let objects = statement
+            .query_map(params![active_layer_id], |row| {
+                let radius: f64 = row.get(3)?;
+                let mass: f64 = row.get(5)?;
+                let compute_step: RawTime = row.get(6)?;
+                let removed_time: Option<RawTime> = row.get(7)?;
+
+                let object = object::Object::new(
+                    row.get(1)?,
+                    row.get(2)?,
+                    radius as _,
+                    graphics::unpack_color(&row.get(4)?),
+                    mass as _,
+                    compute_step.into_rust_duration(),
+                );
+
+                Ok(ObjectInfo(
+                    row.get(0)?,
+                    object,
+                    removed_time.map(|time| time.into_rust_duration()),
+                ))
+            })?
+            .filter(|info| match info {
+                Ok(ObjectInfo(object_id, ..)) => !known_objects_ids.contains(object_id),
+                Err(_) => true,
+            })
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(objects)
+    }
+
+    fn get_mass_overrides(&mut self, active_layer_id: LayerId) -> Result<Vec<MassOverrideInfo>> {
+        let mut statement = self.connection.prepare(with_ancestors!(
+            "SELECT o.object_fk_id, o.start_time, o.mass
+            FROM object_override o
+            INNER JOIN ancestors
+                ON o.layer_fk_id = ancestors.layer_id
+            WHERE o.mass IS NOT NULL
+            ORDER BY o.object_fk_id, o.start_time, ancestors.depth DESC"
+        ))?;
+
+        let mass_overrides = statement
+            .query_map(params![active_layer_id], |row| {
+                let start_time: RawTime = row.get(1)?;
+                let mass: f64 = row.get(2)?;
+
+                Ok(MassOverrideInfo(
+                    row.get(0)?,
+                    start_time.into_rust_duration(),
+                    mass as _,
+                ))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(mass_overrides)
+    }
+}
@@ -0,0 +1,344 @@
+use super::SqliteTransaction;
+use crate::{
+    r#type::{Coord, LayerId, RawTime, SessionId, SessionInfo, SessionName},
+    storage::{
+        dump::{BookmarkDump, LayerDump, LocationDump, ObjectDump, OverrideDump, SessionDump},
+        SessionApi,
+    },
+    Result,
+};
+use chrono::TimeZone;
+use rusqlite::{params, OptionalExtension, NO_PARAMS};
+
+impl SqliteTransaction {
+    pub(super) fn now() -> RawTime {
+        chrono::Local::now().timestamp_millis()
+    }
+
+    pub(super) fn main_layer_id(&self, session_id: SessionId) -> Result<LayerId> {
+        let layer_id = self.connection.query_row(
+            "SELECT MIN(layer_id) FROM layer WHERE session_fk_id = ?1",
+            params![session_id],
+            |row| row.get(0),
+        )?;
+
+        Ok(layer_id)
+    }
+
+    /// Unlocks the hanged sessions and removes the unlocked unnamed ones
+    /// (the session triggers of the Postgres backend).
+    pub(super) fn maintain_sessions(&self) -> Result<()> {
+        self.connection.execute(
+            "UPDATE session
+            SET is_locked = 0
+            WHERE is_locked = 1 AND ?1 - last_access > ?2",
+            params![Self::now(), self.session_max_hang_time.num_milliseconds()],
+        )?;
+
+        self.connection.execute(
+            "DELETE FROM session WHERE is_locked = 0 AND session_name IS NULL",
+            NO_PARAMS,
+        )?;
+
+        Ok(())
+    }
+
+    fn session_name(&self, id: SessionId) -> Result<Option<SessionName>> {
+        let name = self
+            .connection
+            .query_row(
+                "SELECT session_name FROM session WHERE session_id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+
+        Ok(name)
+    }
+
+    pub(super) fn locked_or_not_exists(name: &str) -> crate::Error {
+        Self::backend_error(format!("session `{}` is either locked or not exists", name))
+    }
+
+    fn not_exists(name: &str) -> crate::Error {
+        Self::backend_error(format!("session `{}` not exists", name))
+    }
+}
+
+impl SessionApi for SqliteTransaction {
+    fn new(&mut self, name: Option<SessionName>) -> Result<(SessionId, LayerId)> {
+        self.connection.execute(
+            "INSERT INTO session(session_name, last_access, is_locked) VALUES(?1, ?2, 1)",
+            params![name, Self::now()],
+        )?;
+
+        let session_id = self.connection.last_insert_rowid() as SessionId;
+
+        self.connection.execute(
+            "INSERT INTO layer(session_fk_id, layer_name, start_time) VALUES(?1, 'main', 0)",
+            params![session_id],
+        )?;
+
+        self.maintain_sessions()?;
+
+        Ok((session_id, self.main_layer_id(session_id)?))
+    }
+
+    fn update_access_time(&mut self, id: SessionId) -> Result<()> {
+        self.connection.execute(
+            "UPDATE session SET last_access = ?2 WHERE session_id = ?1",
+            params![id, Self::now()],
+        )?;
+
+        self.maintain_sessions()
+    }
+
+    fn unlock(&mut self, id: SessionId) -> Result<()> {
+        self.connection.execute(
+            "UPDATE session SET is_locked = 0 WHERE session_id = ?1",
+            params![id],
+        )?;
+
+        self.maintain_sessions()
+    }
+
+    fn save(&mut self, id: SessionId, name: &str) -> Result<()> {
+        self.connection.execute(
+            "UPDATE session SET session_name = ?2 WHERE session_id = ?1",
+            params![id, name],
+        )?;
+
+        Ok(())
+    }
+
+    fn load(&mut self, name: &str) -> Result<(SessionId, LayerId)> {
+        let session_id: SessionId = self
+            .connection
+            .query_row(
+                "SELECT session_id FROM session WHERE session_name = ?1 AND is_locked = 0",
+                params![name],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| Self::locked_or_not_exists(name))?;
+
+        self.connection.execute(
+            "UPDATE session SET last_access = ?2, is_locked = 1 WHERE session_id = ?1",
+            params![session_id, Self::now()],
+        )?;
+
+        self.maintain_sessions()?;
+
+        Ok((session_id, self.main_layer_id(session_id)?))
+    }
+
+    fn attach(&mut self, name: &str) -> Result<(SessionId, LayerId)> {
+        let session_id: SessionId = self
+            .connection
+            .query_row(
+                "SELECT session_id FROM session WHERE session_name = ?1",
+                params![name],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| Self::not_exists(name))?;
+
+        Ok((session_id, self.main_layer_id(session_id)?))
+    }
+
+    fn get_unlocked_id(&mut self, name: &str, current_id: SessionId) -> Result<SessionId> {
+        self.connection
+            .query_row(
+                "SELECT session_id
+                FROM session
+                WHERE session_name = ?1 AND (is_locked = 0 OR session_id = ?2)",
+                params![name, current_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| Self::locked_or_not_exists(name))
+    }
+
+    fn rename(&mut self, old_name: &str, new_name: &str) -> Result<()> {
+        let renamed = self.connection.execute(
+            "UPDATE session SET session_name = ?2 WHERE session_name = ?1",
+            params![old_name, new_name],
+        )?;
+
+        if renamed == 0 {
+            return Err(Self::backend_error(format!("session `{}` not found", old_name)));
+        }
+
+        Ok(())
+    }
+
+    fn get_list(&mut self) -> Result<Vec<SessionInfo>> {
+        let mut statement = self.connection.prepare(
+            "SELECT session_name, last_access, is_locked
+            FROM session
+            WHERE session_name IS NOT NULL
+            ORDER BY session_name",
+        )?;
+
+        let infos = statement
+            .query_map(NO_PARAMS, |row| {
+                let last_access: RawTime = row.get(1)?;
+
+                Ok(SessionInfo {
+                    name: row.get(0)?,
+                    last_access: chrono::Local.timestamp_millis(last_access),
+                    is_locked: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(infos)
+    }
+
+    fn get_name(&mut self, id: SessionId) -> Result<SessionName> {
+        Ok(self.session_name(id)?.unwrap_or("/unnamed/".into()))
+    }
+
+    fn dump(&mut self, id: SessionId, with_computed: bool) -> Result<SessionDump> {
+        let mut dump = SessionDump::new(self.session_name(id)?);
+
+        let mut statement = self.connection.prepare(
+            "SELECT layer_id, parent_layer_id, layer_name, start_time
+            FROM layer
+            LEFT JOIN layer_family
+                ON child_layer_id = layer_id
+            WHERE session_fk_id = ?1
+            ORDER BY layer_id",
+        )?;
+
+        dump.layers = statement
+            .query_map(params![id], |row| {
+                Ok(LayerDump {
+                    id: row.get(0)?,
+                    parent_id: row.get(1)?,
+                    name: row.get(2)?,
+                    start_time: row.get(3)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let mut statement = self.connection.prepare(
+            "SELECT object_id, layer_fk_id, object_name, radius, color, mass, compute_step
+            FROM object
+            WHERE session_fk_id = ?1
+            ORDER BY object_id",
+        )?;
+
+        dump.objects = statement
+            .query_map(params![id], |row| {
+                let radius: f64 = row.get(3)?;
+                let mass: f64 = row.get(5)?;
+
+                Ok(ObjectDump {
+                    id: row.get(0)?,
+                    layer_id: row.get(1)?,
+                    name: row.get(2)?,
+                    radius: radius as _,
+                    color: row.get(4)?,
+                    mass: mass as _,
+                    compute_step: row.get(6)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let mut statement = self.connection.prepare(
+            "SELECT
+                object_override.layer_fk_id,
+                object_fk_id,
+                object_override.start_time,
+                object_override.mass,
+                removed_time
+            FROM object_override
+            INNER JOIN layer
+                ON object_override.layer_fk_id = layer_id
+            WHERE session_fk_id = ?1
+            ORDER BY object_override.layer_fk_id, object_fk_id, object_override.start_time",
+        )?;
+
+        dump.overrides = statement
+            .query_map(params![id], |row| {
+                let mass: Option<f64> = row.get(3)?;
+
+                Ok(OverrideDump {
+                    layer_id: row.get(0)?,
+                    object_id: row.get(1)?,
+                    start_time: row.get(2)?,
+                    mass: mass.map(|mass| mass as _),
+                    removed_time: row.get(4)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        let mut statement = self.connection.prepare(
+            "SELECT layer_fk_id, bookmark_name, t
+            FROM bookmark
+            WHERE session_fk_id = ?1
+            ORDER BY t, bookmark_name",
+        )?;
+
+        dump.bookmarks = statement
+            .query_map(params![id], |row| {
+                Ok(BookmarkDump {
+                    layer_id: row.get(0)?,
+                    name: row.get(1)?,
+                    t: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        // The initial location is the earliest one of the object in the layer.
+        let mut statement = self.connection.prepare(
+            "SELECT object_fk_id, layer_fk_id, t, x, y, z, vx, vy, vz, vcx, vcy, vcz
+            FROM (
+                SELECT
+                    location.*,
+                    ROW_NUMBER() OVER (
+                        PARTITION BY object_fk_id, layer_fk_id
+                        ORDER BY t, location_id
+                    ) AS location_rank
+                FROM location
+                INNER JOIN layer
+                    ON layer_fk_id = layer_id
+                WHERE session_fk_id = ?1
+            )
+            WHERE ?2 OR location_rank = 1
+            ORDER BY location_id",
+        )?;
+
+        dump.locations = statement
+            .query_map(params![id, with_computed], |row| {
+                let coord = |idx: usize| row.get::<_, f64>(idx).map(|c| c as Coord);
+                let opt_coord = |idx: usize| -> rusqlite::Result<Option<Coord>> {
+                    if with_computed {
+                        row.get::<_, Option<f64>>(idx).map(|c| c.map(|c| c as Coord))
+                    } else {
+                        Ok(None)
+                    }
+                };
+
+                Ok(LocationDump {
+                    object_id: row.get(0)?,
+                    layer_id: row.get(1)?,
+                    t: row.get(2)?,
+                    x: coord(3)?,
+                    y: coord(4)?,
+                    z: coord(5)?,
+                    vx: coord(6)?,
+                    vy: coord(7)?,
+                    vz: coord(8)?,
+                    vcx: opt_coord(9)?,
+                    vcy: opt_coord(10)?,
+                    vcz: opt_coord(11)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(dump)
+    }
+}
@@ -0,0 +1,232 @@
+-- AUTOINCREMENT keeps the ids of the buried rows (see the tombstones below) from being reused.
+
+CREATE TABLE IF NOT EXISTS session
+(
+    session_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_name TEXT UNIQUE,
+    last_access INTEGER NOT NULL, -- unix time in milliseconds
+    is_locked INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS layer
+(
+    layer_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_fk_id INTEGER
+        NOT NULL
+        REFERENCES session(session_id)
+        ON DELETE CASCADE,
+    layer_name TEXT NOT NULL,
+    start_time INTEGER NOT NULL,
+
+    UNIQUE(session_fk_id, layer_name)
+);
+
+CREATE TABLE IF NOT EXISTS layer_family
+(
+    parent_layer_id INTEGER
+        NOT NULL
+        REFERENCES layer(layer_id)
+        ON DELETE CASCADE,
+    child_layer_id INTEGER
+        NOT NULL
+        REFERENCES layer(layer_id)
+        ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS layer_family_child ON layer_family(child_layer_id);
+CREATE INDEX IF NOT EXISTS layer_family_parent ON layer_family(parent_layer_id);
+
+CREATE TABLE IF NOT EXISTS object
+(
+    object_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_fk_id INTEGER
+        NOT NULL
+        REFERENCES session(session_id)
+        ON DELETE CASCADE,
+    layer_fk_id INTEGER
+        NOT NULL
+        REFERENCES layer(layer_id)
+        ON DELETE CASCADE,
+    object_name TEXT NOT NULL,
+    radius REAL NOT NULL,
+    color INTEGER NOT NULL,
+    mass REAL NOT NULL,
+    compute_step INTEGER NOT NULL,
+
+    UNIQUE (session_fk_id, object_name)
+);
+
+CREATE INDEX IF NOT EXISTS object_layer ON object(layer_fk_id);
+
+CREATE TABLE IF NOT EXISTS location
+(
+    location_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    object_fk_id INTEGER
+        NOT NULL
+        REFERENCES object(object_id)
+        ON DELETE CASCADE,
+    layer_fk_id INTEGER
+        NOT NULL
+        REFERENCES layer(layer_id)
+        ON DELETE CASCADE,
+    t INTEGER NOT NULL,
+    x REAL NOT NULL,
+    y REAL NOT NULL,
+    z REAL NOT NULL,
+    vx REAL NOT NULL,
+    vy REAL NOT NULL,
+    vz REAL NOT NULL,
+
+    vcx REAL NULL, -- vx after collision
+    vcy REAL NULL, -- vy after collision
+    vcz REAL NULL  -- vz after collision
+);
+
+CREATE INDEX IF NOT EXISTS location_object_layer_t ON location(object_fk_id, layer_fk_id, t);
+CREATE INDEX IF NOT EXISTS location_layer ON location(layer_fk_id);
+
+CREATE TABLE IF NOT EXISTS collision_partners
+(
+    location_fk_id INTEGER
+        NOT NULL
+        REFERENCES location(location_id)
+        ON DELETE CASCADE,
+    partner_fk_id INTEGER
+        NOT NULL
+        REFERENCES location(location_id)
+        ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS object_override
+(
+    layer_fk_id INTEGER
+        NOT NULL
+        REFERENCES layer(layer_id)
+        ON DELETE CASCADE,
+    object_fk_id INTEGER
+        NOT NULL
+        REFERENCES object(object_id)
+        ON DELETE CASCADE,
+    start_time INTEGER NOT NULL, -- the start time of the layer the override was created in
+    mass REAL NULL,
+    removed_time INTEGER NULL,
+
+    PRIMARY KEY (layer_fk_id, object_fk_id, start_time)
+);
+
+CREATE TABLE IF NOT EXISTS bookmark
+(
+    bookmark_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_fk_id INTEGER
+        NOT NULL
+        REFERENCES session(session_id)
+        ON DELETE CASCADE,
+    layer_fk_id INTEGER
+        NOT NULL
+        REFERENCES layer(layer_id)
+        ON DELETE CASCADE,
+    bookmark_name TEXT NOT NULL,
+    t INTEGER NOT NULL,
+
+    UNIQUE (session_fk_id, bookmark_name)
+);
+
+CREATE TABLE IF NOT EXISTS journal
+(
+    journal_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_fk_id INTEGER
+        NOT NULL
+        REFERENCES session(session_id)
+        ON DELETE CASCADE,
+    operation TEXT NOT NULL, -- 'rm-layer', 'rename-layer' or 'delete-session'
+    target_id INTEGER NOT NULL, -- the layer's or the session's id
+    old_name TEXT NULL,
+    new_name TEXT NULL,
+    is_undone INTEGER NOT NULL DEFAULT 0,
+    record_time INTEGER NOT NULL -- unix time in milliseconds
+);
+
+-- Tombstones keep the deleted rows until the journal record is purged.
+
+CREATE TABLE IF NOT EXISTS tomb_session
+(
+    journal_fk_id INTEGER NOT NULL REFERENCES journal(journal_id) ON DELETE CASCADE,
+    session_id INTEGER NOT NULL,
+    session_name TEXT,
+    last_access INTEGER NOT NULL,
+    is_locked INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS tomb_layer
+(
+    journal_fk_id INTEGER NOT NULL REFERENCES journal(journal_id) ON DELETE CASCADE,
+    layer_id INTEGER NOT NULL,
+    session_fk_id INTEGER NOT NULL,
+    layer_name TEXT NOT NULL,
+    start_time INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS tomb_layer_family
+(
+    journal_fk_id INTEGER NOT NULL REFERENCES journal(journal_id) ON DELETE CASCADE,
+    parent_layer_id INTEGER NOT NULL,
+    child_layer_id INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS tomb_object
+(
+    journal_fk_id INTEGER NOT NULL REFERENCES journal(journal_id) ON DELETE CASCADE,
+    object_id INTEGER NOT NULL,
+    session_fk_id INTEGER NOT NULL,
+    layer_fk_id INTEGER NOT NULL,
+    object_name TEXT NOT NULL,
+    radius REAL NOT NULL,
+    color INTEGER NOT NULL,
+    mass REAL NOT NULL,
+    compute_step INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS tomb_location
+(
+    journal_fk_id INTEGER NOT NULL REFERENCES journal(journal_id) ON DELETE CASCADE,
+    location_id INTEGER NOT NULL,
+    object_fk_id INTEGER NOT NULL,
+    layer_fk_id INTEGER NOT NULL,
+    t INTEGER NOT NULL,
+    x REAL NOT NULL,
+    y REAL NOT NULL,
+    z REAL NOT NULL,
+    vx REAL NOT NULL,
+    vy REAL NOT NULL,
+    vz REAL NOT NULL,
+    vcx REAL NULL,
+    vcy REAL NULL,
+    vcz REAL NULL
+);
+
+CREATE TABLE IF NOT EXISTS tomb_collision_partners
+(
+    journal_fk_id INTEGER NOT NULL REFERENCES journal(journal_id) ON DELETE CASCADE,
+    location_fk_id INTEGER NOT NULL,
+    partner_fk_id INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS tomb_object_override
+(
+    journal_fk_id INTEGER NOT NULL REFERENCES journal(journal_id) ON DELETE CASCADE,
+    layer_fk_id INTEGER NOT NULL,
+    object_fk_id INTEGER NOT NULL,
+    start_time INTEGER NOT NULL,
+    mass REAL NULL,
+    removed_time INTEGER NULL
+);
+
+CREATE TABLE IF NOT EXISTS tomb_bookmark
+(
+    journal_fk_id INTEGER NOT NULL REFERENCES journal(journal_id) ON DELETE CASCADE,
+    bookmark_id INTEGER NOT NULL,
+    session_fk_id INTEGER NOT NULL,
+    layer_fk_id INTEGER NOT NULL,
+    bookmark_name TEXT NOT NULL,
+    t INTEGER NOT NULL
+);
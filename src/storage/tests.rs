@@ -0,0 +1,689 @@
+//! The in-memory and SQLite ports of the Postgres functions are checked
+//! against the semantics of the Postgres ones.
+
+use {
+    super::{memory::MemoryBackend, sqlite::SqliteBackend, StorageBackend, StorageManager},
+    crate::{
+        engine::context::{Context, TimeRange, UpdateKind},
+        object::{GenCoord, Object},
+        r#type::{
+            Color, Coord, IntoRustDuration, IntoStorageDuration, LayerId, Mass, ObjectId, RawTime,
+            SessionId, Vector,
+        },
+        transaction, Result,
+    },
+    std::{
+        path::{Path, PathBuf},
+        sync::{mpsc, Arc},
+    },
+};
+
+/// The times are multiples of the step, so they are exact in the relative time too.
+const STEP: RawTime = 125;
+
+const MASS: Mass = 1.0;
+
+fn ms(time: RawTime) -> chrono::Duration {
+    time.into_rust_duration()
+}
+
+fn session_max_hang_time() -> chrono::Duration {
+    chrono::Duration::minutes(1)
+}
+
+struct TestSession {
+    storage_mgr: StorageManager,
+    session_id: SessionId,
+    main_layer_id: LayerId,
+
+    /// The database file removed with the session.
+    db_path: Option<PathBuf>,
+}
+
+impl TestSession {
+    fn memory() -> Result<Self> {
+        let backend = MemoryBackend::setup(session_max_hang_time());
+
+        Self::new(Arc::new(backend), None)
+    }
+
+    fn sqlite(test_name: &str) -> Result<Self> {
+        let db_path = std::env::temp_dir().join(format!(
+            "storage-test-{}-{}.db",
+            std::process::id(),
+            test_name
+        ));
+
+        remove_db_files(&db_path);
+        let backend = SqliteBackend::setup(db_path.clone(), session_max_hang_time())?;
+
+        Self::new(Arc::new(backend), Some(db_path))
+    }
+
+    fn new(backend: Arc<dyn StorageBackend>, db_path: Option<PathBuf>) -> Result<Self> {
+        let storage_mgr = StorageManager { backend };
+
+        let session_id;
+        let main_layer_id;
+
+        transaction! {
+            storage_mgr => t {
+                let (new_session_id, new_main_layer_id) = t.session().new(None)?;
+
+                session_id = new_session_id;
+                main_layer_id = new_main_layer_id;
+            }
+        }
+
+        Ok(Self {
+            storage_mgr,
+            session_id,
+            main_layer_id,
+            db_path,
+        })
+    }
+
+    fn add_layer(
+        &self,
+        active_layer_id: LayerId,
+        name: &str,
+        start_time: RawTime,
+    ) -> Result<LayerId> {
+        let layer_id;
+
+        transaction! {
+            self.storage_mgr => t {
+                layer_id = t.layer().add_layer(
+                    self.session_id,
+                    active_layer_id,
+                    &name.to_string(),
+                    ms(start_time)
+                )?;
+            }
+        }
+
+        Ok(layer_id)
+    }
+
+    /// Adds a resting object with the initial location at the time.
+    fn add_object(
+        &self,
+        layer_id: LayerId,
+        name: &str,
+        step: RawTime,
+        x: Coord,
+        start_time: RawTime,
+    ) -> Result<ObjectId> {
+        let object = Object::new(
+            layer_id,
+            name.to_string(),
+            1.0,
+            Color::new(1.0, 1.0, 1.0),
+            MASS,
+            ms(step),
+        );
+
+        let object_id;
+
+        transaction! {
+            self.storage_mgr => t {
+                object_id = t.object().add(self.session_id, layer_id, object)?;
+            }
+        }
+
+        self.add_location(layer_id, object_id, x, start_time)?;
+
+        Ok(object_id)
+    }
+
+    fn add_location(
+        &self,
+        layer_id: LayerId,
+        object_id: ObjectId,
+        x: Coord,
+        t: RawTime,
+    ) -> Result<()> {
+        let coord = GenCoord::new(ms(t), Vector::new(x, 0.0, 0.0), Vector::zeros());
+
+        transaction! {
+            self.storage_mgr => t {
+                t.location().add(object_id, layer_id, coord)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds the object's locations in [start; stop] with the step
+    /// marked by the layer id in the `x` coordinate.
+    fn add_track(
+        &self,
+        layer_id: LayerId,
+        object_id: ObjectId,
+        step: RawTime,
+        start: RawTime,
+        stop: RawTime,
+    ) -> Result<()> {
+        for t in (start..=stop).step_by(step as usize) {
+            self.add_location(layer_id, object_id, layer_id as Coord, t)?;
+        }
+
+        Ok(())
+    }
+
+    fn set_override(
+        &self,
+        layer_id: LayerId,
+        object_id: ObjectId,
+        mass: Option<Mass>,
+        removed_time: Option<RawTime>,
+    ) -> Result<()> {
+        transaction! {
+            self.storage_mgr => t {
+                t.object().set_override(layer_id, object_id, None, mass, removed_time.map(ms))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Merges the layer into its parent, returns the count of the replaced parent's locations.
+    fn merge_layer(&self, layer_id: LayerId) -> Result<i64> {
+        let replaced_locations;
+
+        transaction! {
+            self.storage_mgr => t {
+                replaced_locations = t.layer().get_merge_info(layer_id)?.replaced_locations;
+                t.layer().merge_layer(layer_id)?;
+            }
+        }
+
+        Ok(replaced_locations)
+    }
+
+    /// Computes and stores the layer's tracks in [0; stop].
+    fn update(&self, layer_id: LayerId, stop: RawTime) -> Result<Context> {
+        let time_range = TimeRange::with_bounds(ms(0), ms(stop));
+        let context = Context::with_time_range(self.session_id, layer_id, time_range.clone());
+
+        let (_interrupt_sender, interrupter) = mpsc::channel();
+        let (progress_sender, _progress_receiver) = mpsc::channel();
+
+        context.update_content(
+            self.storage_mgr.clone(),
+            UpdateKind::Initial(time_range),
+            false,
+            interrupter,
+            progress_sender,
+        )
+    }
+
+    /// Returns the object's (time, x) pairs visible from the layer.
+    fn range_locations(
+        &self,
+        layer_id: LayerId,
+        object_id: ObjectId,
+        start: RawTime,
+        stop: RawTime,
+        step_coeff: i16,
+    ) -> Result<Vec<(RawTime, Coord)>> {
+        let locations;
+
+        transaction! {
+            self.storage_mgr => t {
+                locations = t.location().get_range_locations(
+                    layer_id,
+                    &TimeRange::with_bounds(ms(start), ms(stop)),
+                    step_coeff
+                )?;
+            }
+        }
+
+        let locations = locations
+            .into_iter()
+            .filter(|location| location.object_id == object_id)
+            .map(|location| (location.t.into_storage_duration(), location.x))
+            .collect();
+
+        Ok(locations)
+    }
+
+    fn range_times(
+        &self,
+        layer_id: LayerId,
+        object_id: ObjectId,
+        start: RawTime,
+        stop: RawTime,
+        step_coeff: i16,
+    ) -> Result<Vec<RawTime>> {
+        let times = self
+            .range_locations(layer_id, object_id, start, stop, step_coeff)?
+            .into_iter()
+            .map(|(t, _)| t)
+            .collect();
+
+        Ok(times)
+    }
+}
+
+impl Drop for TestSession {
+    fn drop(&mut self) {
+        if let Some(db_path) = &self.db_path {
+            remove_db_files(db_path);
+        }
+    }
+}
+
+fn remove_db_files(db_path: &Path) {
+    for suffix in &["", "-wal", "-shm"] {
+        let mut path = db_path.as_os_str().to_owned();
+        path.push(suffix);
+
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Runs each check against every backend which can be set up locally.
+macro_rules! backend_tests {
+    ($($check:ident),+ $(,)?) => {
+        mod memory {
+            $(
+                #[test]
+                fn $check() -> $crate::Result<()> {
+                    super::$check(super::TestSession::memory()?)
+                }
+            )+
+        }
+
+        mod sqlite {
+            $(
+                #[test]
+                fn $check() -> $crate::Result<()> {
+                    super::$check(super::TestSession::sqlite(stringify!($check))?)
+                }
+            )+
+        }
+    };
+}
+
+fn times(start: RawTime, stop: RawTime, step: RawTime) -> Vec<RawTime> {
+    (start..=stop).step_by(step as usize).collect()
+}
+
+fn assert_continuous(times: &[RawTime], step: RawTime) {
+    for pair in times.windows(2) {
+        assert_eq!(pair[1] - pair[0], step, "the track has a gap: {:?}", times);
+    }
+}
+
+fn range_locations_extend_the_range_by_the_object_step(session: TestSession) -> Result<()> {
+    let main_layer_id = session.main_layer_id;
+
+    let fast_id = session.add_object(main_layer_id, "fast", STEP, 0.0, 0)?;
+    let slow_id = session.add_object(main_layer_id, "slow", 2 * STEP, 100.0, 0)?;
+
+    session.add_track(main_layer_id, fast_id, STEP, STEP, 2000)?;
+    session.add_track(main_layer_id, slow_id, 2 * STEP, 2 * STEP, 2000)?;
+
+    // Both sides are extended by the step for the initial loading.
+    assert_eq!(
+        session.range_times(main_layer_id, fast_id, 500, 1000, 0)?,
+        times(375, 1125, STEP)
+    );
+    assert_eq!(
+        session.range_times(main_layer_id, slow_id, 500, 1000, 0)?,
+        times(250, 1250, 2 * STEP)
+    );
+
+    // The range is shifted by the step multiplied by the coefficient.
+    assert_eq!(
+        session.range_times(main_layer_id, fast_id, 500, 1000, 1)?,
+        times(625, 1125, STEP)
+    );
+    assert_eq!(
+        session.range_times(main_layer_id, fast_id, 500, 1000, -1)?,
+        times(375, 875, STEP)
+    );
+    assert_eq!(
+        session.range_times(main_layer_id, slow_id, 500, 1000, 2)?,
+        times(1000, 1500, 2 * STEP)
+    );
+
+    Ok(())
+}
+
+fn range_locations_switch_to_the_descendant_track_at_its_first_location(
+    session: TestSession,
+) -> Result<()> {
+    let main_layer_id = session.main_layer_id;
+
+    let object_id = session.add_object(main_layer_id, "object", STEP, main_layer_id as Coord, 0)?;
+    session.add_track(main_layer_id, object_id, STEP, STEP, 2000)?;
+
+    let child_id = session.add_layer(main_layer_id, "child", 750)?;
+    session.add_track(child_id, object_id, STEP, 1000, 2000)?;
+
+    let grandchild_id = session.add_layer(child_id, "grandchild", 1250)?;
+    session.add_track(grandchild_id, object_id, STEP, 1500, 2000)?;
+
+    let locations = session.range_locations(grandchild_id, object_id, 0, 2000, 0)?;
+
+    // Each time is taken once, the descendant's location wins at the boundary.
+    let times: Vec<_> = locations.iter().map(|(t, _)| *t).collect();
+    assert_eq!(times, self::times(0, 2000, STEP));
+
+    for (t, x) in locations {
+        let expected_layer_id = match t {
+            t if t < 1000 => main_layer_id,
+            t if t < 1500 => child_id,
+            _ => grandchild_id,
+        };
+
+        assert_eq!(x, expected_layer_id as Coord, "wrong layer at {}", t);
+    }
+
+    // The ancestors don't see the descendants' tracks.
+    let locations = session.range_locations(main_layer_id, object_id, 0, 2000, 0)?;
+    assert!(locations.iter().all(|(_, x)| *x == main_layer_id as Coord));
+
+    let locations = session.range_locations(child_id, object_id, 0, 2000, 0)?;
+    assert!(locations
+        .iter()
+        .all(|(t, x)| *t < 1000 || *x == child_id as Coord));
+
+    Ok(())
+}
+
+fn range_locations_stop_at_the_inherited_removal_time(session: TestSession) -> Result<()> {
+    let main_layer_id = session.main_layer_id;
+
+    let object_id = session.add_object(main_layer_id, "object", STEP, 0.0, 0)?;
+    session.add_track(main_layer_id, object_id, STEP, STEP, 2000)?;
+
+    let child_id = session.add_layer(main_layer_id, "child", 500)?;
+    session.set_override(child_id, object_id, None, Some(1000))?;
+
+    let grandchild_id = session.add_layer(child_id, "grandchild", 750)?;
+    session.set_override(grandchild_id, object_id, None, Some(1500))?;
+
+    assert_eq!(
+        session.range_times(main_layer_id, object_id, 0, 2000, 0)?,
+        times(0, 2000, STEP)
+    );
+
+    // The earliest removal among the ancestors is applied, the removal time itself is kept.
+    assert_eq!(
+        session.range_times(child_id, object_id, 0, 2000, 0)?,
+        times(0, 1000, STEP)
+    );
+    assert_eq!(
+        session.range_times(grandchild_id, object_id, 0, 2000, 0)?,
+        times(0, 1000, STEP)
+    );
+
+    Ok(())
+}
+
+fn update_content_extends_the_inherited_tracks_in_the_active_layer(
+    session: TestSession,
+) -> Result<()> {
+    let main_layer_id = session.main_layer_id;
+
+    let inherited_id = session.add_object(main_layer_id, "inherited", STEP, 0.0, 0)?;
+
+    let context = session.update(main_layer_id, 1000)?;
+    assert_eq!(context.actors().len(), 1);
+
+    let main_times = session.range_times(main_layer_id, inherited_id, 0, 2000, 0)?;
+    assert_eq!(main_times, times(0, 1000 + STEP, STEP));
+
+    let child_id = session.add_layer(main_layer_id, "child", 500)?;
+    let own_id = session.add_object(child_id, "own", STEP, 100.0, 500)?;
+
+    let context = session.update(child_id, 2000)?;
+    assert_eq!(context.actors().len(), 2);
+    assert_eq!(context.actor_id(&"own".to_string()), Some(own_id));
+
+    // The inherited track is continued from the parent's last location.
+    let child_times = session.range_times(child_id, inherited_id, 0, 2000, 0)?;
+    assert_eq!(child_times.first(), Some(&0));
+    assert!(*child_times.last().unwrap() >= 2000);
+    assert_continuous(&child_times, STEP);
+
+    let own_times = session.range_times(child_id, own_id, 0, 2000, 0)?;
+    assert_eq!(own_times.first(), Some(&500));
+    assert!(*own_times.last().unwrap() >= 2000);
+    assert_continuous(&own_times, STEP);
+
+    // The new locations are stored into the active layer only.
+    assert_eq!(
+        session.range_times(main_layer_id, inherited_id, 0, 2000, 0)?,
+        main_times
+    );
+    assert!(session
+        .range_times(main_layer_id, own_id, 0, 2000, 0)?
+        .is_empty());
+
+    let context = session.update(main_layer_id, 2000)?;
+    assert_eq!(context.actors().len(), 1);
+
+    Ok(())
+}
+
+fn update_content_applies_the_overrides_from_the_overriding_layer(
+    session: TestSession,
+) -> Result<()> {
+    let main_layer_id = session.main_layer_id;
+
+    let object_id = session.add_object(main_layer_id, "object", STEP, 0.0, 0)?;
+
+    let child_id = session.add_layer(main_layer_id, "child", 500)?;
+    session.set_override(child_id, object_id, Some(5.0), Some(1500))?;
+
+    let grandchild_id = session.add_layer(child_id, "grandchild", 1000)?;
+    session.set_override(grandchild_id, object_id, Some(7.0), None)?;
+
+    let context = session.update(child_id, 2000)?;
+    let actor = context.actor(&object_id);
+
+    assert_eq!(actor.removed_time(), Some(ms(1500)));
+    assert_eq!(actor.mass_at(ms(500 - STEP)), MASS);
+    assert_eq!(actor.mass_at(ms(500)), 5.0);
+
+    // The track stops exactly at the removal time.
+    assert_eq!(
+        session.range_times(child_id, object_id, 0, 2000, 0)?,
+        times(0, 1500, STEP)
+    );
+
+    // The removal is inherited, each mass override applies from its own layer's start.
+    let context = session.update(grandchild_id, 2000)?;
+    let actor = context.actor(&object_id);
+
+    assert_eq!(actor.removed_time(), Some(ms(1500)));
+    assert_eq!(actor.object().mass(), MASS);
+    assert_eq!(actor.mass_at(ms(500 - STEP)), MASS);
+    assert_eq!(actor.mass_at(ms(500)), 5.0);
+    assert_eq!(actor.mass_at(ms(1000 - STEP)), 5.0);
+    assert_eq!(actor.mass_at(ms(1000)), 7.0);
+
+    assert_eq!(
+        session.range_times(grandchild_id, object_id, 0, 2000, 0)?,
+        times(0, 1500, STEP)
+    );
+
+    // The overrides don't affect the parent.
+    let context = session.update(main_layer_id, 2000)?;
+    let actor = context.actor(&object_id);
+
+    assert_eq!(actor.removed_time(), None);
+    assert_eq!(actor.mass_at(ms(2000)), MASS);
+
+    let main_times = session.range_times(main_layer_id, object_id, 0, 2000, 0)?;
+    assert!(*main_times.last().unwrap() >= 2000);
+    assert_continuous(&main_times, STEP);
+
+    Ok(())
+}
+
+fn merge_layer_keeps_the_overrides_start_time_and_drops_the_stale_tracks(
+    session: TestSession,
+) -> Result<()> {
+    let main_layer_id = session.main_layer_id;
+
+    let branched_id = session.add_object(main_layer_id, "branched", STEP, 0.0, 0)?;
+    let inherited_id = session.add_object(main_layer_id, "inherited", STEP, 100.0, 0)?;
+
+    session.add_track(main_layer_id, branched_id, STEP, STEP, 2000)?;
+    session.add_track(main_layer_id, inherited_id, STEP, STEP, 2000)?;
+
+    let child_id = session.add_layer(main_layer_id, "child", 500)?;
+    session.set_override(child_id, branched_id, Some(5.0), None)?;
+    session.add_track(child_id, branched_id, STEP, 500, 2000)?;
+
+    // The parent's track of the branched object is replaced from the child's first location,
+    // the one of the object without locations in the child is dropped after the child's start.
+    let replaced_locations = session.merge_layer(child_id)?;
+    assert_eq!(
+        replaced_locations,
+        (times(500, 2000, STEP).len() + times(500 + STEP, 2000, STEP).len()) as i64
+    );
+
+    let locations = session.range_locations(main_layer_id, branched_id, 0, 2000, 0)?;
+    let branched_times: Vec<_> = locations.iter().map(|(t, _)| *t).collect();
+    assert_eq!(branched_times, times(0, 2000, STEP));
+
+    for (t, x) in locations {
+        let expected_layer_id = if t < 500 { main_layer_id } else { child_id };
+        assert_eq!(x, expected_layer_id as Coord, "wrong layer at {}", t);
+    }
+
+    assert_eq!(
+        session.range_times(main_layer_id, inherited_id, 0, 2000, 0)?,
+        times(0, 500, STEP)
+    );
+
+    // The override still applies from the child's start, not from the main layer's one.
+    let context = session.update(main_layer_id, 2000)?;
+    let actor = context.actor(&branched_id);
+
+    assert_eq!(actor.mass_at(ms(500 - STEP)), MASS);
+    assert_eq!(actor.mass_at(ms(500)), 5.0);
+
+    // The dropped track is recomputed.
+    let inherited_times = session.range_times(main_layer_id, inherited_id, 0, 2000, 0)?;
+    assert!(*inherited_times.last().unwrap() >= 2000);
+    assert_continuous(&inherited_times, STEP);
+
+    Ok(())
+}
+
+fn bookmarks_are_listed_and_removed_across_the_session_layers(session: TestSession) -> Result<()> {
+    let main_layer_id = session.main_layer_id;
+    let session_id = session.session_id;
+
+    let left_id = session.add_layer(main_layer_id, "left", 500)?;
+    let right_id = session.add_layer(main_layer_id, "right", 500)?;
+
+    transaction! {
+        session.storage_mgr => t {
+            t.bookmark().add(session_id, left_id, &"impact".to_string(), ms(1000))?;
+            t.bookmark().add(session_id, main_layer_id, &"start".to_string(), ms(0))?;
+        }
+    }
+
+    // The names are unique inside the session, so a sibling's bookmark is listed too.
+    let names = |session: &TestSession| -> Result<Vec<String>> {
+        let bookmarks;
+
+        transaction! {
+            session.storage_mgr => t {
+                bookmarks = t.bookmark().get_list(session_id)?;
+            }
+        }
+
+        Ok(bookmarks
+            .into_iter()
+            .map(|bookmark| bookmark.name)
+            .collect())
+    };
+
+    assert_eq!(names(&session)?, vec!["start", "impact"]);
+
+    let add_to_sibling = || -> Result<()> {
+        transaction! {
+            session.storage_mgr => t {
+                t.bookmark().add(session_id, right_id, &"impact".to_string(), ms(1500))?;
+            }
+        }
+
+        Ok(())
+    };
+    assert!(add_to_sibling().is_err());
+
+    transaction! {
+        session.storage_mgr => t {
+            t.bookmark().remove(session_id, &"impact".to_string())?;
+        }
+    }
+
+    assert_eq!(names(&session)?, vec!["start"]);
+
+    Ok(())
+}
+
+fn add_layer_before_the_active_one_attaches_to_the_ancestor(session: TestSession) -> Result<()> {
+    let main_layer_id = session.main_layer_id;
+
+    let inherited_id = session.add_object(main_layer_id, "inherited", STEP, 0.0, 0)?;
+    session.add_track(main_layer_id, inherited_id, STEP, STEP, 2000)?;
+
+    let child_id = session.add_layer(main_layer_id, "child", 1000)?;
+    let own_id = session.add_object(child_id, "own", STEP, 100.0, 1000)?;
+
+    // The new layer starts before the active one, so the child's objects aren't inherited.
+    let early_id = session.add_layer(child_id, "early", 500)?;
+
+    let parent_id;
+    let ancestors;
+
+    transaction! {
+        session.storage_mgr => t {
+            parent_id = t.layer().get_parent_id(early_id)?;
+            ancestors = t.layer().layer_ancestors(early_id)?;
+        }
+    }
+
+    assert_eq!(parent_id, Some(main_layer_id));
+    assert!(!ancestors.contains(&child_id));
+
+    let context = session.update(early_id, 2000)?;
+    assert!(context.actors().contains_key(&inherited_id));
+    assert!(!context.actors().contains_key(&own_id));
+
+    // At the active layer's start the new layer is attached to the active one.
+    let late_id = session.add_layer(child_id, "late", 1000)?;
+
+    let late_parent_id;
+
+    transaction! {
+        session.storage_mgr => t {
+            late_parent_id = t.layer().get_parent_id(late_id)?;
+        }
+    }
+
+    assert_eq!(late_parent_id, Some(child_id));
+
+    Ok(())
+}
+
+backend_tests![
+    range_locations_extend_the_range_by_the_object_step,
+    range_locations_switch_to_the_descendant_track_at_its_first_location,
+    range_locations_stop_at_the_inherited_removal_time,
+    update_content_extends_the_inherited_tracks_in_the_active_layer,
+    update_content_applies_the_overrides_from_the_overriding_layer,
+    merge_layer_keeps_the_overrides_start_time_and_drops_the_stale_tracks,
+    bookmarks_are_listed_and_removed_across_the_session_layers,
+    add_layer_before_the_active_one_attaches_to_the_ancestor,
+];
@@ -25,6 +25,8 @@ pub type LayerId = i32;
 pub type ObjectName = String;
 pub type ObjectId = i64;
 pub type LocationId = i64;
+pub type BreakpointId = usize;
+pub type BookmarkName = String;
 
 const DAYS_IN_WEEK: RawTime = 7;
 const HOURS_IN_DAY: RawTime = 24;
@@ -92,10 +94,81 @@ pub struct SessionInfo {
     pub is_locked: bool,
 }
 
+#[derive(Debug, Clone)]
+pub struct BookmarkInfo {
+    pub name: BookmarkName,
+    pub layer_name: LayerName,
+    pub time: chrono::Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct LayerInfo {
+    pub id: LayerId,
+    pub parent_id: Option<LayerId>,
+    pub name: LayerName,
+    pub start_time: chrono::Duration,
+    pub objects_count: i64,
+    /// The last time of the locations computed in the layer.
+    pub last_computed_time: Option<chrono::Duration>,
+    pub is_active: bool,
+    pub is_current: bool,
+}
+
+/// Changes made by merging a layer into its parent.
+#[derive(Debug, Clone)]
+pub struct LayerMergeInfo {
+    pub parent_name: LayerName,
+    pub start_time: chrono::Duration,
+    pub replaced_locations: i64,
+    pub moved_locations: i64,
+    pub moved_objects: Vec<ObjectName>,
+    pub overridden_objects: Vec<ObjectName>,
+    pub reparented_layers: Vec<LayerName>,
+    /// The parent's other children branched after a replaced location,
+    /// they inherit the replaced tracks so the merge is refused.
+    pub conflicting_layers: Vec<LayerName>,
+}
+
+/// Result of copying a layer (and its descendants) into a session.
+#[derive(Debug, Clone)]
+pub struct LayerCopyInfo {
+    /// (source name, new name)
+    pub layers: Vec<(LayerName, LayerName)>,
+    /// (source name, new name) of the objects which names were already taken.
+    pub renamed_objects: Vec<(ObjectName, ObjectName)>,
+    pub copied_objects: usize,
+    pub copied_locations: i64,
+    /// Inherited objects which tracks can't be copied into another session.
+    pub skipped_objects: Vec<ObjectName>,
+}
+
+/// A structural operation which can be undone.
+#[derive(Debug, Clone)]
+pub enum JournalOperation {
+    RemoveLayer(LayerId, LayerName),
+    RenameLayer(LayerId, LayerName, LayerName),
+    DeleteSession(SessionId, SessionName),
+}
+
+impl fmt::Display for JournalOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JournalOperation::RemoveLayer(_, name) => write!(f, "rm-layer \"{}\"", name),
+            JournalOperation::RenameLayer(_, old_name, new_name) => {
+                write!(f, "rename-layer \"{}\" -> \"{}\"", old_name, new_name)
+            }
+            JournalOperation::DeleteSession(_, name) => write!(f, "delete-session \"{}\"", name),
+        }
+    }
+}
+
 pub enum TimeFormat {
     VirtualTimeLong(chrono::Duration),
     VirtualTimeShort(chrono::Duration),
     FrameDelta(chrono::Duration),
+
+    /// Wall-clock duration, e.g. an estimated time of a computation.
+    RealTime(chrono::Duration),
 }
 
 impl fmt::Display for TimeFormat {
@@ -151,6 +224,24 @@ impl fmt::Display for TimeFormat {
                 Ok(())
             }
             TimeFormat::FrameDelta(time) => write!(f, "{}ms", time.num_milliseconds()),
+            TimeFormat::RealTime(time) => {
+                if time.num_seconds() < 1 {
+                    return write!(f, "<1s");
+                }
+
+                let hours = time.num_hours();
+                let minutes = time.num_minutes() % MINS_IN_HOUR;
+
+                if hours != 0 {
+                    write!(f, "{}h ", hours)?;
+                }
+
+                if hours != 0 || minutes != 0 {
+                    write!(f, "{}m ", minutes)?;
+                }
+
+                write!(f, "{}s", time.num_seconds() % SECS_IN_MIN)
+            }
         }
     }
 }